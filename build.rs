@@ -0,0 +1,37 @@
+//! Embeds the git commit being built as `DSC_GIT_COMMIT`, for the reproducibility block of
+//! every report. Builds outside a git checkout, such as from a published crate, leave it
+//! unset, even when unpacked inside some other repository.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let git_dir = Path::new(".git");
+    if !git_dir.exists() {
+        return;
+    }
+
+    // The commit changes with HEAD, or with the branch HEAD names when committing. Only files
+    // that exist are watched, since Cargo reruns the script every build for a missing one.
+    let mut watched = vec![git_dir.join("HEAD"), git_dir.join("packed-refs")];
+    if let Ok(head) = fs::read_to_string(git_dir.join("HEAD")) {
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            watched.push(git_dir.join(branch));
+        }
+    }
+    for path in watched.iter().filter(|path| path.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    let commit = Command::new("git")
+        .args(["--git-dir=.git", "rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=DSC_GIT_COMMIT={}", commit.trim());
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::{self, Display};
+use std::io::{self, ErrorKind, Read};
+use std::str::FromStr;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// A hash algorithm that published digit files commonly ship sidecar checksums for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    fn hex_len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 => 64,
+            ChecksumAlgorithm::Md5 => 32,
+        }
+    }
+
+    /// Returns the sidecar file extension for this algorithm, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Md5 => "md5",
+        }
+    }
+}
+
+impl Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// A checksum the input is expected to have, with its digest as lowercase hex.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedChecksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub hex: String,
+}
+
+impl ExpectedChecksum {
+    /// Builds a checksum from a hex digest, taking the algorithm from the digest's length.
+    pub fn from_hex(hex: &str) -> Result<ExpectedChecksum, String> {
+        let algorithm = [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5]
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.hex_len() == hex.len());
+        match algorithm {
+            Some(algorithm) if hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+                Ok(ExpectedChecksum {
+                    algorithm,
+                    hex: hex.to_ascii_lowercase(),
+                })
+            }
+            _ => Err(format!(
+                "{:?} is not a 64-digit SHA-256 or 32-digit MD5 hex digest",
+                hex
+            )),
+        }
+    }
+
+    /// Finds the checksum for `file_name` in the contents of a sidecar file in the
+    /// `HASH  filename` format of `sha256sum` and `md5sum`, where `*` may mark binary mode
+    /// and a lone hash applies to any file. When the sidecar lists several files, the line
+    /// naming `file_name` is used.
+    pub fn from_sidecar(contents: &str, file_name: &str) -> Result<ExpectedChecksum, String> {
+        let entries = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.find(char::is_whitespace) {
+                Some(space) => (&line[..space], Some(line[space..].trim_start())),
+                None => (line, None),
+            })
+            .map(|(hex, name)| (hex, name.map(|name| name.strip_prefix('*').unwrap_or(name))))
+            .collect::<Vec<_>>();
+        let entry = match entries.as_slice() {
+            [] => return Err(String::from("the checksum file has no entries")),
+            [entry] => *entry,
+            _ => match entries.iter().find(|(_, name)| {
+                name.is_some_and(|name| {
+                    name == file_name || name.rsplit(['/', '\\']).next() == Some(file_name)
+                })
+            }) {
+                Some(entry) => *entry,
+                None => return Err(format!("the checksum file has no entry for {}", file_name)),
+            },
+        };
+        ExpectedChecksum::from_hex(entry.0)
+    }
+}
+
+impl FromStr for ExpectedChecksum {
+    type Err = String;
+
+    /// Parses `sha256:HEX` or `md5:HEX`.
+    fn from_str(s: &str) -> Result<ExpectedChecksum, String> {
+        let (algorithm, hex) = match s.split_once(':') {
+            Some(("sha256", hex)) => (ChecksumAlgorithm::Sha256, hex),
+            Some(("md5", hex)) => (ChecksumAlgorithm::Md5, hex),
+            _ => return Err(format!("expected sha256:HEX or md5:HEX, not {:?}", s)),
+        };
+        match ExpectedChecksum::from_hex(hex) {
+            Ok(expected) if expected.algorithm == algorithm => Ok(expected),
+            _ => Err(format!("{:?} is not a {} hex digest", hex, algorithm)),
+        }
+    }
+}
+
+impl Display for ExpectedChecksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+/// An in-progress checksum of bytes fed to it.
+#[derive(Clone, Debug)]
+pub(crate) enum ChecksumHasher {
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl ChecksumHasher {
+    pub(crate) fn new(algorithm: ChecksumAlgorithm) -> ChecksumHasher {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Md5 => ChecksumHasher::Md5(Md5::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hasher.update(bytes),
+            ChecksumHasher::Md5(hasher) => hasher.update(bytes),
+        }
+    }
+
+    /// Returns the digest so far as lowercase hex.
+    pub(crate) fn hex(&self) -> String {
+        let digest = match self {
+            ChecksumHasher::Sha256(hasher) => hasher.clone().finalize().to_vec(),
+            ChecksumHasher::Md5(hasher) => hasher.clone().finalize().to_vec(),
+        };
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// Reads `reader` to the end and returns its digest as lowercase hex.
+pub fn checksum_of<R: Read>(mut reader: R, algorithm: ChecksumAlgorithm) -> io::Result<String> {
+    let mut hasher = ChecksumHasher::new(algorithm);
+    let mut buffer = vec![0; 65536];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.hex()),
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/pi_2000.txt");
+    const PI_SIDECAR: &str = "\
+e8b47004670d0934ae79bd51e995a9fb8d48f9228049fc6c46c568aa52f31d25  other.txt
+E8B47004670D0934AE79BD51E995A9FB8D48F9228049FC6C46C568AA52F31D25 *fixtures/pi_2000.txt
+";
+
+    fn expected() -> ExpectedChecksum {
+        ExpectedChecksum::from_sidecar(PI_SIDECAR, "pi_2000.txt").unwrap()
+    }
+
+    #[test]
+    fn matching_fixture_verifies() {
+        let expected = expected();
+        assert_eq!(expected.algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(
+            checksum_of(PI_FIXTURE, expected.algorithm).unwrap(),
+            expected.hex
+        );
+        assert_eq!(
+            checksum_of(PI_FIXTURE, ChecksumAlgorithm::Md5).unwrap(),
+            "83b804fdd4b00443c183ddd7910fb2dd"
+        );
+    }
+
+    #[test]
+    fn corrupted_fixture_does_not_verify() {
+        let mut corrupted = PI_FIXTURE.to_vec();
+        corrupted[1000] = if corrupted[1000] == b'0' { b'1' } else { b'0' };
+        let expected = expected();
+        assert_ne!(
+            checksum_of(&corrupted[..], expected.algorithm).unwrap(),
+            expected.hex
+        );
+        assert_ne!(
+            checksum_of(&PI_FIXTURE[..PI_FIXTURE.len() - 1], expected.algorithm).unwrap(),
+            expected.hex
+        );
+    }
+
+    #[test]
+    fn sidecar_without_the_file_is_an_error() {
+        assert!(ExpectedChecksum::from_sidecar(PI_SIDECAR, "e_2000.txt").is_err());
+        assert!(ExpectedChecksum::from_sidecar("# nothing\n\n", "pi_2000.txt").is_err());
+    }
+
+    #[test]
+    fn spec_must_name_the_right_algorithm() {
+        let md5 = "md5:83b804fdd4b00443c183ddd7910fb2dd";
+        assert_eq!(md5.parse::<ExpectedChecksum>().unwrap().to_string(), md5);
+        assert!("sha256:83b804fdd4b00443c183ddd7910fb2dd"
+            .parse::<ExpectedChecksum>()
+            .is_err());
+        assert!("md5:83b804fdd4b00443c183ddd7910fb2dz"
+            .parse::<ExpectedChecksum>()
+            .is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::convert::TryFrom;
+
+use crate::hyperloglog;
+
+/// A count-min sketch of sequence counts: `depth` rows of `width` counters, each row hashing
+/// a sequence index to one of its counters. A count's estimate is the smallest of its
+/// counters, which never underestimates and overestimates by at most `e / width` of the
+/// total with probability at least `1 - e^-depth`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct CountMin {
+    width: usize,
+    depth: usize,
+    counters: Vec<u64>,
+    total: u128,
+}
+
+impl CountMin {
+    pub(crate) const DEPTHS: std::ops::RangeInclusive<usize> = 1..=16;
+
+    pub(crate) fn new(width: usize, depth: usize) -> CountMin {
+        if width == 0 || !CountMin::DEPTHS.contains(&depth) {
+            panic!(
+                "Cannot create a count-min sketch {} wide and {} deep; it needs a width of at least 1 and a depth of {} to {}.",
+                width,
+                depth,
+                CountMin::DEPTHS.start(),
+                CountMin::DEPTHS.end()
+            );
+        }
+        CountMin {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            total: 0,
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub(crate) fn total(&self) -> u128 {
+        self.total
+    }
+
+    /// Returns the counter `index` uses in `row`, with a differently seeded hash per row.
+    fn slot(&self, row: usize, index: usize) -> usize {
+        let seed = (row as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        row * self.width + (hyperloglog::mix(index as u64 ^ seed) % self.width as u64) as usize
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, count: u128) {
+        self.total += count;
+        let count = u64::try_from(count).unwrap_or(u64::MAX);
+        for row in 0..self.depth {
+            let slot = self.slot(row, index);
+            self.counters[slot] = self.counters[slot].saturating_add(count);
+        }
+    }
+
+    pub(crate) fn estimate(&self, index: usize) -> u128 {
+        (0..self.depth)
+            .map(|row| self.counters[self.slot(row, index)])
+            .min()
+            .unwrap_or(0) as u128
+    }
+
+    /// Merges another sketch of the same dimensions into this one, as if its inputs had been
+    /// inserted here.
+    pub(crate) fn merge(&mut self, other: &CountMin) {
+        if (self.width, self.depth) != (other.width, other.depth) {
+            panic!("Cannot merge count-min sketches of different dimensions.");
+        }
+        self.total += other.total;
+        // Saturating sums of counts clamp to the same value in any order, so sketches merged
+        // from chunks don't depend on which thread counted which chunk.
+        self.counters
+            .iter_mut()
+            .zip(&other.counters)
+            .for_each(|(counter, &other)| *counter = counter.saturating_add(other));
+    }
+
+    /// Returns how far any estimate can exceed its true count, `e / width` of the total, and
+    /// the probability that a given estimate stays within it, `1 - e^-depth`.
+    pub(crate) fn error_bound(&self) -> (f64, f64) {
+        (
+            std::f64::consts::E / self.width as f64 * self.total as f64,
+            1.0 - (-(self.depth as f64)).exp(),
+        )
+    }
+
+    pub(crate) fn bytes(width: usize, depth: usize) -> usize {
+        width
+            .saturating_mul(depth)
+            .saturating_mul(std::mem::size_of::<u64>())
+    }
+}
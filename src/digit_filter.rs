@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// What counting does with one input byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteAction {
+    /// Counts a digit with this value, from 0 to 15.
+    Emit(u8),
+    /// Drops the byte, so it neither counts nor breaks a sequence.
+    Skip,
+    /// Breaks the sequence, as any byte that isn't a digit does by default.
+    Reset,
+    /// Ends the input at this byte.
+    Stop,
+    /// Fails the count at this byte.
+    Error,
+}
+
+impl Display for ByteAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ByteAction::Emit(digit) => write!(f, "mapped to {:X}", digit),
+            ByteAction::Skip => f.write_str("skipped"),
+            ByteAction::Reset => f.write_str("a sequence break"),
+            ByteAction::Stop => f.write_str("the end of the input"),
+            ByteAction::Error => f.write_str("an error"),
+        }
+    }
+}
+
+/// A compiled table of what to do with each of the 256 byte values, looked up once per
+/// byte before the counters see it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitFilter {
+    actions: [ByteAction; 256],
+}
+
+/// The default classification: `0`-`9`, `A`-`F`, and `a`-`f` are digits and every other
+/// byte breaks the sequence.
+pub const HEX: DigitFilter = DigitFilter::hex();
+
+impl DigitFilter {
+    const fn hex() -> DigitFilter {
+        let mut actions = [ByteAction::Reset; 256];
+        let mut byte = 0;
+        while byte < 256 {
+            let character = byte as u8;
+            actions[byte] = match character {
+                b'0'..=b'9' => ByteAction::Emit(character - b'0'),
+                b'A'..=b'F' => ByteAction::Emit(character - b'A' + 10),
+                b'a'..=b'f' => ByteAction::Emit(character - b'a' + 10),
+                _ => ByteAction::Reset,
+            };
+            byte += 1;
+        }
+        DigitFilter { actions }
+    }
+
+    pub fn builder() -> DigitFilterBuilder {
+        DigitFilterBuilder::default()
+    }
+
+    #[inline]
+    pub fn action(&self, byte: u8) -> ByteAction {
+        self.actions[byte as usize]
+    }
+}
+
+/// Two rules of a `DigitFilterBuilder` that give one byte different actions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilterConflict {
+    pub byte: u8,
+    pub first: ByteAction,
+    pub second: ByteAction,
+}
+
+impl Display for FilterConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "byte {:?} is both {} and {}",
+            self.byte as char, self.first, self.second
+        )
+    }
+}
+
+impl Error for FilterConflict {}
+
+/// Rules for the bytes that shouldn't be classified as `HEX` does, compiled into a
+/// `DigitFilter` by `build`. Bytes without a rule keep their `HEX` action. Giving a byte the
+/// same action twice is harmless, but giving it two different actions is a conflict, since
+/// either order of applying them would be a surprise.
+///
+/// ```
+/// use digit_sequence_counter::digit_filter::{self, ByteAction, DigitFilter};
+///
+/// let filter = DigitFilter::builder()
+///     .skip(b" \n")
+///     .map(b'O', 0)
+///     .map(b'l', 1)
+///     .stop_at(b"e")
+///     .error_on(b"\0")
+///     .build()
+///     .unwrap();
+///
+/// // The table matches applying each rule in turn to the default classification.
+/// for byte in 0..=255 {
+///     let mut expected = digit_filter::HEX.action(byte);
+///     if b" \n".contains(&byte) {
+///         expected = ByteAction::Skip;
+///     }
+///     match byte {
+///         b'O' => expected = ByteAction::Emit(0),
+///         b'l' => expected = ByteAction::Emit(1),
+///         b'e' => expected = ByteAction::Stop,
+///         0 => expected = ByteAction::Error,
+///         _ => {}
+///     }
+///     assert_eq!(filter.action(byte), expected);
+/// }
+///
+/// let conflict = DigitFilter::builder().skip(b",").map(b',', 3).build().unwrap_err();
+/// assert_eq!(conflict.to_string(), "byte ',' is both skipped and mapped to 3");
+/// assert!(DigitFilter::builder().skip(b"  ").build().is_ok());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigitFilterBuilder {
+    rules: Vec<(u8, ByteAction)>,
+}
+
+impl DigitFilterBuilder {
+    /// Drops `bytes`, like the separators of a wrapped digit file.
+    pub fn skip(self, bytes: &[u8]) -> DigitFilterBuilder {
+        self.rules(bytes, ByteAction::Skip)
+    }
+
+    /// Reads `byte` as a digit of value `digit`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digit` is greater than 15.
+    pub fn map(self, byte: u8, digit: u8) -> DigitFilterBuilder {
+        if digit > 15 {
+            panic!("Cannot map a byte to the digit value {}.", digit);
+        }
+        self.rules(&[byte], ByteAction::Emit(digit))
+    }
+
+    /// Breaks the sequence at `bytes`, even digits.
+    pub fn reset(self, bytes: &[u8]) -> DigitFilterBuilder {
+        self.rules(bytes, ByteAction::Reset)
+    }
+
+    /// Ends the input at the first of `bytes`.
+    pub fn stop_at(self, bytes: &[u8]) -> DigitFilterBuilder {
+        self.rules(bytes, ByteAction::Stop)
+    }
+
+    /// Fails the count at the first of `bytes`.
+    pub fn error_on(self, bytes: &[u8]) -> DigitFilterBuilder {
+        self.rules(bytes, ByteAction::Error)
+    }
+
+    fn rules(mut self, bytes: &[u8], action: ByteAction) -> DigitFilterBuilder {
+        self.rules.extend(bytes.iter().map(|&byte| (byte, action)));
+        self
+    }
+
+    pub fn build(&self) -> Result<DigitFilter, FilterConflict> {
+        let mut filter = HEX;
+        let mut ruled = [None; 256];
+        for &(byte, action) in &self.rules {
+            match ruled[byte as usize] {
+                Some(first) if first != action => {
+                    return Err(FilterConflict {
+                        byte,
+                        first,
+                        second: action,
+                    })
+                }
+                _ => ruled[byte as usize] = Some(action),
+            }
+            filter.actions[byte as usize] = action;
+        }
+        Ok(filter)
+    }
+}
@@ -0,0 +1,134 @@
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::DigitSequenceCounter;
+
+/// Why `downsample` couldn't scale a counter's counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DownsampleError {
+    /// Downsampling only scales counts down, so the target must be at most the total count.
+    TargetExceedsSource,
+}
+
+impl Display for DownsampleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DownsampleError::TargetExceedsSource => {
+                write!(f, "the target total is greater than the total count")
+            }
+        }
+    }
+}
+
+impl Error for DownsampleError {}
+
+impl DigitSequenceCounter {
+    /// Returns a copy of this counter with its counts scaled to sum to exactly
+    /// `target_total`, so counters of different inputs can be compared at the same total.
+    /// Each count becomes `count * target_total / total_count()` rounded down, and the counts
+    /// left short are then rounded up in order of the largest remainder, lower sequence
+    /// indices first among equal remainders.
+    ///
+    /// ```
+    /// use digit_sequence_counter::downsample::DownsampleError;
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x3141592653589793238462643383279".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// for target_total in 0..=counter.total_count() {
+    ///     let downsampled = counter.downsample(target_total).unwrap();
+    ///     assert_eq!(downsampled.total_count(), target_total);
+    ///     for (sequence_index, count) in downsampled.nonzero_counts() {
+    ///         let exact = counter.count_at(sequence_index) as f64 * target_total as f64
+    ///             / counter.total_count() as f64;
+    ///         assert!((count as f64 - exact).abs() < 1.0);
+    ///     }
+    /// }
+    ///
+    /// // Each of 1, 3, and 4 is a third of the counts, so the lowest index takes the extra one.
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x1331144".iter().for_each(|&byte| counter.process_character(byte));
+    /// let downsampled = counter.downsample(4).unwrap();
+    /// assert_eq!(downsampled.nonzero_counts(), vec![(1, 2), (3, 1), (4, 1)]);
+    ///
+    /// let mut huge = counter.clone();
+    /// huge.apply_transform(|_, count| count * (u128::MAX / 8));
+    /// let downsampled = huge.downsample(huge.total_count() - 1).unwrap();
+    /// assert_eq!(downsampled.total_count(), huge.total_count() - 1);
+    /// assert_eq!(downsampled.count_at(4), huge.count_at(4) - 1);
+    ///
+    /// assert_eq!(
+    ///     counter.downsample(7).unwrap_err(),
+    ///     DownsampleError::TargetExceedsSource
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn downsample(&self, target_total: u128) -> Result<DigitSequenceCounter, DownsampleError> {
+        let total_count = self.total_count();
+        if target_total > total_count {
+            return Err(DownsampleError::TargetExceedsSource);
+        }
+        let mut downsampled = self
+            .nonzero_counts()
+            .into_iter()
+            .map(|(sequence_index, count)| {
+                let (scaled, remainder) = mul_div(count, target_total, total_count);
+                (sequence_index, scaled, remainder)
+            })
+            .collect::<Vec<_>>();
+
+        let short = target_total
+            - downsampled
+                .iter()
+                .map(|&(_, scaled, _)| scaled)
+                .sum::<u128>();
+        let mut by_remainder = (0..downsampled.len()).collect::<Vec<_>>();
+        by_remainder.sort_by_key(|&entry| (Reverse(downsampled[entry].2), entry));
+        for &entry in &by_remainder[..short as usize] {
+            downsampled[entry].1 += 1;
+        }
+
+        let mut counter = self.clone();
+        counter.sequence_counts.update_stored(|sequence_index, _| {
+            downsampled
+                .binary_search_by_key(&sequence_index, |&(sequence_index, _, _)| sequence_index)
+                .map_or(0, |entry| downsampled[entry].1)
+        });
+        counter.rebuild_seen();
+        Ok(counter)
+    }
+}
+
+/// Returns the quotient and remainder of `a * b / d` for `a` and `b` at most `d`, without
+/// overflowing when the product doesn't fit in a `u128`.
+fn mul_div(a: u128, b: u128, d: u128) -> (u128, u128) {
+    if let Some(product) = a.checked_mul(b) {
+        return (product / d, product % d);
+    }
+    // Multiplies bit by bit from the top of `b`, keeping `a * (b's bits so far)` as
+    // `quotient * d + remainder` with the remainder below `d`.
+    let (mut quotient, mut remainder) = (0u128, 0u128);
+    for bit in (0..128 - b.leading_zeros()).rev() {
+        quotient <<= 1;
+        if remainder >= d - remainder {
+            remainder -= d - remainder;
+            quotient += 1;
+        } else {
+            remainder <<= 1;
+        }
+        if b >> bit & 1 == 1 {
+            if remainder >= d - a {
+                remainder -= d - a;
+                quotient += 1;
+            } else {
+                remainder += a;
+            }
+        }
+    }
+    (quotient, remainder)
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+
+use crate::notation::SequenceNotation;
+use crate::{DigitSequenceCounter, StorageMode};
+
+const NEVER: u64 = u64::MAX;
+
+/// The longest stretch between two consecutive occurrences of one sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Drought {
+    pub sequence_index: usize,
+    /// Position of the occurrence that starts the drought.
+    pub start: u64,
+    /// Position of the occurrence that ends it.
+    pub end: u64,
+}
+
+impl Drought {
+    pub fn gap(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// Tracks, for every sequence of one length, where it was last seen and its longest gap
+/// between consecutive occurrences. Positions count every byte fed in, starting at 0, and
+/// an occurrence is placed at its window's last byte.
+///
+/// This needs three `u64`s per possible sequence, so it is only practical for short lengths.
+#[derive(Debug)]
+pub struct DroughtTracker {
+    window: DigitSequenceCounter,
+    position: u64,
+    last_seen: Vec<u64>,
+    longest_start: Vec<u64>,
+    longest_gap: Vec<u64>,
+}
+
+impl DroughtTracker {
+    /// The longest sequence length tracked without an explicit override.
+    pub const DEFAULT_LENGTH_LIMIT: usize = 7;
+
+    pub fn new(sequence_length: usize) -> DroughtTracker {
+        let window = DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse);
+        let possible_sequences = window.possible_sequences();
+        DroughtTracker {
+            window,
+            position: 0,
+            last_seen: vec![NEVER; possible_sequences],
+            longest_start: vec![NEVER; possible_sequences],
+            longest_gap: vec![0; possible_sequences],
+        }
+    }
+
+    pub fn process_character(&mut self, character: u8) {
+        if let Some(sequence_index) = self.window.advance_window(character) {
+            let last_seen = self.last_seen[sequence_index];
+            if last_seen != NEVER {
+                let gap = self.position - last_seen;
+                if gap > self.longest_gap[sequence_index] {
+                    self.longest_gap[sequence_index] = gap;
+                    self.longest_start[sequence_index] = last_seen;
+                }
+            }
+            self.last_seen[sequence_index] = self.position;
+        }
+        self.position += 1;
+    }
+
+    pub fn sequence_length(&self) -> usize {
+        self.window.sequence_length()
+    }
+
+    /// Returns the `count` sequences with the longest droughts, longest first, with ties
+    /// broken by sequence index. Sequences seen fewer than twice have no drought.
+    pub fn longest_droughts(&self, count: usize) -> Vec<Drought> {
+        let mut droughts = self
+            .longest_start
+            .iter()
+            .zip(&self.longest_gap)
+            .enumerate()
+            .filter(|&(_, (&start, _))| start != NEVER)
+            .map(|(sequence_index, (&start, &gap))| Drought {
+                sequence_index,
+                start,
+                end: start + gap,
+            })
+            .collect::<Vec<_>>();
+        droughts.sort_by(|a, b| {
+            b.gap()
+                .cmp(&a.gap())
+                .then(a.sequence_index.cmp(&b.sequence_index))
+        });
+        droughts.truncate(count);
+        droughts
+    }
+
+    pub fn sequence_label(&self, sequence_index: usize) -> String {
+        self.window.sequence_label(sequence_index)
+    }
+
+    /// Sets how `sequence_label` and `write_longest` write sequences.
+    pub fn set_sequence_notation(&mut self, sequence_notation: SequenceNotation) {
+        self.window.set_sequence_notation(sequence_notation);
+    }
+
+    /// Writes a heading and then the `count` longest droughts, one per line as
+    /// `sequence start end gap`.
+    pub fn write_longest<W: Write>(&self, out: &mut W, count: usize) -> io::Result<()> {
+        writeln!(
+            out,
+            "Longest droughts for length {}:",
+            self.sequence_length()
+        )?;
+        for drought in self.longest_droughts(count) {
+            writeln!(
+                out,
+                "{} {} {} {}",
+                self.sequence_label(drought.sequence_index),
+                drought.start,
+                drought.end,
+                drought.gap()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(sequence_length: usize, input: &[u8]) -> DroughtTracker {
+        let mut tracker = DroughtTracker::new(sequence_length);
+        input
+            .iter()
+            .for_each(|&byte| tracker.process_character(byte));
+        tracker
+    }
+
+    #[test]
+    fn planted_drought_is_found() {
+        // 12 ends at bytes 2, 10, and 14, so its longest drought runs from 2 to 10.
+        let tracker = tracker(2, b"912 000 912 912");
+        let droughts = tracker.longest_droughts(10);
+        assert_eq!(
+            droughts,
+            vec![Drought {
+                sequence_index: 0x12,
+                start: 2,
+                end: 10,
+            }]
+        );
+        assert_eq!(droughts[0].gap(), 8);
+    }
+
+    #[test]
+    fn sequence_seen_once_has_no_drought() {
+        let tracker = tracker(3, b"x0123456789");
+        assert!(tracker.longest_droughts(10).is_empty());
+    }
+
+    #[test]
+    fn ties_sort_by_sequence_index_and_truncate() {
+        // The x resets the window, so 3x3 makes no 33, and the window has to fill again
+        // before 91 at byte 8. 12 and 23 both have a drought of 7; 33 and 91 are seen once.
+        let tracker = tracker(2, b"91233x39123");
+        assert_eq!(
+            tracker.longest_droughts(10),
+            vec![
+                Drought {
+                    sequence_index: 0x12,
+                    start: 2,
+                    end: 9,
+                },
+                Drought {
+                    sequence_index: 0x23,
+                    start: 3,
+                    end: 10,
+                },
+            ]
+        );
+        assert_eq!(tracker.longest_droughts(1).len(), 1);
+        assert_eq!(tracker.longest_droughts(1)[0].sequence_index, 0x12);
+        assert!(tracker.longest_droughts(0).is_empty());
+    }
+
+    #[test]
+    fn longest_gap_replaces_shorter_ones() {
+        // The first digit of each run only fills the window, so 7 ends at 1, 2, 5, 7, 11,
+        // 12, 13, and 14, and 0 only at 6.
+        let tracker = tracker(1, b"077 0707 x07777");
+        let mut out = Vec::new();
+        tracker.write_longest(&mut out, 3).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Longest droughts for length 1:\n7 7 11 4\n"
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::notation::SequenceNotation;
+use crate::paths;
+use crate::reporter::{LengthSummary, Reporter, SequenceRecord};
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// Scratch space each counted window takes, as its packed sequence index.
+pub const BYTES_PER_WINDOW: u64 = 8;
+
+/// Scratch directories that still exist, so `remove_scratch_dirs` can clean up after a run
+/// that exits without unwinding.
+static SCRATCH_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Where and how `ExternalCounter`s keep their windows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalStorage {
+    /// The directory each counter makes its own scratch directory in.
+    pub scratch_dir: PathBuf,
+    /// Windows held in memory before they are sorted and spilled as a run, at
+    /// `BYTES_PER_WINDOW` each.
+    pub run_windows: usize,
+    /// The most runs merged at once. More runs than this are first merged in groups of this
+    /// many, so the open files stay within limits however many runs there are.
+    pub merge_fan_in: usize,
+}
+
+impl ExternalStorage {
+    /// 64 MiB of windows per run.
+    pub const DEFAULT_RUN_WINDOWS: usize = 1 << 23;
+    pub const DEFAULT_MERGE_FAN_IN: usize = 64;
+
+    pub fn new<P: Into<PathBuf>>(scratch_dir: P) -> ExternalStorage {
+        ExternalStorage {
+            scratch_dir: scratch_dir.into(),
+            run_windows: Self::DEFAULT_RUN_WINDOWS,
+            merge_fan_in: Self::DEFAULT_MERGE_FAN_IN,
+        }
+    }
+
+    /// Returns the most scratch space one counter can need for `input_bytes` of input, at
+    /// most one window per byte. A cascading merge holds a group's runs and their merged copy
+    /// at once, so that much again is added for the largest group.
+    ///
+    /// ```
+    /// use digit_sequence_counter::external::ExternalStorage;
+    ///
+    /// let mut storage = ExternalStorage::new("scratch");
+    /// storage.run_windows = 1000;
+    /// storage.merge_fan_in = 4;
+    /// assert_eq!(storage.scratch_bytes_needed(3000), 3000 * 8 * 2);
+    /// assert_eq!(storage.scratch_bytes_needed(10_000), 10_000 * 8 + 4000 * 8);
+    /// ```
+    pub fn scratch_bytes_needed(&self, input_bytes: u64) -> u64 {
+        let group_windows = (self.run_windows as u64).saturating_mul(self.merge_fan_in as u64);
+        input_bytes
+            .saturating_add(input_bytes.min(group_windows))
+            .saturating_mul(BYTES_PER_WINDOW)
+    }
+}
+
+/// Removes every scratch directory still in use, for a process about to exit without
+/// dropping its counters. Counters used afterward fail on their missing files.
+pub fn remove_scratch_dirs() {
+    let dirs = std::mem::take(
+        &mut *SCRATCH_DIRS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    for dir in dirs {
+        let _ = fs::remove_dir_all(paths::for_opening(&dir));
+    }
+}
+
+/// A directory of run files, removed with everything in it when dropped.
+#[derive(Debug)]
+struct ScratchDir {
+    path: PathBuf,
+    files_created: usize,
+}
+
+impl ScratchDir {
+    fn create(parent: &Path) -> io::Result<ScratchDir> {
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let path = parent.join(format!(
+                "dsc-external-{}-{}",
+                process::id(),
+                CREATED.fetch_add(1, Ordering::Relaxed)
+            ));
+            match fs::create_dir(paths::for_opening(&path)) {
+                Ok(()) => {
+                    SCRATCH_DIRS
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push(path.clone());
+                    return Ok(ScratchDir {
+                        path,
+                        files_created: 0,
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn new_file(&mut self) -> PathBuf {
+        self.files_created += 1;
+        self.path.join(format!("run-{}", self.files_created))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        SCRATCH_DIRS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .retain(|dir| *dir != self.path);
+        let _ = fs::remove_dir_all(paths::for_opening(&self.path));
+    }
+}
+
+/// Counts one sequence length exactly in bounded memory by writing every window's sequence
+/// index to disk instead of keeping a table: windows are buffered, sorted, and spilled as
+/// runs, and `finish` merges the runs into counts in index order. It takes
+/// `BYTES_PER_WINDOW` of scratch space per window, for lengths too long for a table where
+/// a sketch's estimates won't do.
+///
+/// ```
+/// use digit_sequence_counter::external::{ExternalCounter, ExternalStorage};
+/// use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+///
+/// let mut storage = ExternalStorage::new(std::env::temp_dir());
+/// // Tiny runs and merges make even this short input spill dozens of runs and merge them
+/// // in several rounds.
+/// storage.run_windows = 50;
+/// storage.merge_fan_in = 3;
+///
+/// let mut state = 7u64;
+/// let mut input = vec![b'x'];
+/// for _ in 0..2000 {
+///     state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+///     input.push(b"0123456789ABCDEF.x"[(state >> 59) as usize % 18]);
+/// }
+///
+/// let mut external = ExternalCounter::new(3, &storage).unwrap();
+/// let mut sparse = DigitSequenceCounter::with_storage(3, StorageMode::Sparse);
+/// for &byte in &input {
+///     external.process_character(byte).unwrap();
+///     sparse.process_character(byte);
+/// }
+/// assert!(external.scratch_bytes() > 0);
+///
+/// let counts = external.finish().unwrap();
+/// let merged = counts.counts().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(merged, sparse.nonzero_counts());
+/// assert_eq!(counts.total_count(), sparse.total_count());
+/// assert_eq!(counts.unique_count(), sparse.unique_count());
+/// assert_eq!(counts.most_common(), sparse.top_n(1).first().copied());
+/// ```
+#[derive(Debug)]
+pub struct ExternalCounter {
+    window: DigitSequenceCounter,
+    buffer: Vec<u64>,
+    run_windows: usize,
+    merge_fan_in: usize,
+    runs: Vec<PathBuf>,
+    scratch: ScratchDir,
+    total_count: u128,
+}
+
+impl ExternalCounter {
+    /// Creates a counter with its own scratch directory in `storage.scratch_dir`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `storage` holds no windows per run or merges fewer than two runs at once,
+    /// or if the sequence length is greater than `DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH`.
+    pub fn new(sequence_length: usize, storage: &ExternalStorage) -> io::Result<ExternalCounter> {
+        if storage.run_windows == 0 || storage.merge_fan_in < 2 {
+            panic!(
+                "Cannot count externally without windows per run and at least two runs per merge."
+            );
+        }
+        Ok(ExternalCounter {
+            // Only the window is used, so no counts are ever stored.
+            window: DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse),
+            buffer: Vec::with_capacity(storage.run_windows),
+            run_windows: storage.run_windows,
+            merge_fan_in: storage.merge_fan_in,
+            runs: Vec::new(),
+            scratch: ScratchDir::create(&storage.scratch_dir)?,
+            total_count: 0,
+        })
+    }
+
+    pub fn sequence_length(&self) -> usize {
+        self.window.sequence_length()
+    }
+
+    /// Counts `character` as `DigitSequenceCounter::process_character` does, failing only if
+    /// a full run can't be spilled.
+    pub fn process_character(&mut self, character: u8) -> io::Result<()> {
+        match self.window.advance_window(character) {
+            Some(sequence_index) => self.push(sequence_index),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a digit value that a `DigitFilter` already classified.
+    pub(crate) fn process_digit(&mut self, digit: u8) -> io::Result<()> {
+        if self.window.shift_in_digit(digit) {
+            self.push(self.window.current_sequence)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn reset_window(&mut self) {
+        self.window.reset_window();
+    }
+
+    fn push(&mut self, sequence_index: usize) -> io::Result<()> {
+        self.buffer.push(sequence_index as u64);
+        self.total_count += 1;
+        if self.buffer.len() == self.run_windows {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_unstable();
+        let path = self.scratch.new_file();
+        let mut out = BufWriter::new(File::create(paths::for_opening(&path))?);
+        for sequence_index in &self.buffer {
+            out.write_all(&sequence_index.to_le_bytes())?;
+        }
+        out.flush()?;
+        self.runs.push(path);
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Returns the windows counted so far, which is `total_count` of the finished counts.
+    pub fn total_count(&self) -> u128 {
+        self.total_count
+    }
+
+    /// Returns the scratch space the spilled runs take so far.
+    pub fn scratch_bytes(&self) -> u64 {
+        (self.total_count - self.buffer.len() as u128) as u64 * BYTES_PER_WINDOW
+    }
+
+    /// Spills the last run and merges runs in groups of `merge_fan_in` until that many or
+    /// fewer are left, then reads them through once for the summary counts.
+    pub fn finish(mut self) -> io::Result<ExternalCounts> {
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        self.buffer = Vec::new();
+        while self.runs.len() > self.merge_fan_in {
+            let group = self.runs.drain(..self.merge_fan_in).collect::<Vec<_>>();
+            let path = self.scratch.new_file();
+            let mut out = BufWriter::new(File::create(paths::for_opening(&path))?);
+            let mut merger = RunMerger::open(&group)?;
+            while let Some(sequence_index) = merger.next_index()? {
+                out.write_all(&sequence_index.to_le_bytes())?;
+            }
+            out.flush()?;
+            drop(merger);
+            for run in group {
+                fs::remove_file(paths::for_opening(&run))?;
+            }
+            self.runs.push(path);
+        }
+
+        let mut counts = ExternalCounts {
+            sequence_length: self.window.sequence_length(),
+            runs: self.runs,
+            _scratch: self.scratch,
+            total_count: self.total_count,
+            unique_count: 0,
+            most_common: None,
+            sequence_notation: SequenceNotation::Hex,
+        };
+        for entry in counts.counts()? {
+            let (sequence_index, count) = entry?;
+            counts.unique_count += 1;
+            if counts.most_common.is_none_or(|(_, most)| count > most) {
+                counts.most_common = Some((sequence_index, count));
+            }
+        }
+        Ok(counts)
+    }
+}
+
+/// The finished counts of an `ExternalCounter`, still on disk as sorted runs and read back
+/// in index order each time they are reported, in memory for the merge buffers alone. The
+/// scratch directory is removed when these are dropped.
+#[derive(Debug)]
+pub struct ExternalCounts {
+    sequence_length: usize,
+    runs: Vec<PathBuf>,
+    /// Held so the runs are removed along with these counts.
+    _scratch: ScratchDir,
+    total_count: u128,
+    unique_count: usize,
+    most_common: Option<(usize, u128)>,
+    sequence_notation: SequenceNotation,
+}
+
+impl ExternalCounts {
+    pub fn sequence_length(&self) -> usize {
+        self.sequence_length
+    }
+
+    pub fn possible_sequences(&self) -> usize {
+        1 << (self.sequence_length << 2)
+    }
+
+    pub fn total_count(&self) -> u128 {
+        self.total_count
+    }
+
+    pub fn unique_count(&self) -> usize {
+        self.unique_count
+    }
+
+    /// Returns the fraction of possible sequences that were seen at least once.
+    pub fn coverage(&self) -> f64 {
+        self.unique_count as f64 / self.possible_sequences() as f64
+    }
+
+    /// Returns the most frequent sequence and its count, the first in index order of any
+    /// tied, or `None` without counts.
+    pub fn most_common(&self) -> Option<(usize, u128)> {
+        self.most_common
+    }
+
+    pub fn sequence_notation(&self) -> SequenceNotation {
+        self.sequence_notation
+    }
+
+    pub fn set_sequence_notation(&mut self, sequence_notation: SequenceNotation) {
+        self.sequence_notation = sequence_notation;
+    }
+
+    /// Returns the scratch space the runs take.
+    pub fn scratch_bytes(&self) -> u64 {
+        self.total_count as u64 * BYTES_PER_WINDOW
+    }
+
+    /// Reads the runs back as `(sequence index, count)` for every nonzero count in ascending
+    /// index order.
+    pub fn counts(&self) -> io::Result<MergedCounts> {
+        Ok(MergedCounts {
+            merger: RunMerger::open(&self.runs)?,
+            next: None,
+        })
+    }
+
+    /// Summarizes these counts the way `Reporter::begin_length` is told about them.
+    pub fn length_summary(&self) -> LengthSummary {
+        LengthSummary {
+            sequence_length: self.sequence_length,
+            possible_sequences: self.possible_sequences(),
+            unique_sequences: self.unique_count,
+            total_count: self.total_count,
+            coverage: self.coverage(),
+            estimated: false,
+            sequence_notation: self.sequence_notation,
+        }
+    }
+
+    /// Reports these counts to `reporter` as one length, streaming them from the runs.
+    pub fn report_to(&self, reporter: &mut dyn Reporter) -> io::Result<()> {
+        reporter.begin_length(&self.length_summary())?;
+        for entry in self.counts()? {
+            let (sequence_index, count) = entry?;
+            reporter.sequence(&SequenceRecord {
+                sequence_index,
+                count,
+            })?;
+        }
+        reporter.end_length()
+    }
+}
+
+/// The counts of `ExternalCounts::counts`, merged from the runs as they are read.
+#[derive(Debug)]
+pub struct MergedCounts {
+    merger: RunMerger,
+    next: Option<u64>,
+}
+
+impl Iterator for MergedCounts {
+    type Item = io::Result<(usize, u128)>;
+
+    fn next(&mut self) -> Option<io::Result<(usize, u128)>> {
+        let sequence_index = match self.next.take() {
+            Some(sequence_index) => sequence_index,
+            None => match self.merger.next_index() {
+                Ok(Some(sequence_index)) => sequence_index,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            },
+        };
+        let mut count = 1;
+        loop {
+            match self.merger.next_index() {
+                Ok(Some(next)) if next == sequence_index => count += 1,
+                Ok(next) => {
+                    self.next = next;
+                    break;
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        Some(Ok((sequence_index as usize, count)))
+    }
+}
+
+/// A k-way merge of sorted run files.
+#[derive(Debug)]
+struct RunMerger {
+    runs: Vec<BufReader<File>>,
+    heads: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl RunMerger {
+    fn open(run_paths: &[PathBuf]) -> io::Result<RunMerger> {
+        let mut merger = RunMerger {
+            runs: Vec::with_capacity(run_paths.len()),
+            heads: BinaryHeap::with_capacity(run_paths.len()),
+        };
+        for path in run_paths {
+            let mut run = BufReader::new(paths::open(path)?);
+            if let Some(sequence_index) = read_index(&mut run)? {
+                merger
+                    .heads
+                    .push(Reverse((sequence_index, merger.runs.len())));
+            }
+            merger.runs.push(run);
+        }
+        Ok(merger)
+    }
+
+    fn next_index(&mut self) -> io::Result<Option<u64>> {
+        let Reverse((sequence_index, run)) = match self.heads.pop() {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        if let Some(next) = read_index(&mut self.runs[run])? {
+            self.heads.push(Reverse((next, run)));
+        }
+        Ok(Some(sequence_index))
+    }
+}
+
+fn read_index<R: Read>(run: &mut R) -> io::Result<Option<u64>> {
+    let mut bytes = [0; BYTES_PER_WINDOW as usize];
+    match run.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
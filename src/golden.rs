@@ -0,0 +1,355 @@
+use std::fmt::{self, Display};
+use std::io::Cursor;
+
+use crate::pipeline::{self, CountOptions, SkipBehavior};
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// The lengths each case has expected counts for.
+pub const GOLDEN_LENGTHS: std::ops::RangeInclusive<usize> = 1..=4;
+
+/// A small input with its complete expected counts, generated once by a naive reference count
+/// and checked in, so a change to how the counters window their input can't also change what
+/// they're checked against.
+#[derive(Clone, Copy, Debug)]
+pub struct GoldenCase {
+    pub name: &'static str,
+    pub input: &'static [u8],
+    pub skip: SkipBehavior,
+    /// The nonzero counts for each of `GOLDEN_LENGTHS`, in ascending sequence index order.
+    pub counts: [&'static [(usize, u128)]; 4],
+}
+
+/// How a `GoldenConfiguration` hands the input to the counters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feeding {
+    /// Through `pipeline::count_digits`, which reads the input into buffers and, with more
+    /// than one thread, counts it in chunks.
+    Reader,
+    /// One byte at a time through `DigitSequenceCounter::process_character`, with the
+    /// separators and skipped bytes dropped first.
+    Bytes,
+}
+
+/// One way of counting a `GoldenCase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GoldenConfiguration {
+    pub feeding: Feeding,
+    pub threads: usize,
+    pub storage: StorageMode,
+    /// Whether the counters also track seen sequences in a bitset, as
+    /// `DigitSequenceCounter::new_with_bitset` does.
+    pub bitset: bool,
+    /// Whether the input is wrapped into short CRLF-separated lines, with CR and LF given as
+    /// separators, rather than counted as it is.
+    pub separated: bool,
+}
+
+impl Display for GoldenConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.feeding {
+            Feeding::Reader => write!(f, "reader threads={}", self.threads)?,
+            Feeding::Bytes => f.write_str("bytes")?,
+        }
+        write!(f, " storage={}", self.storage)?;
+        if self.bitset {
+            f.write_str(" bitset")?;
+        }
+        if self.separated {
+            f.write_str(" separated")?;
+        }
+        Ok(())
+    }
+}
+
+/// The golden cases: decimal digits, hex digits of both cases broken by invalid characters
+/// and a second point, runs of repeated digits counted from the first byte, and an input
+/// with no point to skip through.
+#[rustfmt::skip]
+pub const CASES: &[GoldenCase] = &[
+    GoldenCase {
+        name: "decimal pi",
+        input: b"3.14159265358979323846264338327950288419716939937510582097494459",
+        skip: SkipBehavior::ThroughFirstPoint,
+        counts: [
+            &[
+                (0x0, 3), (0x1, 4), (0x2, 6), (0x3, 8), (0x4, 7), (0x5, 7), (0x6, 4), (0x7, 5),
+                (0x8, 6), (0x9, 11),
+            ],
+            &[
+                (0x02, 1), (0x05, 1), (0x09, 1), (0x10, 1), (0x15, 1), (0x16, 1), (0x19, 1),
+                (0x20, 1), (0x23, 1), (0x26, 2), (0x27, 1), (0x28, 1), (0x32, 2), (0x33, 1),
+                (0x35, 1), (0x37, 1), (0x38, 2), (0x39, 1), (0x41, 2), (0x43, 1), (0x44, 1),
+                (0x45, 1), (0x46, 1), (0x49, 1), (0x50, 1), (0x51, 1), (0x53, 1), (0x58, 2),
+                (0x59, 2), (0x62, 1), (0x64, 1), (0x65, 1), (0x69, 1), (0x71, 1), (0x74, 1),
+                (0x75, 1), (0x79, 2), (0x82, 1), (0x83, 1), (0x84, 2), (0x88, 1), (0x89, 1),
+                (0x92, 1), (0x93, 3), (0x94, 1), (0x95, 1), (0x97, 3), (0x99, 1),
+            ],
+            &[
+                (0x028, 1), (0x058, 1), (0x097, 1), (0x105, 1), (0x159, 1), (0x169, 1),
+                (0x197, 1), (0x209, 1), (0x238, 1), (0x264, 1), (0x265, 1), (0x279, 1),
+                (0x288, 1), (0x323, 1), (0x327, 1), (0x338, 1), (0x358, 1), (0x375, 1),
+                (0x383, 1), (0x384, 1), (0x399, 1), (0x415, 1), (0x419, 1), (0x433, 1),
+                (0x445, 1), (0x459, 1), (0x462, 1), (0x494, 1), (0x502, 1), (0x510, 1),
+                (0x535, 1), (0x582, 1), (0x589, 1), (0x592, 1), (0x626, 1), (0x643, 1),
+                (0x653, 1), (0x693, 1), (0x716, 1), (0x749, 1), (0x751, 1), (0x793, 1),
+                (0x795, 1), (0x820, 1), (0x832, 1), (0x841, 1), (0x846, 1), (0x884, 1),
+                (0x897, 1), (0x926, 1), (0x932, 1), (0x937, 1), (0x939, 1), (0x944, 1),
+                (0x950, 1), (0x971, 1), (0x974, 1), (0x979, 1), (0x993, 1),
+            ],
+            &[
+                (0x0288, 1), (0x0582, 1), (0x0974, 1), (0x1058, 1), (0x1592, 1), (0x1693, 1),
+                (0x1971, 1), (0x2097, 1), (0x2384, 1), (0x2643, 1), (0x2653, 1), (0x2795, 1),
+                (0x2884, 1), (0x3238, 1), (0x3279, 1), (0x3383, 1), (0x3589, 1), (0x3751, 1),
+                (0x3832, 1), (0x3846, 1), (0x3993, 1), (0x4159, 1), (0x4197, 1), (0x4338, 1),
+                (0x4459, 1), (0x4626, 1), (0x4944, 1), (0x5028, 1), (0x5105, 1), (0x5358, 1),
+                (0x5820, 1), (0x5897, 1), (0x5926, 1), (0x6264, 1), (0x6433, 1), (0x6535, 1),
+                (0x6939, 1), (0x7169, 1), (0x7494, 1), (0x7510, 1), (0x7932, 1), (0x7950, 1),
+                (0x8209, 1), (0x8327, 1), (0x8419, 1), (0x8462, 1), (0x8841, 1), (0x8979, 1),
+                (0x9265, 1), (0x9323, 1), (0x9375, 1), (0x9399, 1), (0x9445, 1), (0x9502, 1),
+                (0x9716, 1), (0x9749, 1), (0x9793, 1), (0x9937, 1),
+            ],
+        ],
+    },
+    GoldenCase {
+        name: "hex pi with breaks",
+        input: b"3.243F6A88 85A308D3-13198A2E.037073 44a4093822299f31d0082efa98ec4e6c89452821e638d01377be5466cf34e90c6cc0ac",
+        skip: SkipBehavior::ThroughFirstPoint,
+        counts: [
+            &[
+                (0x0, 8), (0x1, 4), (0x2, 7), (0x3, 11), (0x4, 7), (0x5, 3), (0x6, 6), (0x7, 4),
+                (0x8, 10), (0x9, 7), (0xA, 6), (0xB, 1), (0xC, 7), (0xD, 3), (0xE, 7), (0xF, 4),
+            ],
+            &[
+                (0x00, 1), (0x01, 1), (0x07, 1), (0x08, 2), (0x09, 1), (0x0A, 1), (0x0C, 1),
+                (0x13, 1), (0x19, 1), (0x1D, 1), (0x1E, 1), (0x21, 1), (0x22, 2), (0x28, 1),
+                (0x29, 1), (0x2E, 2), (0x30, 1), (0x31, 2), (0x34, 1), (0x37, 2), (0x38, 2),
+                (0x3F, 1), (0x40, 1), (0x43, 1), (0x45, 1), (0x46, 1), (0x4A, 1), (0x4E, 2),
+                (0x52, 1), (0x54, 1), (0x5A, 1), (0x63, 1), (0x66, 1), (0x6A, 1), (0x6C, 3),
+                (0x70, 1), (0x73, 1), (0x77, 1), (0x7B, 1), (0x82, 3), (0x88, 1), (0x89, 1),
+                (0x8A, 1), (0x8D, 2), (0x8E, 1), (0x90, 1), (0x93, 1), (0x94, 1), (0x98, 2),
+                (0x99, 1), (0x9F, 1), (0xA2, 1), (0xA3, 1), (0xA4, 1), (0xA8, 1), (0xA9, 1),
+                (0xAC, 1), (0xBE, 1), (0xC0, 1), (0xC4, 1), (0xC6, 1), (0xC8, 1), (0xCC, 1),
+                (0xCF, 1), (0xD0, 2), (0xD3, 1), (0xE5, 1), (0xE6, 2), (0xE9, 1), (0xEC, 1),
+                (0xEF, 1), (0xF3, 2), (0xF6, 1), (0xFA, 1),
+            ],
+            &[
+                (0x008, 1), (0x013, 1), (0x073, 1), (0x082, 1), (0x08D, 1), (0x093, 1),
+                (0x0AC, 1), (0x0C6, 1), (0x137, 1), (0x198, 1), (0x1D0, 1), (0x1E6, 1),
+                (0x21E, 1), (0x222, 1), (0x229, 1), (0x282, 1), (0x299, 1), (0x2EF, 1),
+                (0x308, 1), (0x319, 1), (0x31D, 1), (0x34E, 1), (0x370, 1), (0x377, 1),
+                (0x382, 1), (0x38D, 1), (0x3F6, 1), (0x409, 1), (0x43F, 1), (0x452, 1),
+                (0x466, 1), (0x4A4, 1), (0x4E6, 1), (0x4E9, 1), (0x528, 1), (0x546, 1),
+                (0x5A3, 1), (0x638, 1), (0x66C, 1), (0x6A8, 1), (0x6C8, 1), (0x6CC, 1),
+                (0x6CF, 1), (0x707, 1), (0x77B, 1), (0x7BE, 1), (0x821, 1), (0x822, 1),
+                (0x82E, 1), (0x894, 1), (0x8A2, 1), (0x8D0, 1), (0x8D3, 1), (0x8EC, 1),
+                (0x90C, 1), (0x938, 1), (0x945, 1), (0x98A, 1), (0x98E, 1), (0x99F, 1),
+                (0x9F3, 1), (0xA2E, 1), (0xA30, 1), (0xA40, 1), (0xA88, 1), (0xA98, 1),
+                (0xBE5, 1), (0xC0A, 1), (0xC4E, 1), (0xC6C, 1), (0xC89, 1), (0xCC0, 1),
+                (0xCF3, 1), (0xD00, 1), (0xD01, 1), (0xE54, 1), (0xE63, 1), (0xE6C, 1),
+                (0xE90, 1), (0xEC4, 1), (0xEFA, 1), (0xF31, 1), (0xF34, 1), (0xF6A, 1),
+                (0xFA9, 1),
+            ],
+            &[
+                (0x0082, 1), (0x0137, 1), (0x082E, 1), (0x08D3, 1), (0x0938, 1), (0x0C6C, 1),
+                (0x1377, 1), (0x198A, 1), (0x1D00, 1), (0x1E63, 1), (0x21E6, 1), (0x2229, 1),
+                (0x2299, 1), (0x2821, 1), (0x299F, 1), (0x2EFA, 1), (0x308D, 1), (0x3198, 1),
+                (0x31D0, 1), (0x34E9, 1), (0x3707, 1), (0x377B, 1), (0x3822, 1), (0x38D0, 1),
+                (0x3F6A, 1), (0x4093, 1), (0x43F6, 1), (0x4528, 1), (0x466C, 1), (0x4A40, 1),
+                (0x4E6C, 1), (0x4E90, 1), (0x5282, 1), (0x5466, 1), (0x5A30, 1), (0x638D, 1),
+                (0x66CF, 1), (0x6A88, 1), (0x6C89, 1), (0x6CC0, 1), (0x6CF3, 1), (0x7073, 1),
+                (0x77BE, 1), (0x7BE5, 1), (0x821E, 1), (0x8222, 1), (0x82EF, 1), (0x8945, 1),
+                (0x8A2E, 1), (0x8D01, 1), (0x8EC4, 1), (0x90C6, 1), (0x9382, 1), (0x9452, 1),
+                (0x98A2, 1), (0x98EC, 1), (0x99F3, 1), (0x9F31, 1), (0xA308, 1), (0xA409, 1),
+                (0xA98E, 1), (0xBE54, 1), (0xC0AC, 1), (0xC4E6, 1), (0xC6CC, 1), (0xC894, 1),
+                (0xCC0A, 1), (0xCF34, 1), (0xD008, 1), (0xD013, 1), (0xE546, 1), (0xE638, 1),
+                (0xE6C8, 1), (0xE90C, 1), (0xEC4E, 1), (0xEFA9, 1), (0xF31D, 1), (0xF34E, 1),
+                (0xF6A8, 1), (0xFA98, 1),
+            ],
+        ],
+    },
+    GoldenCase {
+        name: "repeats from start",
+        input: b"0000000111ffFFff x 9 99 999 9999 99999",
+        skip: SkipBehavior::None,
+        counts: [
+            &[
+                (0x0, 6), (0x1, 3), (0x9, 10), (0xF, 6),
+            ],
+            &[
+                (0x00, 5), (0x01, 1), (0x11, 2), (0x1F, 1), (0x99, 6), (0xFF, 5),
+            ],
+            &[
+                (0x000, 4), (0x001, 1), (0x011, 1), (0x111, 1), (0x11F, 1), (0x1FF, 1),
+                (0x999, 3), (0xFFF, 4),
+            ],
+            &[
+                (0x0000, 3), (0x0001, 1), (0x0011, 1), (0x0111, 1), (0x111F, 1), (0x11FF, 1),
+                (0x1FFF, 1), (0x9999, 1), (0xFFFF, 3),
+            ],
+        ],
+    },
+    GoldenCase {
+        name: "no point",
+        input: b"1234567890ABCDEF",
+        skip: SkipBehavior::ThroughFirstPoint,
+        counts: [
+            &[],
+            &[],
+            &[],
+            &[],
+        ],
+    },
+];
+
+/// Returns every configuration the golden cases are checked under: with and without
+/// separators, each storage mode with exact counts fed through a reader with one and four
+/// threads and fed byte by byte, and byte feeding with a bitset.
+pub fn configurations() -> Vec<GoldenConfiguration> {
+    let mut configurations = Vec::new();
+    for &separated in &[false, true] {
+        for &storage in &[
+            StorageMode::Dense,
+            StorageMode::Sparse,
+            StorageMode::Adaptive,
+        ] {
+            for &(feeding, threads) in &[
+                (Feeding::Reader, 1),
+                (Feeding::Reader, 4),
+                (Feeding::Bytes, 1),
+            ] {
+                configurations.push(GoldenConfiguration {
+                    feeding,
+                    threads,
+                    storage,
+                    bitset: false,
+                    separated,
+                });
+            }
+        }
+        configurations.push(GoldenConfiguration {
+            feeding: Feeding::Bytes,
+            threads: 1,
+            storage: StorageMode::Dense,
+            bitset: true,
+            separated,
+        });
+    }
+    configurations
+}
+
+/// Counts `case` under `configuration` and compares every length against its checked-in
+/// counts, describing the first difference found.
+pub fn check(case: &GoldenCase, configuration: GoldenConfiguration) -> Result<(), String> {
+    let (input, separators) = if configuration.separated {
+        let mut wrapped = Vec::new();
+        for line in case.input.chunks(7) {
+            wrapped.extend_from_slice(line);
+            wrapped.extend_from_slice(b"\r\n");
+        }
+        (wrapped, b"\r\n".to_vec())
+    } else {
+        (case.input.to_vec(), Vec::new())
+    };
+
+    let counters = match configuration.feeding {
+        Feeding::Reader => {
+            let mut options = CountOptions::new(GOLDEN_LENGTHS);
+            options.storage = configuration.storage;
+            options.threads = configuration.threads;
+            options.separators = separators;
+            options.skip = case.skip;
+            // Tiny chunks put chunk boundaries all through these short inputs.
+            options.chunk_size = 5;
+            let report = pipeline::count_digits(Cursor::new(&input), &options)
+                .map_err(|err| err.to_string())?;
+            if report.input_bytes != input.len() as u64 {
+                return Err(format!(
+                    "read {} bytes of {}",
+                    report.input_bytes,
+                    input.len()
+                ));
+            }
+            report.counters
+        }
+        Feeding::Bytes => {
+            let mut counters = GOLDEN_LENGTHS
+                .map(|sequence_length| {
+                    if configuration.bitset {
+                        DigitSequenceCounter::new_with_bitset(sequence_length)
+                    } else {
+                        DigitSequenceCounter::with_storage(sequence_length, configuration.storage)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut counting = case.skip == SkipBehavior::None;
+            for &byte in input.iter().filter(|byte| !separators.contains(byte)) {
+                if !counting {
+                    counting = byte == b'.';
+                    continue;
+                }
+                for counter in &mut counters {
+                    counter.process_character(byte);
+                }
+            }
+            counters
+        }
+    };
+
+    for (counter, expected) in counters.iter().zip(&case.counts) {
+        if let Some(difference) = describe_difference(counter, expected) {
+            return Err(format!(
+                "length {}: {}",
+                counter.sequence_length(),
+                difference
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks every golden case under every configuration, describing the first failure.
+///
+/// ```
+/// use digit_sequence_counter::golden;
+///
+/// assert_eq!(golden::verify_golden(), Ok(()));
+/// ```
+pub fn verify_golden() -> Result<(), String> {
+    for case in CASES {
+        for configuration in configurations() {
+            check(case, configuration)
+                .map_err(|err| format!("{} ({}): {}", case.name, configuration, err))?;
+        }
+    }
+    Ok(())
+}
+
+/// Describes the first difference between `counter`'s nonzero counts and `expected`, or
+/// returns `None` if they're the same.
+pub(crate) fn describe_difference(
+    counter: &DigitSequenceCounter,
+    expected: &[(usize, u128)],
+) -> Option<String> {
+    let actual = counter.nonzero_counts();
+    if actual == expected {
+        return None;
+    }
+    let difference = actual
+        .iter()
+        .zip(expected)
+        .find(|(actual, expected)| actual != expected);
+    Some(match difference {
+        Some((&(actual_index, actual_count), &(expected_index, expected_count))) => format!(
+            "got {} x{}, expected {} x{}",
+            counter.sequence_label(actual_index),
+            actual_count,
+            counter.sequence_label(expected_index),
+            expected_count
+        ),
+        None => format!(
+            "got {} distinct sequences, expected {}",
+            actual.len(),
+            expected.len()
+        ),
+    })
+}
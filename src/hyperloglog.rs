@@ -0,0 +1,104 @@
+/// A HyperLogLog sketch of the distinct sequence indices seen, with `2^bits` one-byte
+/// registers, plus the total number of windows added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Sketch {
+    bits: usize,
+    registers: Vec<u8>,
+    total: u128,
+}
+
+impl Sketch {
+    pub(crate) const BITS: std::ops::RangeInclusive<usize> = 4..=16;
+
+    pub(crate) fn new(bits: usize) -> Sketch {
+        if !Sketch::BITS.contains(&bits) {
+            panic!(
+                "Cannot create a HyperLogLog sketch with {} bits; it needs {} to {}.",
+                bits,
+                Sketch::BITS.start(),
+                Sketch::BITS.end()
+            );
+        }
+        Sketch {
+            bits,
+            registers: vec![0; 1 << bits],
+            total: 0,
+        }
+    }
+
+    pub(crate) fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub(crate) fn total(&self) -> u128 {
+        self.total
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, count: u128) {
+        if count == 0 {
+            return;
+        }
+        self.total += count;
+        let hash = mix(index as u64);
+        let register = (hash >> (64 - self.bits)) as usize;
+        let rank = ((hash << self.bits) | (1 << (self.bits - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[register] {
+            self.registers[register] = rank;
+        }
+    }
+
+    /// Merges another sketch of the same size into this one, as if its inputs had been
+    /// inserted here.
+    pub(crate) fn merge(&mut self, other: &Sketch) {
+        if self.bits != other.bits {
+            panic!("Cannot merge HyperLogLog sketches of different sizes.");
+        }
+        self.total += other.total;
+        self.registers
+            .iter_mut()
+            .zip(&other.registers)
+            .for_each(|(register, &other)| *register = (*register).max(other));
+    }
+
+    /// Returns the relative standard error of `estimate`, about `1.04 / sqrt(2^bits)`.
+    pub(crate) fn relative_standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+
+    /// Returns the estimated number of distinct indices inserted, falling back to linear
+    /// counting while many registers are still empty.
+    pub(crate) fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let harmonic_sum = self
+            .registers
+            .iter()
+            .map(|&register| 2_f64.powi(-(register as i32)))
+            .sum::<f64>();
+        let raw_estimate = alpha * m * m / harmonic_sum;
+
+        let empty_registers = self
+            .registers
+            .iter()
+            .filter(|&&register| register == 0)
+            .count();
+        if raw_estimate <= 2.5 * m && empty_registers != 0 {
+            m * (m / empty_registers as f64).ln()
+        } else {
+            raw_estimate
+        }
+    }
+}
+
+/// Scrambles a sequence index into a well-distributed 64-bit hash using the SplitMix64
+/// finalizer, so nearby indices land in unrelated registers.
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
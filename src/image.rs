@@ -0,0 +1,261 @@
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::Path;
+
+use crate::paths;
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    /// The longest sequence length `write_counts_as_image` accepts, whose image is 4096
+    /// pixels square.
+    pub const IMAGE_LENGTH_LIMIT: usize = 6;
+
+    /// Returns the side of the square count image, `16^(sequence_length / 2)`, or `None` if
+    /// the sequence length is odd, 0, or greater than `IMAGE_LENGTH_LIMIT`.
+    pub fn image_side(&self) -> Option<usize> {
+        if self.sequence_length == 0
+            || !self.sequence_length.is_multiple_of(2)
+            || self.sequence_length > Self::IMAGE_LENGTH_LIMIT
+        {
+            return None;
+        }
+        Some(1 << (self.sequence_length << 1))
+    }
+
+    /// Returns the count image's grayscale pixels in rows from the top, or `None` for a
+    /// counter without an `image_side`. The first half of a sequence's digits picks its row
+    /// and the second half its column, and each pixel is its count scaled so the most common
+    /// sequence is 255. A counter with no counts is all black.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x14141417".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let pixels = counter.count_pixels().unwrap();
+    /// assert_eq!(pixels.len(), 16 * 16);
+    /// let brightest = (0..pixels.len()).max_by_key(|&pixel| pixels[pixel]).unwrap();
+    /// assert_eq!(brightest, counter.top_n(1)[0].0);
+    /// assert_eq!(
+    ///     (pixels[0x41], pixels[0x14], pixels[0x17], pixels[0x00]),
+    ///     (255, 170, 85, 0)
+    /// );
+    /// assert_eq!(DigitSequenceCounter::new(3).count_pixels(), None);
+    /// ```
+    pub fn count_pixels(&self) -> Option<Vec<u8>> {
+        let side = self.image_side()?;
+        let mut pixels = vec![0; side * side];
+        let nonzero_counts = self.nonzero_counts();
+        let largest = nonzero_counts.iter().map(|&(_, count)| count).max();
+        if let Some(largest) = largest {
+            for (sequence_index, count) in nonzero_counts {
+                pixels[sequence_index] = (count as f64 / largest as f64 * 255.0).round() as u8;
+            }
+        }
+        Some(pixels)
+    }
+
+    /// Writes `count_pixels` as an 8-bit grayscale PNG.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(4);
+    /// b"x31415926535".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let mut png = Vec::new();
+    /// counter.write_counts_as_png(&mut png).unwrap();
+    /// assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    /// assert_eq!(&png[12..16], b"IHDR");
+    /// assert_eq!(&png[16..24], [0, 0, 1, 0, 0, 0, 1, 0]);
+    /// assert!(DigitSequenceCounter::new(1).write_counts_as_png(&mut png).is_err());
+    /// ```
+    pub fn write_counts_as_png<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let pixels = self.count_pixels().ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a count image needs an even sequence length from 2 to {}, not {}",
+                    Self::IMAGE_LENGTH_LIMIT,
+                    self.sequence_length
+                ),
+            )
+        })?;
+        let side = self.image_side().unwrap_or(0);
+        write_grayscale_png(&mut writer, side, &pixels)
+    }
+
+    /// Writes `write_counts_as_png` to the file at `path`, replacing any file already there.
+    pub fn write_counts_as_image<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(paths::for_opening(path.as_ref()))?);
+        self.write_counts_as_png(&mut out)?;
+        out.flush()
+    }
+}
+
+/// Writes a square grayscale PNG. The image data is stored without compression, which keeps
+/// the encoder to a few checksums.
+fn write_grayscale_png<W: Write>(writer: &mut W, side: usize, pixels: &[u8]) -> io::Result<()> {
+    // Deflate's stored blocks hold at most this many bytes each.
+    const STORED_BLOCK_LIMIT: usize = 65535;
+
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(side as u32).to_be_bytes());
+    header.extend_from_slice(&(side as u32).to_be_bytes());
+    // 8 bits per pixel, grayscale, deflate, adaptive filtering, no interlacing.
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+
+    // Each row starts with filter type 0, leaving its pixels as they are.
+    let mut rows = Vec::with_capacity(pixels.len() + side);
+    for row in pixels.chunks(side) {
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+    let mut data = vec![0x78, 0x01];
+    let blocks = rows.chunks(STORED_BLOCK_LIMIT).count();
+    for (block, stored) in rows.chunks(STORED_BLOCK_LIMIT).enumerate() {
+        data.push((block + 1 == blocks) as u8);
+        data.extend_from_slice(&(stored.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(!(stored.len() as u16)).to_le_bytes());
+        data.extend_from_slice(stored);
+    }
+    data.extend_from_slice(&adler32(&rows).to_be_bytes());
+    write_chunk(writer, b"IDAT", &data)?;
+
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let mut crc = !0u32;
+    for &byte in kind.iter().chain(data) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DigitSequenceCounter, StorageMode};
+
+    /// Reads back a PNG of the kind `write_grayscale_png` writes, checking every chunk's CRC,
+    /// the zlib framing, each stored block's length, and the Adler-32, and returns the side
+    /// and the pixels with the row filters removed.
+    fn decode(png: &[u8]) -> (usize, Vec<u8>) {
+        let crc_table = (0..256u32)
+            .map(|byte| {
+                (0..8).fold(byte, |crc, _| {
+                    if crc & 1 == 1 {
+                        0xEDB8_8320 ^ (crc >> 1)
+                    } else {
+                        crc >> 1
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let be32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = be32(rest) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = !rest[4..8 + len].iter().fold(!0u32, |crc, &byte| {
+                crc_table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+            });
+            assert_eq!(crc, be32(&rest[8 + len..]), "CRC of {:?}", kind);
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+        let kinds = chunks.iter().map(|&(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+
+        let header = chunks[0].1;
+        let side = be32(header) as usize;
+        assert_eq!(be32(&header[4..]) as usize, side);
+        assert_eq!(&header[8..], [8, 0, 0, 0, 0]);
+
+        let zlib = chunks[1].1;
+        assert_eq!(
+            (zlib[0] & 0xF, u16::from_be_bytes([zlib[0], zlib[1]]) % 31),
+            (8, 0)
+        );
+        let mut inflated = Vec::new();
+        let mut at = 2;
+        loop {
+            let last = zlib[at];
+            assert_eq!(last & !1, 0, "only stored blocks are expected");
+            let len = u16::from_le_bytes([zlib[at + 1], zlib[at + 2]]);
+            let nlen = u16::from_le_bytes([zlib[at + 3], zlib[at + 4]]);
+            assert_eq!(nlen, !len);
+            inflated.extend_from_slice(&zlib[at + 5..at + 5 + len as usize]);
+            at += 5 + len as usize;
+            if last == 1 {
+                break;
+            }
+            assert_eq!(len, 65535, "only the last block may be short");
+        }
+        let (a, b) = inflated.iter().fold((1u64, 0u64), |(a, b), &byte| {
+            let a = (a + byte as u64) % 65521;
+            (a, (b + a) % 65521)
+        });
+        assert_eq!(be32(&zlib[at..]) as u64, (b << 16) | a);
+        assert_eq!(at + 4, zlib.len());
+
+        assert_eq!(inflated.len(), side * (side + 1));
+        let mut pixels = Vec::with_capacity(side * side);
+        for row in inflated.chunks(side + 1) {
+            assert_eq!(row[0], 0, "filter type");
+            pixels.extend_from_slice(&row[1..]);
+        }
+        (side, pixels)
+    }
+
+    fn round_trip(sequence_length: usize, input: &[u8]) {
+        let mut counter = DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse);
+        counter.process_bytes(input);
+        let mut png = Vec::new();
+        counter.write_counts_as_png(&mut png).unwrap();
+        let (side, pixels) = decode(&png);
+        assert_eq!(Some(side), counter.image_side());
+        assert_eq!(Some(pixels), counter.count_pixels());
+    }
+
+    #[test]
+    fn small_images_decode_to_their_pixels() {
+        round_trip(2, b"x14141417");
+        round_trip(2, b"");
+        round_trip(
+            4,
+            b"x3141592653589793238462643383279502884197169399375105820974944",
+        );
+    }
+
+    #[test]
+    fn largest_image_decodes_across_many_stored_blocks() {
+        let input = (0..2000u32)
+            .map(|index| format!("{:07X}", index.wrapping_mul(2_654_435_761) >> 4))
+            .collect::<Vec<_>>()
+            .join(" ");
+        round_trip(DigitSequenceCounter::IMAGE_LENGTH_LIMIT, input.as_bytes());
+    }
+}
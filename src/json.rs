@@ -0,0 +1,29 @@
+use std::io::{self, Write};
+
+use crate::number_format::NumberFormat;
+
+/// Writes `s` as a JSON string literal.
+pub fn write_string<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+/// Writes `value` as a JSON number rounded by `format`, or `null` if it isn't finite.
+pub fn write_number<W: Write>(out: &mut W, value: f64, format: &NumberFormat) -> io::Result<()> {
+    if value.is_finite() {
+        write!(out, "{}", format.format(value))
+    } else {
+        write!(out, "null")
+    }
+}
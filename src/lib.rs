@@ -0,0 +1,609 @@
+use std::fmt::{self, Display};
+use std::io::{self, ErrorKind, Read};
+use std::ops::AddAssign;
+use std::path::Path;
+
+pub mod checksum;
+mod count_min;
+pub mod digit_filter;
+pub mod downsample;
+pub mod drought;
+pub mod external;
+pub mod golden;
+mod hyperloglog;
+#[cfg(feature = "image")]
+pub mod image;
+pub mod json;
+pub mod lookup;
+pub mod markov;
+pub mod matrix;
+pub mod merge;
+pub mod metrics;
+pub mod neighborhood;
+pub mod notation;
+pub mod number_format;
+pub mod overflow;
+pub mod parallel;
+#[cfg(feature = "parquet")]
+pub mod parquet_io;
+pub mod passes;
+pub mod paths;
+pub mod pipeline;
+pub mod reporter;
+pub mod reproducibility;
+pub mod rolling;
+pub mod runs;
+#[cfg(feature = "sampling")]
+pub mod sampling;
+pub mod selftest;
+pub mod serialization;
+pub mod simd;
+pub mod smoothing;
+pub mod sniff;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statistics;
+pub mod statistics_plan;
+mod storage;
+pub mod tracking;
+pub mod transform;
+pub mod visualization;
+
+use count_min::CountMin;
+use digit_filter::ByteAction;
+use hyperloglog::Sketch;
+use notation::SequenceNotation;
+use overflow::OverflowError;
+use storage::Counts;
+pub use storage::{StorageConversion, StorageMode};
+
+#[derive(Clone, Debug)]
+pub struct DigitSequenceCounter {
+    sequence_length: usize,
+    current_sequence: usize,
+    sequence_counts: Counts,
+    storage_mode: StorageMode,
+    storage_conversion: Option<StorageConversion>,
+    seen: Option<Vec<u64>>,
+    seen_count: usize,
+    bitmask: usize,
+    stalled_for: usize,
+    sequence_notation: SequenceNotation,
+    radix: u32,
+    overflow_check: bool,
+    overflow: Option<OverflowError>,
+    first_seen: Option<Vec<u64>>,
+}
+
+impl DigitSequenceCounter {
+    pub const LARGEST_SEQUENCE_LENGTH: usize = (std::mem::size_of::<usize>() << 1) - 1;
+    /// The count-min sketch dimensions `StorageMode::Sketch` uses with `with_storage`.
+    pub const DEFAULT_SKETCH_WIDTH: usize = storage::DEFAULT_COUNT_MIN_WIDTH;
+    pub const DEFAULT_SKETCH_DEPTH: usize = storage::DEFAULT_COUNT_MIN_DEPTH;
+    pub const SKETCH_DEPTHS: std::ops::RangeInclusive<usize> = CountMin::DEPTHS;
+
+    pub fn new(sequence_length: usize) -> DigitSequenceCounter {
+        DigitSequenceCounter::with_storage(sequence_length, StorageMode::Dense)
+    }
+
+    pub fn with_storage(sequence_length: usize, storage_mode: StorageMode) -> DigitSequenceCounter {
+        if sequence_length > Self::LARGEST_SEQUENCE_LENGTH {
+            panic!(
+                "Cannot create a DigitSequenceCounter for a sequence length greater than {}.",
+                Self::LARGEST_SEQUENCE_LENGTH
+            );
+        }
+        let modulus = 1 << (sequence_length << 2);
+        DigitSequenceCounter {
+            sequence_length,
+            current_sequence: 0,
+            sequence_counts: Counts::new(storage_mode, modulus),
+            storage_mode,
+            storage_conversion: None,
+            seen: None,
+            seen_count: 0,
+            bitmask: modulus - 1,
+            stalled_for: sequence_length,
+            sequence_notation: SequenceNotation::Hex,
+            radix: 16,
+            overflow_check: false,
+            overflow: None,
+            first_seen: None,
+        }
+    }
+
+    /// Creates a counter that only estimates how many distinct sequences it has seen, using a
+    /// HyperLogLog sketch of `2^sketch_bits` one-byte registers instead of any counts. The
+    /// standard error of the estimate is about `1.04 / sqrt(2^sketch_bits)`.
+    ///
+    /// `total_count` stays exact, but `count_at` is always 0 and `nonzero_counts` is empty,
+    /// so statistics built on per-sequence counts don't apply. `unique_count` returns the
+    /// rounded estimate. Sketches with the same `sketch_bits` can be merged with `+=`.
+    ///
+    /// Passing `StorageMode::HyperLogLog` to `with_storage` uses 12 sketch bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sketch_bits` is outside `4..=16`.
+    pub fn new_hyperloglog(sequence_length: usize, sketch_bits: usize) -> DigitSequenceCounter {
+        let mut counter =
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::HyperLogLog);
+        counter.sequence_counts = Counts::HyperLogLog(Sketch::new(sketch_bits));
+        counter
+    }
+
+    /// Creates a counter that only estimates, using a count-min sketch `width` counters wide
+    /// and `depth` rows deep for the counts and a HyperLogLog sketch with 12 bits for the
+    /// number of distinct sequences. It takes `width * depth * 8` bytes plus 4 KiB whatever
+    /// the sequence length.
+    ///
+    /// `count_at` returns an estimate that is never too low and, with probability at least
+    /// `1 - e^-depth`, too high by at most `e / width` of the total; see `count_error_bound`.
+    /// `total_count` stays exact, `nonzero_counts` is empty, and `unique_count` returns the
+    /// rounded HyperLogLog estimate. Sketches with the same dimensions can be merged with `+=`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0 or `depth` is outside `1..=16`.
+    pub fn new_count_min(
+        sequence_length: usize,
+        width: usize,
+        depth: usize,
+    ) -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sketch);
+        counter.sequence_counts = Counts::CountMin(
+            CountMin::new(width, depth),
+            Sketch::new(storage::DEFAULT_SKETCH_BITS),
+        );
+        counter
+    }
+
+    /// Returns the bytes a `new_count_min` counter of these dimensions takes.
+    pub fn count_min_bytes(width: usize, depth: usize) -> usize {
+        CountMin::bytes(width, depth).saturating_add(1 << storage::DEFAULT_SKETCH_BITS)
+    }
+
+    /// Returns the widest `new_count_min` sketch of `depth` rows that fits in `bytes`, or 0 if
+    /// none does.
+    pub fn count_min_width_within(bytes: usize, depth: usize) -> usize {
+        bytes.saturating_sub(DigitSequenceCounter::count_min_bytes(0, depth))
+            / CountMin::bytes(1, depth).max(1)
+    }
+
+    /// Creates a dense counter that also keeps one bit per possible sequence, making
+    /// `running_unique_count` O(1) at the cost of `possible_sequences() / 8` extra bytes.
+    pub fn new_with_bitset(sequence_length: usize) -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new(sequence_length);
+        counter.seen = Some(vec![0; (counter.possible_sequences() + 63) >> 6]);
+        counter
+    }
+
+    pub fn process_character(&mut self, character: u8) {
+        if self.shift_in(character) {
+            self.count_shifted_sequence();
+        }
+    }
+
+    /// Feeds each of `bytes` to `process_character` in order.
+    pub fn process_bytes(&mut self, bytes: &[u8]) {
+        bytes
+            .iter()
+            .for_each(|&character| self.process_character(character));
+    }
+
+    /// Counts the window a character just completed, checking for overflow if the counter
+    /// was created to.
+    fn count_shifted_sequence(&mut self) {
+        if self.overflow_check {
+            self.count_current_sequence_checked();
+        } else {
+            self.count_current_sequence();
+        }
+    }
+
+    /// Counts the file at `path` as raw binary, feeding each byte in as two hex digits, high
+    /// nibble first, so the byte `0xAB` counts the same as the characters `AB`.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let path = std::env::temp_dir().join(format!("dsc-binary-{}.bin", std::process::id()));
+    /// std::fs::write(&path, [0xAB, 0x01, 0xFF, 0x10, 0x31, 0xAB]).unwrap();
+    ///
+    /// for sequence_length in 1..=4 {
+    ///     let mut binary = DigitSequenceCounter::new(sequence_length);
+    ///     binary.process_binary_file(&path).unwrap();
+    ///     let mut text = DigitSequenceCounter::new(sequence_length);
+    ///     text.process_bytes(b"AB01FF1031AB");
+    ///     assert_eq!(binary, text);
+    /// }
+    ///
+    /// let mut binary = DigitSequenceCounter::new(2);
+    /// binary.process_binary_file(&path).unwrap();
+    /// assert_eq!(binary.count_at(0xAB), 1);
+    /// assert_eq!(binary.total_count(), 10);
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn process_binary_file(&mut self, path: &Path) -> io::Result<()> {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+        let mut file = paths::open(path)?;
+        let mut buffer = vec![0; 65536];
+        loop {
+            let n = match file.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            for &byte in &buffer[..n] {
+                self.process_character(HEX_DIGITS[(byte >> 4) as usize]);
+                self.process_character(HEX_DIGITS[(byte & 0xF) as usize]);
+            }
+        }
+    }
+
+    /// Advances the window without counting, returning the sequence index of the window
+    /// `process_character` would have counted.
+    pub(crate) fn advance_window(&mut self, character: u8) -> Option<usize> {
+        if self.shift_in(character) {
+            Some(self.current_sequence)
+        } else {
+            None
+        }
+    }
+
+    /// Feeds a character into the sliding window without counting anything, so a counter
+    /// can pick up mid-stream with the same window state as one that saw the whole input.
+    pub fn warm_up(&mut self, character: u8) {
+        self.shift_in(character);
+    }
+
+    /// Clears the sliding window, as a non-digit character does.
+    pub fn reset_window(&mut self) {
+        self.current_sequence = 0;
+        self.stalled_for = self.sequence_length;
+    }
+
+    fn shift_in(&mut self, character: u8) -> bool {
+        match digit_filter::HEX.action(character) {
+            ByteAction::Emit(digit) => self.shift_in_digit(digit),
+            _ => {
+                self.reset_window();
+                false
+            }
+        }
+    }
+
+    /// Counts a digit value from 0 to 15 that a `DigitFilter` already classified.
+    #[inline]
+    pub(crate) fn process_digit(&mut self, digit: u8) {
+        if self.shift_in_digit(digit) {
+            self.count_shifted_sequence();
+        }
+    }
+
+    fn shift_in_digit(&mut self, digit: u8) -> bool {
+        self.current_sequence = ((self.current_sequence << 4) | digit as usize) & self.bitmask;
+
+        if self.stalled_for == 0 {
+            true
+        } else {
+            self.stalled_for -= 1;
+            false
+        }
+    }
+
+    fn count_current_sequence(&mut self) {
+        let is_new_sequence = match &mut self.sequence_counts {
+            Counts::Dense(counts) => {
+                counts[self.current_sequence] += 1;
+                if self.seen.is_none() {
+                    return;
+                }
+                counts[self.current_sequence] == 1
+            }
+            Counts::Sparse(counts) => {
+                let count = counts.entry(self.current_sequence).or_insert(0);
+                *count += 1;
+                *count == 1
+            }
+            Counts::HyperLogLog(sketch) => {
+                sketch.insert(self.current_sequence, 1);
+                return;
+            }
+            Counts::CountMin(counts, distinct) => {
+                counts.insert(self.current_sequence, 1);
+                distinct.insert(self.current_sequence, 1);
+                return;
+            }
+        };
+        if is_new_sequence {
+            self.record_new_sequence(self.current_sequence);
+        }
+    }
+
+    pub(crate) fn add_count(&mut self, sequence_index: usize, count: u128) {
+        if count == 0 {
+            return;
+        }
+        self.sequence_counts.add(sequence_index, count);
+        self.record_new_sequence(sequence_index);
+    }
+
+    /// Takes back one window of `sequence_index` that was counted earlier, for counters
+    /// over a sliding span of the input. The seen bitset isn't updated, so this is only for
+    /// counters created without one.
+    pub(crate) fn remove_count(&mut self, sequence_index: usize) {
+        self.sequence_counts.remove_one(sequence_index);
+    }
+
+    fn record_new_sequence(&mut self, sequence_index: usize) {
+        if let Some(seen) = &mut self.seen {
+            let bit = 1 << (sequence_index & 63);
+            let word = &mut seen[sequence_index >> 6];
+            if *word & bit == 0 {
+                *word |= bit;
+                self.seen_count += 1;
+            }
+        }
+        if self.storage_mode == StorageMode::Adaptive {
+            self.densify_if_profitable();
+        }
+    }
+
+    /// Brings the seen bitset and first-seen offsets back in line with the counts after they
+    /// were changed in place, dropping those of sequences whose count is now 0.
+    pub(crate) fn rebuild_seen(&mut self) {
+        if let Some(seen) = &mut self.seen {
+            seen.iter_mut().for_each(|word| *word = 0);
+            self.seen_count = 0;
+            for (sequence_index, _) in self.sequence_counts.nonzero() {
+                seen[sequence_index >> 6] |= 1 << (sequence_index & 63);
+                self.seen_count += 1;
+            }
+        }
+        self.forget_unseen_offsets();
+    }
+
+    fn densify_if_profitable(&mut self) {
+        let possible_sequences = self.possible_sequences();
+        let dense_bytes = storage::dense_bytes(possible_sequences);
+        let (distinct_sequences, sparse_bytes) = match &self.sequence_counts {
+            Counts::Sparse(counts) => match self.sequence_counts.sparse_bytes() {
+                Some(sparse_bytes) if sparse_bytes >= dense_bytes => (counts.len(), sparse_bytes),
+                _ => return,
+            },
+            Counts::Dense(_) | Counts::HyperLogLog(_) | Counts::CountMin(..) => return,
+        };
+        self.sequence_counts.densify(possible_sequences);
+        self.storage_conversion = Some(StorageConversion {
+            distinct_sequences,
+            sparse_bytes,
+            dense_bytes,
+        });
+    }
+
+    /// Formats a sequence index in the counter's sequence notation, which starts as uppercase
+    /// hex digits zero-padded to the sequence length.
+    pub fn sequence_label(&self, sequence_index: usize) -> String {
+        self.sequence_notation
+            .label(sequence_index, self.sequence_length)
+    }
+
+    pub fn sequence_notation(&self) -> SequenceNotation {
+        self.sequence_notation
+    }
+
+    /// Sets how `sequence_label`, and every output built on it, writes sequences.
+    pub fn set_sequence_notation(&mut self, sequence_notation: SequenceNotation) {
+        self.sequence_notation = sequence_notation;
+    }
+
+    pub fn sequence_length(&self) -> usize {
+        self.sequence_length
+    }
+
+    pub fn possible_sequences(&self) -> usize {
+        self.bitmask + 1
+    }
+
+    /// Declares that only the digits below `radix` are fed to this counter, so the statistics
+    /// that compare against the uniform distribution spread it over the `reachable_sequences`
+    /// instead of every possible one. This doesn't stop other digits from being counted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is outside `2..=16`.
+    pub fn with_radix(mut self, radix: u32) -> DigitSequenceCounter {
+        if !(2..=16).contains(&radix) {
+            panic!("Cannot count digits in radix {}.", radix);
+        }
+        self.radix = radix;
+        self
+    }
+
+    /// Returns the radix given to `with_radix`, which is 16 otherwise.
+    pub fn radix(&self) -> u32 {
+        self.radix
+    }
+
+    /// Returns the number of sequences made only of digits below `radix()`, which is
+    /// `possible_sequences()` in radix 16.
+    pub fn reachable_sequences(&self) -> usize {
+        (self.radix as usize).pow(self.sequence_length as u32)
+    }
+
+    pub fn storage_mode(&self) -> StorageMode {
+        self.storage_mode
+    }
+
+    /// Returns whether the counts are currently held in a dense table.
+    pub fn is_dense(&self) -> bool {
+        matches!(self.sequence_counts, Counts::Dense(_))
+    }
+
+    /// Returns the number of HyperLogLog sketch bits of a sketching counter, or `None` for
+    /// counters that keep exact counts.
+    pub fn sketch_bits(&self) -> Option<usize> {
+        match &self.sequence_counts {
+            Counts::HyperLogLog(sketch) | Counts::CountMin(_, sketch) => Some(sketch.bits()),
+            Counts::Dense(_) | Counts::Sparse(_) => None,
+        }
+    }
+
+    /// Returns the `(width, depth)` of a count-min counter's sketch, or `None` for other
+    /// counters.
+    pub fn count_min_dimensions(&self) -> Option<(usize, usize)> {
+        match &self.sequence_counts {
+            Counts::CountMin(counts, _) => Some((counts.width(), counts.depth())),
+            Counts::Dense(_) | Counts::Sparse(_) | Counts::HyperLogLog(_) => None,
+        }
+    }
+
+    /// Returns how many windows a count-min counter's `count_at` can overestimate by, and the
+    /// probability that any one estimate is within that, or `None` for other counters.
+    pub fn count_error_bound(&self) -> Option<(f64, f64)> {
+        match &self.sequence_counts {
+            Counts::CountMin(counts, _) => Some(counts.error_bound()),
+            Counts::Dense(_) | Counts::Sparse(_) | Counts::HyperLogLog(_) => None,
+        }
+    }
+
+    /// Returns the relative standard error of `unique_count_estimate` for a sketching
+    /// counter, or `None` for counters whose unique count is exact.
+    pub fn unique_count_relative_error(&self) -> Option<f64> {
+        match &self.sequence_counts {
+            Counts::HyperLogLog(sketch) | Counts::CountMin(_, sketch) => {
+                Some(sketch.relative_standard_error())
+            }
+            Counts::Dense(_) | Counts::Sparse(_) => None,
+        }
+    }
+
+    /// Returns the sparse-to-dense conversion of an adaptive counter, if it has happened.
+    pub fn storage_conversion(&self) -> Option<StorageConversion> {
+        self.storage_conversion
+    }
+
+    pub fn count_at(&self, sequence_index: usize) -> u128 {
+        self.sequence_counts.get(sequence_index)
+    }
+
+    /// Returns the sum of all counts, which is the number of windows counted.
+    pub fn total_count(&self) -> u128 {
+        match &self.sequence_counts {
+            Counts::Dense(counts) => counts.iter().sum(),
+            Counts::Sparse(counts) => counts.values().sum(),
+            Counts::HyperLogLog(sketch) => sketch.total(),
+            Counts::CountMin(counts, _) => counts.total(),
+        }
+    }
+
+    /// Returns the number of sequences with a nonzero count, or the rounded estimate for a
+    /// sketching counter.
+    pub fn unique_count(&self) -> usize {
+        match &self.sequence_counts {
+            Counts::Dense(counts) => counts.iter().filter(|&&count| count != 0).count(),
+            Counts::Sparse(counts) => counts.len(),
+            Counts::HyperLogLog(_) | Counts::CountMin(..) => {
+                (self.unique_count_estimate().round() as usize).min(self.possible_sequences())
+            }
+        }
+    }
+
+    /// Returns the number of distinct sequences seen, estimated by the HyperLogLog sketch for
+    /// a sketching counter and exact otherwise.
+    pub fn unique_count_estimate(&self) -> f64 {
+        match &self.sequence_counts {
+            Counts::HyperLogLog(sketch) | Counts::CountMin(_, sketch) => sketch.estimate(),
+            Counts::Dense(_) | Counts::Sparse(_) => self.unique_count() as f64,
+        }
+    }
+
+    /// Returns the number of distinct sequences seen so far, in O(1) for counters created
+    /// with `new_with_bitset` and by scanning the counts otherwise.
+    pub fn running_unique_count(&self) -> usize {
+        match self.seen {
+            Some(_) => self.seen_count,
+            None => self.unique_count(),
+        }
+    }
+
+    /// Returns `(sequence index, count)` for every nonzero count in ascending index order,
+    /// regardless of storage.
+    pub fn nonzero_counts(&self) -> Vec<(usize, u128)> {
+        self.sequence_counts.nonzero()
+    }
+}
+
+impl AddAssign<&DigitSequenceCounter> for DigitSequenceCounter {
+    fn add_assign(&mut self, other: &DigitSequenceCounter) {
+        if self.sequence_length != other.sequence_length {
+            panic!("Cannot merge DigitSequenceCounters of different sequence lengths.");
+        }
+        match (&mut self.sequence_counts, &other.sequence_counts) {
+            (Counts::HyperLogLog(sketch), Counts::HyperLogLog(other)) => {
+                sketch.merge(other);
+                return;
+            }
+            (Counts::CountMin(counts, distinct), Counts::CountMin(other_counts, other)) => {
+                counts.merge(other_counts);
+                distinct.merge(other);
+                return;
+            }
+            (Counts::HyperLogLog(_), _)
+            | (_, Counts::HyperLogLog(_))
+            | (Counts::CountMin(..), _)
+            | (_, Counts::CountMin(..)) => {
+                panic!("Cannot merge DigitSequenceCounters with different kinds of sketch or none.")
+            }
+            _ => {}
+        }
+        for (sequence_index, count) in other.sequence_counts.nonzero() {
+            self.add_count(sequence_index, count);
+        }
+    }
+}
+
+/// Counters are equal when they have the same sequence length and the same counts, whatever
+/// their storage and window. Sketching counters are equal to sketches of the same kind in the
+/// same state, and never to exact counters.
+impl PartialEq for DigitSequenceCounter {
+    fn eq(&self, other: &DigitSequenceCounter) -> bool {
+        if self.sequence_length != other.sequence_length {
+            return false;
+        }
+        match (&self.sequence_counts, &other.sequence_counts) {
+            (Counts::HyperLogLog(sketch), Counts::HyperLogLog(other)) => sketch == other,
+            (Counts::CountMin(counts, distinct), Counts::CountMin(other_counts, other)) => {
+                counts == other_counts && distinct == other
+            }
+            (Counts::HyperLogLog(_), _)
+            | (_, Counts::HyperLogLog(_))
+            | (Counts::CountMin(..), _)
+            | (_, Counts::CountMin(..)) => false,
+            _ => self.sequence_counts.nonzero() == other.sequence_counts.nonzero(),
+        }
+    }
+}
+
+impl Display for DigitSequenceCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return write!(f, "~{} []", self.unique_count());
+        }
+        let nonzero_sequence_counts = self
+            .sequence_counts
+            .nonzero()
+            .into_iter()
+            .map(|(_, count)| count)
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "{} {:?}",
+            nonzero_sequence_counts.len(),
+            nonzero_sequence_counts.as_slice()
+        )
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::DigitSequenceCounter;
+
+/// Why a hex sequence string could not be turned into a sequence index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    WrongLength { expected: usize, found: usize },
+    NonHexCharacter { character: char, position: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::WrongLength { expected, found } => write!(
+                f,
+                "expected {} hex digits but found {} characters",
+                expected, found
+            ),
+            ParseError::NonHexCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "{:?} at position {} is not a hex digit",
+                character, position
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl DigitSequenceCounter {
+    /// Parses a string of exactly `sequence_length` hex digits, in either case, into its
+    /// sequence index.
+    pub fn sequence_string_to_index(&self, hex_str: &str) -> Result<usize, ParseError> {
+        let found = hex_str.chars().count();
+        if found != self.sequence_length {
+            return Err(ParseError::WrongLength {
+                expected: self.sequence_length,
+                found,
+            });
+        }
+        hex_str
+            .chars()
+            .enumerate()
+            .try_fold(0, |sequence_index, (position, character)| {
+                match character.to_digit(16) {
+                    Some(digit) => Ok((sequence_index << 4) | digit as usize),
+                    None => Err(ParseError::NonHexCharacter {
+                        character,
+                        position,
+                    }),
+                }
+            })
+    }
+
+    /// Returns the count of the sequence `hex_str` names, parsed as by
+    /// `sequence_string_to_index`.
+    ///
+    /// ```
+    /// use digit_sequence_counter::lookup::ParseError;
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x31415926535 x3AFE".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// assert_eq!(counter.count_at_hex_string("41"), Ok(1));
+    /// assert_eq!(counter.count_at_hex_string("77"), Ok(0));
+    /// assert_eq!(counter.count_at_hex_string("af"), Ok(1));
+    /// assert_eq!(counter.count_at_hex_string("Fe"), Ok(1));
+    /// assert_eq!(
+    ///     counter.count_at_hex_string("414"),
+    ///     Err(ParseError::WrongLength { expected: 2, found: 3 })
+    /// );
+    /// assert_eq!(
+    ///     counter.count_at_hex_string(""),
+    ///     Err(ParseError::WrongLength { expected: 2, found: 0 })
+    /// );
+    /// assert_eq!(
+    ///     counter.count_at_hex_string("4g"),
+    ///     Err(ParseError::NonHexCharacter { character: 'g', position: 1 })
+    /// );
+    /// assert_eq!(
+    ///     counter.count_at_hex_string("é1"),
+    ///     Err(ParseError::NonHexCharacter { character: 'é', position: 0 })
+    /// );
+    /// assert_eq!(counter.count_at_hex_string_or_zero("4g"), 0);
+    /// ```
+    pub fn count_at_hex_string(&self, hex_str: &str) -> Result<u128, ParseError> {
+        self.sequence_string_to_index(hex_str)
+            .map(|sequence_index| self.count_at(sequence_index))
+    }
+
+    /// Like `count_at_hex_string`, but treats any string that doesn't parse as a sequence
+    /// that was never seen.
+    pub fn count_at_hex_string_or_zero(&self, hex_str: &str) -> u128 {
+        self.count_at_hex_string(hex_str).unwrap_or(0)
+    }
+}
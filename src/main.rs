@@ -1,127 +1,2224 @@
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{self, Display};
-use std::fs::{self, File};
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use digit_sequence_counter::checksum::{self, ChecksumAlgorithm, ExpectedChecksum};
+use digit_sequence_counter::drought::DroughtTracker;
+use digit_sequence_counter::external::{self, ExternalStorage};
+use digit_sequence_counter::golden;
+use digit_sequence_counter::json;
+use digit_sequence_counter::matrix::{Matrix, SavedRun};
+use digit_sequence_counter::notation::SequenceNotation;
+use digit_sequence_counter::number_format::{self, NumberFormat};
+use digit_sequence_counter::parallel::{self, WorkerProgress};
+use digit_sequence_counter::passes::{self, Passes};
+use digit_sequence_counter::paths;
+use digit_sequence_counter::pipeline::{self, CountError, CountOptions, Report, SkipBehavior};
+use digit_sequence_counter::reporter::{CsvReporter, TextReporter};
+use digit_sequence_counter::reproducibility::{ReproducibilityBlock, UtcTimestamp};
+use digit_sequence_counter::selftest;
+use digit_sequence_counter::sniff::{self, ByteProfile};
+use digit_sequence_counter::statistics;
+use digit_sequence_counter::statistics_plan::{ResolvedStatisticsPlan, StatisticsPlan};
+use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+
+const USAGE: &str = "\
+Usage: program [options] <path to file> <maximum sequence length>
+       program matrix [matrix options] <saved report or state file>...
+       program selftest
+A path of - counts standard input.
+Options:
+    --storage MODE                     dense, sparse, adaptive, hyperloglog to only estimate
+                                       unique counts, sketch to estimate counts as well, or
+                                       external:DIR to count exactly by sorting windows in
+                                       scratch files under DIR, taking 8 bytes of disk per
+                                       window per length (default: dense)
+    --sketch-width N                   counters per row of each count-min sketch (default:
+                                       65536, or sized to fit --max-memory)
+    --sketch-depth N                   rows of each count-min sketch, from 1 to 16 (default: 4)
+    --threads N                        count with N worker threads (default: 1)
+    --chunk-size BYTES                 digits per work item when threaded (default: 64 MiB)
+    --chunks N                         split the file into N work items instead
+    --progress                         report per-worker and total throughput to stderr
+    --report-every BYTES               print a snapshot of the counts after every BYTES counted,
+                                       such as while reading a pipe that stays open
+    --flush-interval SECONDS           longest wait for the sniffed prefix before counting
+                                       what has been read (default: 1 for pipes, none for
+                                       regular files)
+    --separators CHARS                 characters to drop without breaking a sequence, such as
+                                       spaces in wrapped digit files
+    --from-start                       count from the first byte instead of after the first '.'
+    --passes auto|N                    scan the file N times, counting some lengths each time;
+                                       auto uses as few as fit --max-memory (default: 1)
+    --max-memory BYTES                 memory budget for the counts of one pass, for all
+                                       sketches with --storage sketch, or for the windows
+                                       held before spilling with --storage external
+    --max-drought LENGTH               report the longest gaps between repeats of a sequence
+    --max-drought-count K              how many droughts to report (default: 10)
+    --summary                          print a block of common statistics for each length
+    --stats PLAN                       print the statistics in PLAN for each length, a comma list
+                                       of chi2, entropy, zscores, tvd, simpson, histogram, all,
+                                       or none, each optionally @N or @N-M for some lengths,
+                                       applied in order: chi2,entropy@1-4,none@8
+    --neighborhood SEQ                 show counts of sequences one digit away from SEQ
+                                       (may be given more than once)
+    --target SEQ                       show the count of SEQ, estimated with --storage sketch
+                                       (may be given more than once)
+    --count-distribution               print how many sequences occurred each number of times
+    --transition-matrix                print the probability of each digit following each digit,
+                                       from the counts of length 2
+    --independence-test PREFIX         G-test whether the first PREFIX digits of each sequence
+                                       are independent of the rest, for each longer length
+    --periodicity                      print the strongest autocorrelation of the counts over
+                                       the sequence indices and its lag, for lengths up to 3
+    --two-sample FILE                  chi-square test whether FILE has the same distribution
+                                       of sequences as the input, for each length
+    --outliers Z                       list the sequences whose counts are more than Z standard
+                                       deviations from uniform, with their z-scores
+    --exact-zero-stats                 include the never-seen sequences in count distributions
+                                       and summaries
+    --precision DIGITS                 significant digits for statistics (default: 6)
+    --label NOTATION                   write sequences as hex, decimal, or binary digits, as
+                                       the digits of the radix sniffed from the input, or as
+                                       their packed integer value (default: radix)
+    --sniff-bytes N                    bytes to check for a digit-like profile (default: 1 MiB)
+    --strict-sniff                     fail instead of warning when the profile looks wrong
+    --fail-if-unsatisfiable            fail when the input has no run of digits long enough
+                                       for some length that shorter lengths were counted for
+    --also-decimal-subset              also count only the digits 0-9, with letters breaking
+                                       runs, and report those lengths in a second section
+    --verify-checksum SPEC             check the input against auto (a .sha256 or .md5 file next
+                                       to it), sha256:HEX, md5:HEX, or file:PATH, while counting
+    --verify-first                     verify the checksum in a separate pass before counting
+    --output-prefix PREFIX             write PREFIXlenN.csv per length and PREFIXsummary.json
+    --flamegraph                       write the counts of the longest length up to 4 as
+                                       collapsed stacks for inferno-flamegraph to
+                                       PREFIXcounts.collapsed, or counts.collapsed
+    --image PATH                       write the counts of the longest even length up to 6
+                                       to PATH as a grayscale PNG (with the image feature)
+    --dot-graph MIN_COUNT              write the transitions of the longest length up to 4
+                                       counted at least MIN_COUNT times as a Graphviz digraph
+    --output PATH                      the file for --dot-graph
+    --no-timestamps                    leave the start and finish times out of the report
+                                       trailer and summary, so reruns are byte-identical
+    --force                            overwrite output files and lift memory safety limits
+    --verbose                          log extra details to stderr
+Matrix options, for a table comparing saved text reports or serialize_to_hex state files,
+which may be given as patterns with * and ? in the file name:
+    --format table|csv|json            how to write the matrix (default: table)
+    --sort-by COLUMN                   sort rows by a column or by name, descending with a
+                                       leading -
+    --precision DIGITS                 significant digits for statistics (default: 6)
+selftest counts built-in synthetic inputs every supported way and checks them against a
+naive reference count, then checks small golden inputs against their checked-in counts,
+printing PASS or FAIL for each.
+Every run ends stderr with one line of the form
+    DSC-RESULT status=S input_bytes=N lengths=1-L elapsed=Ts output=OUT
+where S and the exit code are one of
+    ok (0), usage (1), input_error (2), partial (3), not_digits (4), checksum_mismatch (5),
+    selftest_failed (6), unsatisfiable (7), internal_error (101), interrupted (130)";
+
+/// The notation given with `--label`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Label {
+    /// The digit notation of the radix `ByteProfile::radix` finds in the sniffed bytes.
+    Radix,
+    Notation(SequenceNotation),
+}
+
+impl Label {
+    fn notation(self, profile: &ByteProfile) -> SequenceNotation {
+        match self {
+            Label::Radix => SequenceNotation::for_radix(profile.radix()),
+            Label::Notation(notation) => notation,
+        }
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Label::Radix => f.write_str("radix"),
+            Label::Notation(notation) => write!(f, "{}", notation),
+        }
+    }
+}
+
+impl FromStr for Label {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Label, String> {
+        match s {
+            "radix" => Ok(Label::Radix),
+            _ => s.parse().map(Label::Notation).map_err(|_| {
+                format!(
+                    "expected hex, decimal, binary, radix, or value, not {:?}",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+/// Where the checksum to verify the input against comes from.
+#[derive(Clone, Debug)]
+enum ChecksumSpec {
+    /// A `.sha256` or `.md5` sidecar file next to the input.
+    Auto,
+    Expected(ExpectedChecksum),
+    File(PathBuf),
+}
+
+impl Display for ChecksumSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChecksumSpec::Auto => f.write_str("auto"),
+            ChecksumSpec::Expected(expected) => write!(f, "{}", expected),
+            ChecksumSpec::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for ChecksumSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ChecksumSpec, String> {
+        match s {
+            "auto" => Ok(ChecksumSpec::Auto),
+            _ => match s.strip_prefix("file:") {
+                Some(path) => Ok(ChecksumSpec::File(PathBuf::from(path))),
+                None => s.parse().map(ChecksumSpec::Expected),
+            },
+        }
+    }
+}
+
+impl ChecksumSpec {
+    /// Finds the expected checksum for the input at `path`, reading a sidecar file if needed.
+    fn resolve(&self, path: &Path) -> Result<ExpectedChecksum, String> {
+        let file_name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let read_sidecar = |sidecar: &Path| {
+            fs::read_to_string(paths::for_opening(sidecar))
+                .map_err(|err| format!("cannot read {}: {}", sidecar.display(), err))
+                .and_then(|contents| ExpectedChecksum::from_sidecar(&contents, &file_name))
+                .map_err(|err| format!("{}: {}", sidecar.display(), err))
+        };
+        match self {
+            ChecksumSpec::Expected(expected) => Ok(expected.clone()),
+            ChecksumSpec::File(sidecar) => read_sidecar(sidecar),
+            ChecksumSpec::Auto => {
+                for algorithm in &[ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5] {
+                    let mut sidecar = path.as_os_str().to_os_string();
+                    sidecar.push(".");
+                    sidecar.push(algorithm.extension());
+                    let sidecar = PathBuf::from(sidecar);
+                    if sidecar.exists() {
+                        return read_sidecar(&sidecar);
+                    }
+                }
+                Err(format!(
+                    "--verify-checksum auto found no .sha256 or .md5 file next to {}",
+                    path.display()
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    path: PathBuf,
+    max_sequence_length: usize,
+    storage: StorageMode,
+    /// The scratch directory of `--storage external:DIR`, which counts with
+    /// `ExternalCounter`s instead of `storage`.
+    external: Option<PathBuf>,
+    sketch_width: Option<usize>,
+    sketch_depth: usize,
+    threads: usize,
+    chunk_size: usize,
+    chunks: Option<usize>,
+    progress: bool,
+    report_every: Option<u64>,
+    flush_interval: Option<Duration>,
+    separators: Vec<u8>,
+    from_start: bool,
+    passes: Passes,
+    max_memory: Option<usize>,
+    summary: bool,
+    stats: Option<StatisticsPlan>,
+    statistics_plan: Option<ResolvedStatisticsPlan>,
+    neighborhoods: Vec<String>,
+    targets: Vec<String>,
+    count_distribution: bool,
+    transition_matrix: bool,
+    independence_test: Option<usize>,
+    periodicity: bool,
+    two_sample: Option<PathBuf>,
+    outliers: Option<f64>,
+    exact_zero_stats: bool,
+    precision: usize,
+    label: Label,
+    max_drought: Option<usize>,
+    max_drought_count: usize,
+    sniff_bytes: usize,
+    strict_sniff: bool,
+    fail_if_unsatisfiable: bool,
+    also_decimal_subset: bool,
+    verify_checksum: Option<ChecksumSpec>,
+    verify_first: bool,
+    output_prefix: Option<OsString>,
+    flamegraph: bool,
+    image: Option<PathBuf>,
+    dot_graph: Option<u128>,
+    output: Option<PathBuf>,
+    no_timestamps: bool,
+    force: bool,
+    verbose: bool,
+}
+
+impl Options {
+    fn effective(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("path"), self.path.display().to_string()),
+            (
+                String::from("max_sequence_length"),
+                self.max_sequence_length.to_string(),
+            ),
+            (
+                String::from("storage"),
+                self.external.as_ref().map_or_else(
+                    || self.storage.to_string(),
+                    |dir| format!("external:{}", dir.display()),
+                ),
+            ),
+            (
+                String::from("sketch_width"),
+                self.sketch_width
+                    .map_or_else(|| String::from("none"), |width| width.to_string()),
+            ),
+            (String::from("sketch_depth"), self.sketch_depth.to_string()),
+            (String::from("threads"), self.threads.to_string()),
+            (String::from("chunk_size"), self.chunk_size.to_string()),
+            (
+                String::from("chunks"),
+                self.chunks
+                    .map_or_else(|| String::from("auto"), |chunks| chunks.to_string()),
+            ),
+            (String::from("progress"), self.progress.to_string()),
+            (
+                String::from("report_every"),
+                self.report_every
+                    .map_or_else(|| String::from("none"), |bytes| bytes.to_string()),
+            ),
+            (
+                String::from("flush_interval"),
+                self.flush_interval.map_or_else(
+                    || String::from("auto"),
+                    |interval| interval.as_secs_f64().to_string(),
+                ),
+            ),
+            (
+                String::from("separators"),
+                String::from_utf8_lossy(&self.separators)
+                    .escape_debug()
+                    .to_string(),
+            ),
+            (String::from("from_start"), self.from_start.to_string()),
+            (String::from("passes"), self.passes.to_string()),
+            (
+                String::from("max_memory"),
+                self.max_memory
+                    .map_or_else(|| String::from("none"), |bytes| bytes.to_string()),
+            ),
+            (String::from("summary"), self.summary.to_string()),
+            (
+                String::from("stats"),
+                self.stats
+                    .as_ref()
+                    .map_or_else(|| String::from("none"), |plan| plan.to_string()),
+            ),
+            (
+                String::from("neighborhoods"),
+                if self.neighborhoods.is_empty() {
+                    String::from("none")
+                } else {
+                    self.neighborhoods.join(",")
+                },
+            ),
+            (
+                String::from("targets"),
+                if self.targets.is_empty() {
+                    String::from("none")
+                } else {
+                    self.targets.join(",")
+                },
+            ),
+            (
+                String::from("count_distribution"),
+                self.count_distribution.to_string(),
+            ),
+            (
+                String::from("transition_matrix"),
+                self.transition_matrix.to_string(),
+            ),
+            (
+                String::from("independence_test"),
+                self.independence_test
+                    .map_or_else(|| String::from("none"), |prefix| prefix.to_string()),
+            ),
+            (String::from("periodicity"), self.periodicity.to_string()),
+            (
+                String::from("two_sample"),
+                self.two_sample
+                    .as_ref()
+                    .map_or_else(|| String::from("none"), |path| path.display().to_string()),
+            ),
+            (
+                String::from("outliers"),
+                self.outliers
+                    .map_or_else(|| String::from("none"), |z| z.to_string()),
+            ),
+            (
+                String::from("exact_zero_stats"),
+                self.exact_zero_stats.to_string(),
+            ),
+            (String::from("precision"), self.precision.to_string()),
+            (String::from("label"), self.label.to_string()),
+            (
+                String::from("max_drought"),
+                self.max_drought
+                    .map_or_else(|| String::from("none"), |length| length.to_string()),
+            ),
+            (
+                String::from("max_drought_count"),
+                self.max_drought_count.to_string(),
+            ),
+            (String::from("sniff_bytes"), self.sniff_bytes.to_string()),
+            (String::from("strict_sniff"), self.strict_sniff.to_string()),
+            (
+                String::from("fail_if_unsatisfiable"),
+                self.fail_if_unsatisfiable.to_string(),
+            ),
+            (
+                String::from("also_decimal_subset"),
+                self.also_decimal_subset.to_string(),
+            ),
+            (
+                String::from("verify_checksum"),
+                self.verify_checksum
+                    .as_ref()
+                    .map_or_else(|| String::from("none"), |spec| spec.to_string()),
+            ),
+            (String::from("verify_first"), self.verify_first.to_string()),
+            (
+                String::from("output_prefix"),
+                self.output_prefix.as_ref().map_or_else(
+                    || String::from("none"),
+                    |prefix| prefix.to_string_lossy().into_owned(),
+                ),
+            ),
+            (String::from("flamegraph"), self.flamegraph.to_string()),
+            (
+                String::from("image"),
+                self.image
+                    .as_ref()
+                    .map_or_else(|| String::from("none"), |path| path.display().to_string()),
+            ),
+            (
+                String::from("dot_graph"),
+                self.dot_graph
+                    .map_or_else(|| String::from("none"), |min_count| min_count.to_string()),
+            ),
+            (
+                String::from("output"),
+                self.output
+                    .as_ref()
+                    .map_or_else(|| String::from("none"), |path| path.display().to_string()),
+            ),
+            (
+                String::from("no_timestamps"),
+                self.no_timestamps.to_string(),
+            ),
+            (String::from("force"), self.force.to_string()),
+            (String::from("verbose"), self.verbose.to_string()),
+        ]
+    }
+}
+
+/// How a run ended, as both its exit code and the `status` of its `DSC-RESULT` trailer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Usage,
+    InputError,
+    /// The report or some output files could not be written in full.
+    Partial,
+    NotDigits,
+    ChecksumMismatch,
+    SelfTestFailed,
+    /// Some length counted nothing because no run of digits was long enough for it.
+    Unsatisfiable,
+    InternalError,
+    Interrupted,
+}
+
+impl Status {
+    fn code(self) -> i32 {
+        match self {
+            Status::Ok => 0,
+            Status::Usage => 1,
+            Status::InputError => 2,
+            Status::Partial => 3,
+            Status::NotDigits => 4,
+            Status::ChecksumMismatch => 5,
+            Status::SelfTestFailed => 6,
+            Status::Unsatisfiable => 7,
+            Status::InternalError => 101,
+            Status::Interrupted => 130,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Usage => "usage",
+            Status::InputError => "input_error",
+            Status::Partial => "partial",
+            Status::NotDigits => "not_digits",
+            Status::ChecksumMismatch => "checksum_mismatch",
+            Status::SelfTestFailed => "selftest_failed",
+            Status::Unsatisfiable => "unsatisfiable",
+            Status::InternalError => "internal_error",
+            Status::Interrupted => "interrupted",
+        }
+    }
+}
+
+static RUN_STARTED: OnceLock<Instant> = OnceLock::new();
+static INPUT_BYTES: AtomicU64 = AtomicU64::new(0);
+/// The lengths and output destination for the trailer, once the arguments are parsed.
+static RUN_TARGET: Mutex<Option<(usize, String)>> = Mutex::new(None);
+
+/// Ends the process with `status`, writing the `DSC-RESULT` trailer as the last line of
+/// stderr. Every exit goes through here, including Ctrl-C and panics. Whichever thread gets
+/// here first writes the trailer and exits while holding the stderr lock, so no other output
+/// can follow it and any later caller just waits for the exit. Exiting skips destructors, so
+/// external storage's scratch directories are removed here first.
+fn finish(status: Status) -> ! {
+    static FINISHING: AtomicBool = AtomicBool::new(false);
+    if FINISHING.swap(true, Ordering::SeqCst) {
+        loop {
+            thread::park();
+        }
+    }
+
+    let elapsed = RUN_STARTED
+        .get()
+        .map_or(0.0, |started| started.elapsed().as_secs_f64());
+    let target = RUN_TARGET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    let (lengths, output) = match target {
+        Some((max_sequence_length, output)) => (format!("1-{}", max_sequence_length), output),
+        None => (String::from("none"), String::from("none")),
+    };
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    // Removed with stderr held, so a counter that fails on its missing files can't report it.
+    external::remove_scratch_dirs();
+    let _ = writeln!(
+        stderr,
+        "DSC-RESULT status={} input_bytes={} lengths={} elapsed={:.1}s output={}",
+        status.name(),
+        INPUT_BYTES.load(Ordering::Relaxed),
+        lengths,
+        elapsed,
+        output
+    );
+    process::exit(status.code());
+}
+
+/// Passes reads through, adding the bytes read to the trailer's `input_bytes`.
+struct TallyReader<R>(R);
+
+/// The input being counted: a file, or standard input for a path of `-`.
+enum Input {
+    File(File),
+    Stdin(io::Stdin),
+}
+
+impl Input {
+    fn open(path: &Path) -> io::Result<Input> {
+        if path.as_os_str() == "-" {
+            Ok(Input::Stdin(io::stdin()))
+        } else {
+            paths::open(path).map(Input::File)
+        }
+    }
+
+    /// Returns the file's metadata, or an error for standard input, which is treated like
+    /// any other input that isn't a regular file.
+    fn metadata(&self) -> io::Result<fs::Metadata> {
+        match self {
+            Input::File(file) => file.metadata(),
+            Input::Stdin(_) => Err(io::Error::other("standard input has no metadata")),
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            Input::Stdin(stdin) => stdin.read(buf),
+        }
+    }
+}
+
+impl<R: Read> Read for TallyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        INPUT_BYTES.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
 
 fn main() {
-    let (path, max_sequence_length) = match process_args() {
-        Ok(args) => args,
+    RUN_STARTED.get_or_init(Instant::now);
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_panic_hook(info);
+        finish(Status::InternalError);
+    }));
+    let _ = ctrlc::set_handler(|| finish(Status::Interrupted));
+
+    match env::args_os().nth(1) {
+        Some(arg) if arg == "matrix" => run_matrix(),
+        Some(arg) if arg == "selftest" => run_selftest(),
+        _ => {}
+    }
+
+    let options = match process_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    };
+    *RUN_TARGET.lock().unwrap() = Some((
+        options.max_sequence_length,
+        options.output_prefix.as_ref().map_or_else(
+            || String::from("stdout"),
+            |prefix| prefix.to_string_lossy().into_owned(),
+        ),
+    ));
+
+    if let (Some(prefix), false) = (&options.output_prefix, options.force) {
+        let mut suffixes = (1..=options.max_sequence_length)
+            .map(|sequence_length| length_file_suffix(sequence_length, false))
+            .collect::<Vec<_>>();
+        if options.also_decimal_subset {
+            suffixes.extend(
+                (1..=options.max_sequence_length)
+                    .map(|sequence_length| length_file_suffix(sequence_length, true)),
+            );
+        }
+        suffixes.push(String::from(SUMMARY_FILE_SUFFIX));
+        for suffix in suffixes {
+            let path = output_path(prefix, &suffix);
+            if path.exists() {
+                eprintln!(
+                    "Error: output file {} already exists; use --force to overwrite",
+                    path.display()
+                );
+                finish(Status::Usage);
+            }
+        }
+    }
+
+    let plan = match passes::plan_passes(
+        1..=options.max_sequence_length,
+        options.storage,
+        options.passes,
+        // Sketches were already sized to fit the budget in one pass, and external storage
+        // sizes its runs to fit it.
+        options
+            .max_memory
+            .filter(|_| options.storage != StorageMode::Sketch && options.external.is_none()),
+    ) {
+        Ok(plan) => plan,
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    };
+
+    let expected_checksum = match &options.verify_checksum {
+        Some(spec) => match spec.resolve(&options.path) {
+            Ok(expected) => Some(expected),
+            Err(err) => {
+                eprintln!("{}\nError: {}", USAGE, err);
+                finish(Status::Usage);
+            }
+        },
+        None => None,
+    };
+    if let (Some(expected), true) = (&expected_checksum, options.verify_first) {
+        verify_first(&options.path, expected, options.verbose);
+    }
+
+    if let (Some(plan), true) = (&options.statistics_plan, options.verbose) {
+        eprintln!("Statistics plan:");
+        for line in plan.to_string().lines() {
+            eprintln!("  {}", line);
+        }
+    }
+
+    if let Some(scratch_dir) = &options.external {
+        run_external(&options, scratch_dir, expected_checksum.as_ref());
+    }
+
+    let started = UtcTimestamp::now();
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let number_format = NumberFormat::new(options.precision);
+    let mut drought_tracker = None;
+    let mut length_summaries = Vec::new();
+    let mut file_failures = 0;
+    let mut input_size = 0;
+    let mut content_hash = None;
+    let mut sequence_notation = SequenceNotation::Hex;
+    let mut counted_shorter = false;
+    let mut unsatisfiable = false;
+
+    for (pass, sequence_lengths) in plan.iter().enumerate() {
+        let file = match Input::open(&options.path) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("Error opening {}: {}", options.path.display(), err);
+                finish(Status::InputError);
+            }
+        };
+        let metadata = file.metadata();
+        if plan.len() > 1 {
+            if !metadata.as_ref().is_ok_and(|metadata| metadata.is_file()) {
+                eprintln!(
+                    "Error: {} passes need to read the input more than once, but {} is not a regular file; save the input to a file first",
+                    plan.len(),
+                    options.path.display()
+                );
+                finish(Status::Usage);
+            }
+            if sequence_lengths.start() == sequence_lengths.end() {
+                eprintln!(
+                    "Pass {}/{}: length {}",
+                    pass + 1,
+                    plan.len(),
+                    sequence_lengths.start()
+                );
+            } else {
+                eprintln!(
+                    "Pass {}/{}: lengths {}-{}",
+                    pass + 1,
+                    plan.len(),
+                    sequence_lengths.start(),
+                    sequence_lengths.end()
+                );
+            }
+        }
+        let chunk_size = match (options.chunks, &metadata) {
+            (Some(chunks), Ok(metadata)) => (metadata.len() as usize).div_ceil(chunks).max(1),
+            _ => options.chunk_size,
+        };
+
+        INPUT_BYTES.store(0, Ordering::Relaxed);
+        let mut reader = TallyReader(file);
+        let sniffed = if pass == 0 {
+            let flush_interval = options.flush_interval.or_else(|| {
+                Some(PIPE_FLUSH_INTERVAL)
+                    .filter(|_| !metadata.as_ref().is_ok_and(|metadata| metadata.is_file()))
+            });
+            let (sniffed, profile) = sniff_input(&mut reader, &options, flush_interval);
+            sequence_notation = options.label.notation(&profile);
+            if options.verbose {
+                eprintln!("Labeling sequences in {} notation", sequence_notation);
+            }
+            sniffed
+        } else {
+            Vec::new()
+        };
+        let input = io::Cursor::new(sniffed).chain(reader);
+        let count_options = CountOptions {
+            sequence_lengths: sequence_lengths.clone(),
+            storage: options.storage,
+            sketch_width: options
+                .sketch_width
+                .unwrap_or(DigitSequenceCounter::DEFAULT_SKETCH_WIDTH),
+            sketch_depth: options.sketch_depth,
+            skip: if options.from_start {
+                SkipBehavior::None
+            } else {
+                SkipBehavior::ThroughFirstPoint
+            },
+            separators: options.separators.clone(),
+            threads: options.threads,
+            chunk_size,
+            drought_length: options.max_drought.filter(|_| pass == 0),
+            checksum: expected_checksum
+                .as_ref()
+                .filter(|_| pass == 0 && !options.verify_first)
+                .map(|expected| expected.algorithm),
+            decimal_subset: options.also_decimal_subset,
+        };
+
+        let mut report = count_pass(input, &mut out, &options, &count_options);
+        let second_sample = match &options.two_sample {
+            Some(path) => count_second_sample(path, &count_options),
+            None => Vec::new(),
+        };
+        if let (Some(expected), Some(checksum)) = (&expected_checksum, &report.checksum) {
+            check_checksum(expected, checksum, options.verbose);
+        }
+        if pass == 0 {
+            drought_tracker = report.drought_tracker.take();
+            if let Some(drought_tracker) = &mut drought_tracker {
+                drought_tracker.set_sequence_notation(sequence_notation);
+            }
+        }
+        input_size = report.input_bytes;
+        content_hash = report.content_hash.take();
+        let mut sequence_counters = report.counters;
+        for sequence_counter in &sequence_counters {
+            if sequence_counter.total_count() != 0 {
+                counted_shorter = true;
+            } else if counted_shorter {
+                eprintln!(
+                    "Note: input too short for length {}: needs at least {} consecutive valid digits, longest run seen was {}",
+                    sequence_counter.sequence_length(),
+                    sequence_counter.sequence_length() + 1,
+                    report.longest_run
+                );
+                unsatisfiable = true;
+            }
+        }
+        for sequence_counter in &mut sequence_counters {
+            sequence_counter.set_sequence_notation(sequence_notation);
+        }
+
+        if options.verbose {
+            sequence_counters.iter().for_each(|sequence_counter| {
+                if let Some(conversion) = sequence_counter.storage_conversion() {
+                    eprintln!(
+                        "Length {}: converted sparse storage ({} sequences, ~{} bytes) to dense ({} bytes)",
+                        sequence_counter.sequence_length(),
+                        conversion.distinct_sequences,
+                        conversion.sparse_bytes,
+                        conversion.dense_bytes
+                    );
+                }
+            });
+        }
+
+        match write_length_sections(
+            &mut out,
+            &options,
+            sequence_counters,
+            &second_sample,
+            false,
+            &number_format,
+            &mut length_summaries,
+        ) {
+            Ok(failures) => file_failures += failures,
+            Err(err) => {
+                eprintln!("Error writing report: {}", err);
+                finish(Status::Partial);
+            }
+        }
+
+        if !report.decimal_subset.is_empty() {
+            let mut decimal_subset = report.decimal_subset;
+            for sequence_counter in &mut decimal_subset {
+                sequence_counter.set_sequence_notation(sequence_notation);
+            }
+            let written = writeln!(out, "{}", DECIMAL_SUBSET_HEADING).and_then(|()| {
+                write_length_sections(
+                    &mut out,
+                    &options,
+                    decimal_subset,
+                    &[],
+                    true,
+                    &number_format,
+                    &mut length_summaries,
+                )
+            });
+            match written {
+                Ok(failures) => file_failures += failures,
+                Err(err) => {
+                    eprintln!("Error writing report: {}", err);
+                    finish(Status::Partial);
+                }
+            }
+        }
+    }
+
+    let (started, finished) = if options.no_timestamps {
+        (None, None)
+    } else {
+        (Some(started), Some(UtcTimestamp::now()))
+    };
+    let reproducibility = ReproducibilityBlock {
+        options: options.effective(),
+        input_size,
+        content_hash,
+        started,
+        finished,
+    };
+
+    let written = write_run_sections(
+        &mut out,
+        &options,
+        drought_tracker.as_ref(),
+        &reproducibility,
+        &number_format,
+        &length_summaries,
+    );
+    finish_report(written, file_failures, unsatisfiable, &options);
+}
+
+/// Exits after the run sections were written, with `written`'s file failures added to the
+/// `file_failures` from the lengths.
+fn finish_report(
+    written: io::Result<usize>,
+    file_failures: usize,
+    unsatisfiable: bool,
+    options: &Options,
+) -> ! {
+    match written {
+        Ok(failures) if failures + file_failures == 0 => {
+            if unsatisfiable && options.fail_if_unsatisfiable {
+                finish(Status::Unsatisfiable);
+            }
+            finish(Status::Ok)
+        }
+        Ok(_) => finish(Status::Partial),
         Err(err) => {
+            eprintln!("Error writing report: {}", err);
+            finish(Status::Partial);
+        }
+    }
+}
+
+/// Counts every length in one pass with `--storage external`, streaming each length's
+/// counts from its sorted runs into the report.
+fn run_external(
+    options: &Options,
+    scratch_dir: &Path,
+    expected_checksum: Option<&ExpectedChecksum>,
+) -> ! {
+    let started = UtcTimestamp::now();
+    let file = match Input::open(&options.path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Error opening {}: {}", options.path.display(), err);
+            finish(Status::InputError);
+        }
+    };
+    let metadata = file.metadata();
+    let is_file = metadata.as_ref().is_ok_and(|metadata| metadata.is_file());
+
+    let mut storage = ExternalStorage::new(scratch_dir);
+    if let Some(max_memory) = options.max_memory {
+        storage.run_windows = (max_memory
+            / (external::BYTES_PER_WINDOW as usize * options.max_sequence_length.max(1)))
+        .max(1);
+    }
+    match &metadata {
+        Ok(metadata) if is_file => eprintln!(
+            "External storage: up to {} bytes of scratch space in {} for {} lengths",
+            storage
+                .scratch_bytes_needed(metadata.len())
+                .saturating_mul(options.max_sequence_length as u64),
+            scratch_dir.display(),
+            options.max_sequence_length
+        ),
+        _ => eprintln!(
+            "External storage: up to {} bytes of scratch space in {} per input byte",
+            2 * external::BYTES_PER_WINDOW * options.max_sequence_length as u64,
+            scratch_dir.display()
+        ),
+    }
+
+    INPUT_BYTES.store(0, Ordering::Relaxed);
+    let mut reader = TallyReader(file);
+    let flush_interval = options
+        .flush_interval
+        .or_else(|| Some(PIPE_FLUSH_INTERVAL).filter(|_| !is_file));
+    let (sniffed, profile) = sniff_input(&mut reader, options, flush_interval);
+    let sequence_notation = options.label.notation(&profile);
+    if options.verbose {
+        eprintln!("Labeling sequences in {} notation", sequence_notation);
+    }
+    let input = io::Cursor::new(sniffed).chain(reader);
+    let mut count_options = CountOptions::new(1..=options.max_sequence_length);
+    if options.from_start {
+        count_options.skip = SkipBehavior::None;
+    }
+    count_options.separators = options.separators.clone();
+    count_options.checksum = expected_checksum
+        .filter(|_| !options.verify_first)
+        .map(|expected| expected.algorithm);
+
+    let mut report = match pipeline::count_digits_external(input, &count_options, &storage) {
+        Ok(report) => report,
+        Err(CountError::Io(err)) => {
+            eprintln!("Error counting with external storage: {}", err);
+            finish(Status::InputError);
+        }
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    };
+    if let (Some(expected), Some(checksum)) = (expected_checksum, &report.checksum) {
+        check_checksum(expected, checksum, options.verbose);
+    }
+    if options.verbose {
+        for counts in &report.counts {
+            eprintln!(
+                "Length {}: {} bytes of sorted runs",
+                counts.sequence_length(),
+                counts.scratch_bytes()
+            );
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let number_format = NumberFormat::new(options.precision);
+    let mut length_summaries = Vec::new();
+    let mut file_failures = 0;
+    let mut counted_shorter = false;
+    let mut unsatisfiable = false;
+    for counts in &mut report.counts {
+        if counts.total_count() != 0 {
+            counted_shorter = true;
+        } else if counted_shorter {
             eprintln!(
-                "Usage: program <path to file> <maximum sequence length>\nError: {}",
-                err
+                "Note: input too short for length {}: needs at least {} consecutive valid digits, longest run seen was {}",
+                counts.sequence_length(),
+                counts.sequence_length() + 1,
+                report.longest_run
             );
-            process::exit(1);
+            unsatisfiable = true;
+        }
+        counts.set_sequence_notation(sequence_notation);
+
+        let written = match &options.output_prefix {
+            Some(prefix) => {
+                let path =
+                    output_path(prefix, &length_file_suffix(counts.sequence_length(), false));
+                let written = create_output_file(&path, options.force).and_then(|mut file| {
+                    counts.report_to(&mut CsvReporter::new(&mut file))?;
+                    file.flush()
+                });
+                if let Err(err) = written {
+                    eprintln!("Error writing {}: {}", path.display(), err);
+                    file_failures += 1;
+                }
+                length_summaries.push(LengthSummary {
+                    sequence_length: counts.sequence_length(),
+                    path,
+                    possible_sequences: counts.possible_sequences(),
+                    unique_sequences: counts.unique_count(),
+                    total_count: counts.total_count(),
+                    coverage: counts.coverage(),
+                    most_common: counts.most_common(),
+                    sequence_notation,
+                    decimal_subset: false,
+                });
+                Ok(())
+            }
+            None => counts
+                .report_to(&mut TextReporter::new(&mut out))
+                .and_then(|()| out.flush()),
+        };
+        if let Err(err) = written {
+            eprintln!("Error writing report: {}", err);
+            finish(Status::Partial);
         }
+    }
+
+    let (started, finished) = if options.no_timestamps {
+        (None, None)
+    } else {
+        (Some(started), Some(UtcTimestamp::now()))
+    };
+    let reproducibility = ReproducibilityBlock {
+        options: options.effective(),
+        input_size: report.input_bytes,
+        content_hash: report.content_hash.take(),
+        started,
+        finished,
     };
+    let written = write_run_sections(
+        &mut out,
+        options,
+        None,
+        &reproducibility,
+        &number_format,
+        &length_summaries,
+    );
+    finish_report(written, file_failures, unsatisfiable, options);
+}
 
-    let mut sequence_counters = Vec::with_capacity(max_sequence_length);
-    for sequence_length in 1..=max_sequence_length {
-        sequence_counters.push(DigitSequenceCounter::new(sequence_length));
+/// Hashes the whole input at `path` before counting starts, exiting on a mismatch.
+fn verify_first(path: &Path, expected: &ExpectedChecksum, verbose: bool) {
+    let file = match Input::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Error opening {}: {}", path.display(), err);
+            finish(Status::InputError);
+        }
+    };
+    if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        eprintln!(
+            "{}\nError: --verify-first needs to read the input twice, but {} is not a regular file",
+            USAGE,
+            path.display()
+        );
+        finish(Status::Usage);
+    }
+    match checksum::checksum_of(BufReader::new(file), expected.algorithm) {
+        Ok(checksum) => check_checksum(expected, &checksum, verbose),
+        Err(err) => {
+            eprintln!("Error reading file path: {}", err);
+            finish(Status::InputError);
+        }
     }
+}
 
-    let file = match File::open(&path) {
+/// Exits before anything is reported if `checksum` isn't the expected one.
+fn check_checksum(expected: &ExpectedChecksum, checksum: &str, verbose: bool) {
+    if checksum != expected.hex {
+        eprintln!(
+            "Error: {} checksum mismatch: expected {}, found {}",
+            expected.algorithm, expected.hex, checksum
+        );
+        finish(Status::ChecksumMismatch);
+    }
+    if verbose {
+        eprintln!("Verified {} checksum {}", expected.algorithm, checksum);
+    }
+}
+
+/// Reads the start of the input and checks that it looks like a digit file, warning or, with
+/// `--strict-sniff`, exiting if not. Reading stops early after `flush_interval`, if given.
+/// Returns the bytes read so they can be counted too, along with their profile.
+fn sniff_input<R: Read>(
+    reader: &mut R,
+    options: &Options,
+    flush_interval: Option<Duration>,
+) -> (Vec<u8>, ByteProfile) {
+    let sniffed = match flush_interval {
+        Some(interval) => sniff::read_prefix_within(reader, options.sniff_bytes, interval),
+        None => sniff::read_prefix(reader, options.sniff_bytes),
+    };
+    let sniffed = match sniffed {
+        Ok(sniffed) => sniffed,
+        Err(err) => {
+            eprintln!("Error reading file path: {}", err);
+            finish(Status::InputError);
+        }
+    };
+    let profile = ByteProfile::from_bytes(&sniffed);
+    if let Some(diagnosis) = profile.diagnosis() {
+        if options.strict_sniff {
+            eprintln!(
+                "Error: input does not look like a digit file ({}); it {}",
+                profile, diagnosis
+            );
+            finish(Status::NotDigits);
+        }
+        eprintln!(
+            "Warning: input does not look like a digit file ({}); it {}",
+            profile, diagnosis
+        );
+    }
+    (sniffed, profile)
+}
+
+/// How long sniffing waits on an input that isn't a regular file before counting what it has
+/// read, without `--flush-interval`.
+const PIPE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts one pass through `pipeline::count_digits_with_progress`, reporting progress and
+/// worker statistics to stderr as requested, and writing snapshots to `out` with
+/// `--report-every`.
+fn count_pass<R: Read, W: Write>(
+    input: R,
+    out: &mut W,
+    options: &Options,
+    count_options: &CountOptions,
+) -> Report {
+    let workers = (0..options.threads)
+        .map(|_| WorkerProgress::new())
+        .collect::<Vec<_>>();
+    let counting_done = AtomicBool::new(false);
+    let report = thread::scope(|scope| {
+        if options.progress {
+            scope.spawn(|| report_progress(&workers, &counting_done));
+        }
+        let report = match options.report_every {
+            Some(report_every) => pipeline::count_digits_with_snapshots(
+                input,
+                count_options,
+                &workers,
+                report_every,
+                |counted, sequence_counters| {
+                    if let Err(err) = write_snapshot(&mut *out, counted, sequence_counters) {
+                        eprintln!("Error writing report: {}", err);
+                        finish(Status::Partial);
+                    }
+                },
+            ),
+            None => pipeline::count_digits_with_progress(input, count_options, &workers),
+        };
+        counting_done.store(true, Ordering::Relaxed);
+        report
+    });
+    if options.progress || (options.verbose && options.threads > 1) {
+        workers.iter().enumerate().for_each(|(worker, progress)| {
+            eprintln!(
+                "Worker {}: {} chunks, {} bytes, busy {:.3}s",
+                worker,
+                progress.chunks(),
+                progress.bytes(),
+                progress.busy().as_secs_f64()
+            );
+        });
+    }
+    match report {
+        Ok(report) => report,
+        Err(CountError::Io(err)) => {
+            eprintln!("Error reading file path: {}", err);
+            finish(Status::InputError);
+        }
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    }
+}
+
+/// Counts the file of `--two-sample` for the lengths of `count_options`, without the
+/// extras that only apply to the input.
+fn count_second_sample(path: &Path, count_options: &CountOptions) -> Vec<DigitSequenceCounter> {
+    let file = match Input::open(path) {
         Ok(file) => file,
         Err(err) => {
-            eprintln!("Error opening file path: {}", err);
-            process::exit(2);
-        }
-    };
-    BufReader::with_capacity(65536, file)
-        .bytes()
-        .map(|byte| byte.expect("Error reading file path"))
-        .skip_while(|byte| *byte != b'.')
-        .skip(1)
-        .for_each(|byte| {
-            sequence_counters.iter_mut().for_each(|sequence_counter| {
-                sequence_counter.process_character(byte);
-            })
+            eprintln!("Error opening {}: {}", path.display(), err);
+            finish(Status::InputError);
+        }
+    };
+    let count_options = CountOptions {
+        drought_length: None,
+        checksum: None,
+        decimal_subset: false,
+        ..count_options.clone()
+    };
+    match pipeline::count_digits(file, &count_options) {
+        Ok(report) => report.counters,
+        Err(CountError::Io(err)) => {
+            eprintln!("Error reading {}: {}", path.display(), err);
+            finish(Status::InputError);
+        }
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    }
+}
+
+/// Writes the counts so far as a heading and one `unique [counts]` line per length, then
+/// flushes them so a reader of a pipe sees them right away.
+fn write_snapshot<W: Write>(
+    out: &mut W,
+    counted: u64,
+    sequence_counters: &[DigitSequenceCounter],
+) -> io::Result<()> {
+    writeln!(out, "Snapshot after {} bytes:", counted)?;
+    let mut reporter = TextReporter::new(&mut *out);
+    for sequence_counter in sequence_counters {
+        sequence_counter.report_to(&mut reporter)?;
+    }
+    out.flush()
+}
+
+/// Writes each length's section as soon as it is formatted, in ascending length order, and
+/// flushes it before moving on, so an interrupted run still leaves whole sections behind.
+/// Counters are consumed as they are written, freeing each pass's tables before the next.
+///
+/// With an output prefix, each length's counts go to their own file instead and its entry
+/// for the run summary is collected in `length_summaries`. A file that can't be written is
+/// reported and skipped, and the number of such files is returned.
+///
+/// The counters of `--also-decimal-subset` are written the same way, marked with
+/// `decimal_subset`: their files are named `PREFIXdecimal_lenN.csv`, and they never go to the
+/// flamegraph file, the transition graph, or the image, which hold the hex counts.
+fn write_length_sections<W: Write>(
+    out: &mut W,
+    options: &Options,
+    sequence_counters: Vec<DigitSequenceCounter>,
+    second_sample: &[DigitSequenceCounter],
+    decimal_subset: bool,
+    number_format: &NumberFormat,
+    length_summaries: &mut Vec<LengthSummary>,
+) -> io::Result<usize> {
+    let mut file_failures = 0;
+    for sequence_counter in sequence_counters {
+        match &options.output_prefix {
+            Some(prefix) => {
+                let path = output_path(
+                    prefix,
+                    &length_file_suffix(sequence_counter.sequence_length(), decimal_subset),
+                );
+                if let Err(err) = write_length_csv(&path, options.force, &sequence_counter) {
+                    eprintln!("Error writing {}: {}", path.display(), err);
+                    file_failures += 1;
+                }
+                length_summaries.push(LengthSummary {
+                    sequence_length: sequence_counter.sequence_length(),
+                    path,
+                    possible_sequences: sequence_counter.possible_sequences(),
+                    unique_sequences: sequence_counter.unique_count(),
+                    total_count: sequence_counter.total_count(),
+                    coverage: sequence_counter.coverage(),
+                    most_common: sequence_counter.top_n(1).first().copied(),
+                    sequence_notation: sequence_counter.sequence_notation(),
+                    decimal_subset,
+                });
+            }
+            None => sequence_counter.report_to(&mut TextReporter::new(&mut *out))?,
+        }
+        if options.flamegraph
+            && !decimal_subset
+            && sequence_counter.sequence_length()
+                == options
+                    .max_sequence_length
+                    .min(DigitSequenceCounter::FLAMEGRAPH_LENGTH_LIMIT)
+        {
+            let path = match &options.output_prefix {
+                Some(prefix) => output_path(prefix, FLAMEGRAPH_FILE_SUFFIX),
+                None => PathBuf::from(FLAMEGRAPH_FILE_SUFFIX),
+            };
+            if let Err(err) = write_flamegraph(&path, options.force, &sequence_counter) {
+                eprintln!("Error writing {}: {}", path.display(), err);
+                file_failures += 1;
+            }
+        }
+        if let (Some(min_count), Some(path)) = (options.dot_graph, &options.output) {
+            if !decimal_subset
+                && sequence_counter.sequence_length()
+                    == options
+                        .max_sequence_length
+                        .min(DigitSequenceCounter::DOT_GRAPH_LENGTH_LIMIT)
+            {
+                if let Err(err) = write_dot_graph(path, options.force, &sequence_counter, min_count)
+                {
+                    eprintln!("Error writing {}: {}", path.display(), err);
+                    file_failures += 1;
+                }
+            }
+        }
+        #[cfg(feature = "image")]
+        if let Some(path) = &options.image {
+            let image_length = options
+                .max_sequence_length
+                .min(DigitSequenceCounter::IMAGE_LENGTH_LIMIT)
+                & !1;
+            if !decimal_subset && sequence_counter.sequence_length() == image_length {
+                if let Err(err) = write_image(path, options.force, &sequence_counter) {
+                    eprintln!("Error writing {}: {}", path.display(), err);
+                    file_failures += 1;
+                }
+            }
+        }
+        if sequence_counter.count_min_dimensions().is_some() {
+            write_sketch_estimates(out, &sequence_counter, number_format)?;
+        }
+        if options
+            .targets
+            .iter()
+            .any(|target| target.len() == sequence_counter.sequence_length())
+        {
+            write_targets(out, &sequence_counter, &options.targets)?;
+        }
+        let statistics = options.statistics_plan.as_ref().map_or(&[][..], |plan| {
+            plan.for_length(sequence_counter.sequence_length())
         });
+        if !statistics.is_empty() {
+            writeln!(
+                out,
+                "Statistics for length {}:",
+                sequence_counter.sequence_length()
+            )?;
+            for statistic in statistics {
+                statistic.write(out, &sequence_counter, number_format)?;
+            }
+        }
+        if options.summary {
+            sequence_counter.print_summary_with(out, number_format)?;
+            if options.exact_zero_stats {
+                writeln!(
+                    out,
+                    "  {:<20}{}",
+                    "Zero bucket:",
+                    sequence_counter.zero_bucket()
+                )?;
+            }
+        }
+        for target in &options.neighborhoods {
+            if target.len() == sequence_counter.sequence_length() {
+                write_neighborhood(out, &sequence_counter, target, number_format)?;
+            }
+        }
+        if options.count_distribution {
+            write_count_distribution(out, &sequence_counter, options.exact_zero_stats)?;
+        }
+        if options.transition_matrix && sequence_counter.sequence_length() == 2 {
+            write_transition_matrix(out, &sequence_counter, number_format)?;
+        }
+        if let Some(prefix_length) = options
+            .independence_test
+            .filter(|&prefix_length| prefix_length < sequence_counter.sequence_length())
+        {
+            write_independence_test(out, &sequence_counter, prefix_length, number_format)?;
+        }
+        if options.periodicity
+            && sequence_counter.sequence_length() <= DigitSequenceCounter::PERIODICITY_LENGTH_LIMIT
+        {
+            write_periodicity(out, &sequence_counter, number_format)?;
+        }
+        if let Some(z_threshold) = options.outliers {
+            write_outliers(out, &sequence_counter, z_threshold, number_format)?;
+        }
+        if let (Some(path), Some(second_counter)) = (
+            &options.two_sample,
+            second_sample.iter().find(|second_counter| {
+                second_counter.sequence_length() == sequence_counter.sequence_length()
+            }),
+        ) {
+            write_two_sample_test(out, &sequence_counter, second_counter, path, number_format)?;
+        }
+        out.flush()?;
+    }
 
-    sequence_counters.into_iter().for_each(|sequence_counter| {
-        println!("{}", sequence_counter);
-    })
+    Ok(file_failures)
 }
 
-#[derive(Debug)]
-struct DigitSequenceCounter {
+/// Writes the run-level sections after every length: droughts, then either the summary file
+/// for an output prefix or the reproducibility block. Returns the number of files that
+/// couldn't be written.
+fn write_run_sections<W: Write>(
+    out: &mut W,
+    options: &Options,
+    drought_tracker: Option<&DroughtTracker>,
+    reproducibility: &ReproducibilityBlock,
+    number_format: &NumberFormat,
+    length_summaries: &[LengthSummary],
+) -> io::Result<usize> {
+    let mut file_failures = 0;
+    if let Some(drought_tracker) = drought_tracker {
+        drought_tracker.write_longest(out, options.max_drought_count)?;
+    }
+
+    match &options.output_prefix {
+        Some(prefix) => {
+            let path = output_path(prefix, SUMMARY_FILE_SUFFIX);
+            if let Err(err) = write_summary_json(
+                &path,
+                options.force,
+                length_summaries,
+                reproducibility,
+                number_format,
+            ) {
+                eprintln!("Error writing {}: {}", path.display(), err);
+                file_failures += 1;
+            }
+        }
+        None => writeln!(out, "{}", reproducibility)?,
+    }
+    out.flush()?;
+    Ok(file_failures)
+}
+
+const SUMMARY_FILE_SUFFIX: &str = "summary.json";
+const DECIMAL_SUBSET_HEADING: &str = "Decimal subset (digits 0-9 only, letters break runs):";
+const FLAMEGRAPH_FILE_SUFFIX: &str = "counts.collapsed";
+
+struct LengthSummary {
     sequence_length: usize,
-    current_sequence: usize,
-    sequence_counts: Vec<u128>,
-    bitmask: usize,
-    stalled_for: usize,
+    path: PathBuf,
+    possible_sequences: usize,
+    unique_sequences: usize,
+    total_count: u128,
+    coverage: f64,
+    /// The most common sequence's index and count, if any were counted exactly.
+    most_common: Option<(usize, u128)>,
+    sequence_notation: SequenceNotation,
+    /// Whether this is a length of `--also-decimal-subset`.
+    decimal_subset: bool,
+}
+
+fn length_file_suffix(sequence_length: usize, decimal_subset: bool) -> String {
+    if decimal_subset {
+        format!("decimal_len{}.csv", sequence_length)
+    } else {
+        format!("len{}.csv", sequence_length)
+    }
 }
 
-impl DigitSequenceCounter {
-    pub const LARGEST_SEQUENCE_LENGTH: usize = (std::mem::size_of::<usize>() << 1) - 1;
+fn output_path(prefix: &OsStr, suffix: &str) -> PathBuf {
+    let mut path = prefix.to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
 
-    pub fn new(sequence_length: usize) -> DigitSequenceCounter {
-        if sequence_length > Self::LARGEST_SEQUENCE_LENGTH {
-            panic!(
-                "Cannot create a DigitSequenceCounter for a sequence length greater than {}.",
-                Self::LARGEST_SEQUENCE_LENGTH
-            );
+fn create_output_file(path: &Path, force: bool) -> io::Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(paths::for_opening(parent))?;
         }
-        let modulus = 1 << (sequence_length << 2);
-        DigitSequenceCounter {
-            sequence_length,
-            current_sequence: 0,
-            sequence_counts: vec![0; modulus],
-            bitmask: modulus - 1,
-            stalled_for: sequence_length,
+    }
+    let mut open_options = OpenOptions::new();
+    open_options.write(true);
+    if force {
+        open_options.create(true).truncate(true);
+    } else {
+        open_options.create_new(true);
+    }
+    Ok(BufWriter::new(open_options.open(paths::for_opening(path))?))
+}
+
+fn write_length_csv(
+    path: &Path,
+    force: bool,
+    sequence_counter: &DigitSequenceCounter,
+) -> io::Result<()> {
+    let mut out = create_output_file(path, force)?;
+    sequence_counter.write_csv(&mut out)?;
+    out.flush()
+}
+
+fn write_flamegraph(
+    path: &Path,
+    force: bool,
+    sequence_counter: &DigitSequenceCounter,
+) -> io::Result<()> {
+    let mut out = create_output_file(path, force)?;
+    sequence_counter.write_flamegraph_data(&mut out)?;
+    out.flush()
+}
+
+fn write_dot_graph(
+    path: &Path,
+    force: bool,
+    sequence_counter: &DigitSequenceCounter,
+    min_count: u128,
+) -> io::Result<()> {
+    let mut out = create_output_file(path, force)?;
+    sequence_counter.write_dot_graph(&mut out, min_count)?;
+    out.flush()
+}
+
+#[cfg(feature = "image")]
+fn write_image(
+    path: &Path,
+    force: bool,
+    sequence_counter: &DigitSequenceCounter,
+) -> io::Result<()> {
+    let mut out = create_output_file(path, force)?;
+    sequence_counter.write_counts_as_png(&mut out)?;
+    out.flush()
+}
+
+fn write_summary_json(
+    path: &Path,
+    force: bool,
+    length_summaries: &[LengthSummary],
+    reproducibility: &ReproducibilityBlock,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let mut out = create_output_file(path, force)?;
+    write!(out, "{{")?;
+    reproducibility.write_json_members(&mut out)?;
+    write!(out, ", \"lengths\": [")?;
+    for (i, summary) in length_summaries.iter().enumerate() {
+        if i != 0 {
+            write!(out, ", ")?;
+        }
+        write!(
+            out,
+            "{{\"sequence_length\": {}, \"file\": ",
+            summary.sequence_length
+        )?;
+        json::write_string(&mut out, &summary.path.to_string_lossy())?;
+        write!(
+            out,
+            ", \"possible_sequences\": {}, \"unique_sequences\": {}, \"total_count\": {}, \"coverage\": ",
+            summary.possible_sequences, summary.unique_sequences, summary.total_count
+        )?;
+        json::write_number(&mut out, summary.coverage, number_format)?;
+        write!(out, ", \"sequence_notation\": ")?;
+        json::write_string(&mut out, summary.sequence_notation.name())?;
+        write!(out, ", \"decimal_subset\": {}", summary.decimal_subset)?;
+        write!(out, ", \"most_common\": ")?;
+        match summary.most_common {
+            Some((sequence_index, count)) => {
+                write!(out, "{{\"label\": ")?;
+                json::write_string(
+                    &mut out,
+                    &summary
+                        .sequence_notation
+                        .label(sequence_index, summary.sequence_length),
+                )?;
+                write!(
+                    out,
+                    ", \"value\": {}, \"count\": {}}}",
+                    sequence_index, count
+                )?;
+            }
+            None => write!(out, "null")?,
         }
+        write!(out, "}}")?;
     }
+    writeln!(out, "]}}")?;
+    out.flush()
+}
 
-    pub fn process_character(&mut self, character: u8) {
-        let digit = match character {
-            b'0'..=b'9' => character & 0b1111,
-            b'A'..=b'F' => character - b'A' + 10,
-            b'a'..=b'f' => character - b'a' + 10,
-            _ => {
-                self.current_sequence = 0;
-                self.stalled_for = self.sequence_length;
-                return;
+/// Writes what a count-min counter estimates, each value marked with `~`, along with the
+/// sketch parameters and error bounds behind it.
+fn write_sketch_estimates<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let (width, depth) = sequence_counter.count_min_dimensions().unwrap();
+    let (overcount, confidence) = sequence_counter.count_error_bound().unwrap();
+    let relative_error = sequence_counter.unique_count_relative_error().unwrap();
+    writeln!(
+        out,
+        "Approximate values for length {} (count-min sketch {} wide and {} deep, HyperLogLog with {} registers):",
+        sequence_counter.sequence_length(),
+        width,
+        depth,
+        1_usize << sequence_counter.sketch_bits().unwrap()
+    )?;
+    writeln!(
+        out,
+        "  {:<20}{} (exact)",
+        "Windows:",
+        sequence_counter.total_count()
+    )?;
+    writeln!(
+        out,
+        "  {:<20}~{} (standard error {}%)",
+        "Unique sequences:",
+        number_format.format(sequence_counter.unique_count_estimate()),
+        number_format.format(100.0 * relative_error)
+    )?;
+    writeln!(
+        out,
+        "  {:<20}~{} (standard error {}%)",
+        "Coverage:",
+        number_format.format(
+            (sequence_counter.unique_count_estimate()
+                / sequence_counter.possible_sequences() as f64)
+                .min(1.0)
+        ),
+        number_format.format(100.0 * relative_error)
+    )?;
+    writeln!(
+        out,
+        "  {:<20}at most {} too high with probability {}, never too low",
+        "Counts:",
+        number_format.format(overcount),
+        number_format.format(confidence)
+    )
+}
+
+/// Writes the count of each target as long as this counter's length, marking estimates
+/// with `~`.
+fn write_targets<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    targets: &[String],
+) -> io::Result<()> {
+    let approximate = if sequence_counter.count_min_dimensions().is_some() {
+        "~"
+    } else {
+        ""
+    };
+    writeln!(
+        out,
+        "Targets for length {}:",
+        sequence_counter.sequence_length()
+    )?;
+    for target in targets {
+        if target.len() == sequence_counter.sequence_length() {
+            let sequence_index = usize::from_str_radix(target, 16).unwrap();
+            writeln!(
+                out,
+                "  {} {}{}",
+                sequence_counter.sequence_label(sequence_index),
+                approximate,
+                sequence_counter.count_at(sequence_index)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_neighborhood<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    target: &str,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let neighborhood = sequence_counter.neighborhood(usize::from_str_radix(target, 16).unwrap());
+    let width = neighborhood
+        .counts
+        .iter()
+        .flatten()
+        .max()
+        .map_or(1, |count| count.to_string().len())
+        + 2;
+
+    writeln!(
+        out,
+        "Neighborhood of {}: count {}, neighbor mean {}",
+        sequence_counter.sequence_label(neighborhood.target),
+        neighborhood.target_count,
+        number_format.format(neighborhood.mean)
+    )?;
+    write!(out, "{:>8}", "position")?;
+    for digit in 0..16 {
+        write!(out, " {:>width$X}", digit, width = width)?;
+    }
+    writeln!(out)?;
+    for (position, row) in neighborhood.counts.iter().enumerate() {
+        write!(out, "{:>8}", position)?;
+        for (digit, count) in row.iter().enumerate() {
+            if digit == neighborhood.target_digit(position) {
+                write!(out, " {:>width$}", format!("[{}]", count), width = width)?;
+            } else {
+                write!(out, " {:>width$}", count, width = width)?;
             }
-        } as usize;
-        self.current_sequence = ((self.current_sequence << 4) | digit) & self.bitmask;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
 
-        if self.stalled_for == 0 {
-            self.sequence_counts[self.current_sequence] += 1;
-        } else {
-            self.stalled_for -= 1;
+/// Writes the transition matrix of a length-2 counter as a table with a row per digit and
+/// a column per digit that follows it. Digits above the counter's radix are left out.
+fn write_transition_matrix<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let radix = sequence_counter.radix() as usize;
+    let rows = sequence_counter
+        .as_probability_matrix(1)
+        .into_iter()
+        .take(radix)
+        .map(|row| {
+            row.into_iter()
+                .take(radix)
+                .map(|probability| number_format.format(probability))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let width = rows.iter().flatten().map(String::len).max().unwrap_or(1);
+
+    writeln!(
+        out,
+        "Transition matrix (row digit followed by column digit):"
+    )?;
+    write!(out, "{:>4}", "")?;
+    for digit in 0..radix {
+        write!(out, " {:>width$X}", digit, width = width)?;
+    }
+    writeln!(out)?;
+    for (digit, row) in rows.iter().enumerate() {
+        write!(out, "{:>4X}", digit)?;
+        for probability in row {
+            write!(out, " {:>width$}", probability, width = width)?;
         }
+        writeln!(out)?;
     }
+    Ok(())
 }
 
-impl Display for DigitSequenceCounter {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let nonzero_sequence_counts = self
-            .sequence_counts
-            .iter()
-            .filter(|x| **x != 0)
-            .collect::<Vec<_>>();
-        write!(
-            f,
-            "{} {:?}",
-            nonzero_sequence_counts.len(),
-            nonzero_sequence_counts.as_slice()
-        )
+fn write_independence_test<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    prefix_length: usize,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let result = sequence_counter.test_independence(prefix_length);
+    writeln!(
+        out,
+        "Independence of the first {} digits from the rest for length {}: G = {}, df = {}, p = {}",
+        prefix_length,
+        sequence_counter.sequence_length(),
+        number_format.format(result.g_statistic),
+        result.degrees_of_freedom,
+        number_format.format(result.p_value)
+    )
+}
+
+fn write_periodicity<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    match sequence_counter.strongest_autocorrelation() {
+        Some((lag, autocorrelation)) => writeln!(
+            out,
+            "Periodicity for length {}: score = {}, lag = {} (autocorrelation {})",
+            sequence_counter.sequence_length(),
+            number_format.format(autocorrelation.abs()),
+            lag,
+            number_format.format(autocorrelation)
+        ),
+        None => writeln!(
+            out,
+            "Periodicity for length {}: score = 0, as the counts don't vary",
+            sequence_counter.sequence_length()
+        ),
+    }
+}
+
+fn write_outliers<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    z_threshold: f64,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let outliers = sequence_counter.find_excess_sequences(z_threshold);
+    writeln!(
+        out,
+        "Outliers beyond z = {} for length {}: {}",
+        number_format.format(z_threshold),
+        sequence_counter.sequence_length(),
+        outliers.len()
+    )?;
+    for (sequence_index, z) in outliers {
+        writeln!(
+            out,
+            "  {} z = {} count {}",
+            sequence_counter.sequence_label(sequence_index),
+            number_format.format(z),
+            sequence_counter.count_at(sequence_index)
+        )?;
     }
+    Ok(())
 }
 
-fn process_args() -> Result<(PathBuf, usize), String> {
-    let mut args = env::args_os();
-    args.next();
+fn write_two_sample_test<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    second_counter: &DigitSequenceCounter,
+    second_path: &Path,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let result = statistics::two_sample_chi_square_test(sequence_counter, second_counter);
+    writeln!(
+        out,
+        "Two-sample test against {} for length {}: chi2 = {}, df = {}, p = {}, w = {}: {}",
+        second_path.display(),
+        sequence_counter.sequence_length(),
+        number_format.format(result.chi_square),
+        result.degrees_of_freedom,
+        number_format.format(result.p_value),
+        number_format.format(result.cohens_w),
+        if result.p_value < 0.05 {
+            "Reject H0 at 0.05"
+        } else {
+            "Fail to reject H0"
+        }
+    )
+}
+
+fn write_count_distribution<W: Write>(
+    out: &mut W,
+    sequence_counter: &DigitSequenceCounter,
+    exact_zero_stats: bool,
+) -> io::Result<()> {
+    let distribution = sequence_counter.count_distribution();
+    let count_width = distribution
+        .keys()
+        .last()
+        .map_or(1, |count| count.to_string().len())
+        .max("count".len());
+    writeln!(
+        out,
+        "Count distribution for length {}:",
+        sequence_counter.sequence_length()
+    )?;
+    writeln!(out, "{:>width$} sequences", "count", width = count_width)?;
+    if exact_zero_stats {
+        writeln!(
+            out,
+            "{:>width$} {}",
+            0,
+            sequence_counter.zero_bucket(),
+            width = count_width
+        )?;
+    }
+    for (count, sequences) in distribution {
+        writeln!(out, "{:>width$} {}", count, sequences, width = count_width)?;
+    }
+    Ok(())
+}
+
+fn process_args() -> Result<Options, String> {
+    let mut options = Options {
+        path: PathBuf::new(),
+        max_sequence_length: 0,
+        storage: StorageMode::Dense,
+        external: None,
+        sketch_width: None,
+        sketch_depth: DigitSequenceCounter::DEFAULT_SKETCH_DEPTH,
+        threads: 1,
+        chunk_size: parallel::DEFAULT_CHUNK_SIZE,
+        chunks: None,
+        progress: false,
+        report_every: None,
+        flush_interval: None,
+        separators: Vec::new(),
+        from_start: false,
+        passes: Passes::Count(1),
+        max_memory: None,
+        summary: false,
+        stats: None,
+        statistics_plan: None,
+        neighborhoods: Vec::new(),
+        targets: Vec::new(),
+        count_distribution: false,
+        transition_matrix: false,
+        independence_test: None,
+        periodicity: false,
+        two_sample: None,
+        outliers: None,
+        exact_zero_stats: false,
+        precision: number_format::DEFAULT_PRECISION,
+        label: Label::Radix,
+        max_drought: None,
+        max_drought_count: 10,
+        sniff_bytes: sniff::DEFAULT_SNIFF_BYTES,
+        strict_sniff: false,
+        fail_if_unsatisfiable: false,
+        also_decimal_subset: false,
+        verify_checksum: None,
+        verify_first: false,
+        output_prefix: None,
+        flamegraph: false,
+        image: None,
+        dot_graph: None,
+        output: None,
+        no_timestamps: false,
+        force: false,
+        verbose: false,
+    };
+    let mut positional = Vec::new();
+
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--storage") => match args.next() {
+                Some(value)
+                    if value
+                        .to_str()
+                        .is_some_and(|value| value.starts_with("external:")) =>
+                {
+                    let dir = PathBuf::from(&value.to_str().unwrap()["external:".len()..]);
+                    if !fs::metadata(paths::for_opening(&dir))
+                        .is_ok_and(|metadata| metadata.is_dir())
+                    {
+                        return Err(format!(
+                            "bad --storage value: {} is not a directory",
+                            dir.display()
+                        ));
+                    }
+                    options.external = Some(dir);
+                }
+                value => {
+                    options.storage = flag_value("--storage", value)?;
+                    options.external = None;
+                }
+            },
+            Some("--sketch-width") => {
+                options.sketch_width = Some(flag_value("--sketch-width", args.next())?);
+            }
+            Some("--sketch-depth") => {
+                options.sketch_depth = flag_value("--sketch-depth", args.next())?;
+            }
+            Some("--threads") => options.threads = flag_value("--threads", args.next())?,
+            Some("--chunk-size") => {
+                options.chunk_size = flag_value("--chunk-size", args.next())?;
+            }
+            Some("--chunks") => options.chunks = Some(flag_value("--chunks", args.next())?),
+            Some("--progress") => options.progress = true,
+            Some("--report-every") => {
+                options.report_every = Some(flag_value("--report-every", args.next())?);
+            }
+            Some("--flush-interval") => {
+                let seconds: f64 = flag_value("--flush-interval", args.next())?;
+                if !(seconds.is_finite() && seconds > 0.0) {
+                    return Err(String::from("--flush-interval must be a positive number"));
+                }
+                options.flush_interval = Some(Duration::from_secs_f64(seconds));
+            }
+            Some("--separators") => {
+                options.separators =
+                    flag_value::<String>("--separators", args.next())?.into_bytes();
+            }
+            Some("--from-start") => options.from_start = true,
+            Some("--passes") => options.passes = flag_value("--passes", args.next())?,
+            Some("--max-memory") => {
+                options.max_memory = Some(flag_value("--max-memory", args.next())?);
+            }
+            Some("--max-drought") => {
+                options.max_drought = Some(flag_value("--max-drought", args.next())?);
+            }
+            Some("--max-drought-count") => {
+                options.max_drought_count = flag_value("--max-drought-count", args.next())?;
+            }
+            Some("--summary") => options.summary = true,
+            Some("--stats") => options.stats = Some(flag_value("--stats", args.next())?),
+            Some("--neighborhood") => options
+                .neighborhoods
+                .push(flag_value("--neighborhood", args.next())?),
+            Some("--target") => options.targets.push(flag_value("--target", args.next())?),
+            Some("--count-distribution") => options.count_distribution = true,
+            Some("--transition-matrix") => options.transition_matrix = true,
+            Some("--independence-test") => {
+                options.independence_test = Some(flag_value("--independence-test", args.next())?);
+            }
+            Some("--periodicity") => options.periodicity = true,
+            Some("--two-sample") => {
+                options.two_sample = Some(flag_value("--two-sample", args.next())?);
+            }
+            Some("--outliers") => options.outliers = Some(flag_value("--outliers", args.next())?),
+            Some("--exact-zero-stats") => options.exact_zero_stats = true,
+            Some("--precision") => options.precision = flag_value("--precision", args.next())?,
+            Some("--label") => options.label = flag_value("--label", args.next())?,
+            Some("--sniff-bytes") => {
+                options.sniff_bytes = flag_value("--sniff-bytes", args.next())?;
+            }
+            Some("--strict-sniff") => options.strict_sniff = true,
+            Some("--fail-if-unsatisfiable") => options.fail_if_unsatisfiable = true,
+            Some("--also-decimal-subset") => options.also_decimal_subset = true,
+            Some("--verify-checksum") => {
+                options.verify_checksum = Some(flag_value("--verify-checksum", args.next())?);
+            }
+            Some("--verify-first") => options.verify_first = true,
+            Some("--output-prefix") => match args.next() {
+                Some(prefix) => options.output_prefix = Some(prefix),
+                None => return Err(String::from("missing --output-prefix value")),
+            },
+            Some("--no-timestamps") => options.no_timestamps = true,
+            Some("--force") => options.force = true,
+            Some("--flamegraph") => options.flamegraph = true,
+            Some("--image") => match args.next() {
+                Some(path) => options.image = Some(PathBuf::from(path)),
+                None => return Err(String::from("missing --image value")),
+            },
+            Some("--dot-graph") => {
+                options.dot_graph = Some(flag_value("--dot-graph", args.next())?);
+            }
+            Some("--output") => match args.next() {
+                Some(path) => options.output = Some(PathBuf::from(path)),
+                None => return Err(String::from("missing --output value")),
+            },
+            Some("--verbose") => options.verbose = true,
+            Some(flag) if flag.starts_with("--") => {
+                return Err(format!("unknown option {}", flag));
+            }
+            _ => positional.push(arg),
+        }
+    }
+    if options.threads == 0 {
+        return Err(String::from("--threads must be at least 1"));
+    }
+    if options.external.is_some() {
+        let unsupported = [
+            ("--threads", options.threads > 1),
+            ("--chunks", options.chunks.is_some()),
+            ("--report-every", options.report_every.is_some()),
+            ("--passes", options.passes != Passes::Count(1)),
+            ("--max-drought", options.max_drought.is_some()),
+            ("--summary", options.summary),
+            ("--stats", options.stats.is_some()),
+            ("--neighborhood", !options.neighborhoods.is_empty()),
+            ("--target", !options.targets.is_empty()),
+            ("--count-distribution", options.count_distribution),
+            ("--transition-matrix", options.transition_matrix),
+            ("--independence-test", options.independence_test.is_some()),
+            ("--periodicity", options.periodicity),
+            ("--two-sample", options.two_sample.is_some()),
+            ("--outliers", options.outliers.is_some()),
+            ("--also-decimal-subset", options.also_decimal_subset),
+            ("--flamegraph", options.flamegraph),
+            ("--image", options.image.is_some()),
+            ("--dot-graph", options.dot_graph.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+            return Err(format!(
+                "{} isn't supported with --storage external, which only streams the counts",
+                flag
+            ));
+        }
+    }
+    if options.storage == StorageMode::Sketch {
+        if options.sketch_width == Some(0)
+            || !DigitSequenceCounter::SKETCH_DEPTHS.contains(&options.sketch_depth)
+        {
+            return Err(String::from(
+                "sketches need a --sketch-width of at least 1 and a --sketch-depth of 1 to 16",
+            ));
+        }
+        if !options.neighborhoods.is_empty() {
+            return Err(String::from(
+                "--neighborhood needs exact counts; use --target with --storage sketch",
+            ));
+        }
+    } else if options.sketch_width.is_some()
+        || options.sketch_depth != DigitSequenceCounter::DEFAULT_SKETCH_DEPTH
+    {
+        return Err(String::from(
+            "--sketch-width and --sketch-depth need --storage sketch",
+        ));
+    }
+    if options.dot_graph.is_some() != options.output.is_some() {
+        return Err(String::from(
+            "--dot-graph and --output go together: --output is the file for the graph",
+        ));
+    }
+    if options.outliers.is_some_and(|z| z.is_nan() || z < 0.0) {
+        return Err(String::from("--outliers Z must be a number of at least 0"));
+    }
+    if options.image.is_some() && cfg!(not(feature = "image")) {
+        return Err(String::from("--image needs a build with the image feature"));
+    }
+    if matches!(
+        options.storage,
+        StorageMode::HyperLogLog | StorageMode::Sketch
+    ) {
+        let needs_exact_counts = [
+            ("--flamegraph", options.flamegraph),
+            ("--transition-matrix", options.transition_matrix),
+            ("--dot-graph", options.dot_graph.is_some()),
+            ("--independence-test", options.independence_test.is_some()),
+            ("--periodicity", options.periodicity),
+            ("--two-sample", options.two_sample.is_some()),
+            ("--outliers", options.outliers.is_some()),
+            ("--image", options.image.is_some()),
+        ];
+        if let Some((flag, _)) = needs_exact_counts.iter().find(|(_, given)| *given) {
+            return Err(format!(
+                "{} needs exact counts, which --storage {} doesn't keep",
+                flag, options.storage
+            ));
+        }
+    }
+    if options.report_every.is_some() {
+        if options.report_every == Some(0) {
+            return Err(String::from("--report-every must be at least 1"));
+        }
+        if options.threads > 1 || options.passes != Passes::Count(1) {
+            return Err(String::from(
+                "--report-every needs one thread and one pass to snapshot the counters",
+            ));
+        }
+    }
+    if options.also_decimal_subset && options.threads > 1 {
+        return Err(String::from(
+            "--also-decimal-subset counts in the same pass as the hex digits and cannot be threaded",
+        ));
+    }
+    if options.verify_first && options.verify_checksum.is_none() {
+        return Err(String::from("--verify-first needs --verify-checksum"));
+    }
+    if options.precision == 0 || options.precision > number_format::MAX_PRECISION {
+        return Err(format!(
+            "--precision must be between 1 and {}",
+            number_format::MAX_PRECISION
+        ));
+    }
+    if options.chunk_size == 0 || options.chunks == Some(0) {
+        return Err(String::from("work items must not be empty"));
+    }
+    if let Some(length) = options.max_drought {
+        if length == 0 || length > DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH {
+            return Err(format!(
+                "--max-drought length must be between 1 and {}",
+                DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH
+            ));
+        }
+        if length > DroughtTracker::DEFAULT_LENGTH_LIMIT && !options.force {
+            return Err(format!(
+                "--max-drought above length {} needs 24 bytes per possible sequence; use --force",
+                DroughtTracker::DEFAULT_LENGTH_LIMIT
+            ));
+        }
+        if options.threads > 1 {
+            return Err(String::from(
+                "--max-drought needs positions in file order and cannot be threaded",
+            ));
+        }
+    }
+    let mut args = positional.into_iter();
 
     let path = match args.next() {
-        Some(path) => match fs::canonicalize(&path) {
-            Ok(path) => path,
-            Err(err) => return Err(format!("bad given file path: {}", err)),
-        },
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if path.as_os_str() != "-" {
+                if let Err(err) = fs::metadata(paths::for_opening(&path)) {
+                    return Err(format!("bad given file path {}: {}", path.display(), err));
+                }
+            }
+            path
+        }
         None => return Err(String::from("no given file path")),
     };
+    if path.as_os_str() == "-"
+        && options
+            .two_sample
+            .as_ref()
+            .is_some_and(|second_path| second_path.as_os_str() == "-")
+    {
+        return Err(String::from(
+            "--two-sample can't read standard input, which is already the input",
+        ));
+    }
 
     let max_sequence_length = match args.next() {
         Some(max_sequence_length) => match max_sequence_length.into_string() {
@@ -146,6 +2243,344 @@ fn process_args() -> Result<(PathBuf, usize), String> {
     if args.next().is_some() {
         Err(String::from("too many arguments"))
     } else {
-        Ok((path, max_sequence_length))
+        for (flag, targets) in &[
+            ("--neighborhood", &options.neighborhoods),
+            ("--target", &options.targets),
+        ] {
+            for target in targets.iter() {
+                if target.is_empty()
+                    || target.len() > max_sequence_length
+                    || !target.bytes().all(|byte| byte.is_ascii_hexdigit())
+                {
+                    return Err(format!(
+                        "{} {:?} must be 1 to {} hex digits",
+                        flag, target, max_sequence_length
+                    ));
+                }
+            }
+        }
+        if options.storage == StorageMode::Sketch {
+            // Sketches take the same memory at every length, so the budget sizes them to fit
+            // one pass instead of splitting the lengths.
+            let depth = options.sketch_depth;
+            match (options.sketch_width, options.max_memory) {
+                (None, Some(max_memory)) => {
+                    let width = DigitSequenceCounter::count_min_width_within(
+                        max_memory / max_sequence_length.max(1),
+                        depth,
+                    );
+                    if width == 0 {
+                        return Err(format!(
+                            "--max-memory of {} bytes is too small for {} sketches",
+                            max_memory, max_sequence_length
+                        ));
+                    }
+                    options.sketch_width = Some(width);
+                }
+                (Some(width), Some(max_memory))
+                    if DigitSequenceCounter::count_min_bytes(width, depth)
+                        .saturating_mul(max_sequence_length)
+                        > max_memory =>
+                {
+                    return Err(format!(
+                        "{} sketches {} wide and {} deep need about {} bytes, over the memory budget of {} bytes",
+                        max_sequence_length,
+                        width,
+                        depth,
+                        DigitSequenceCounter::count_min_bytes(width, depth)
+                            .saturating_mul(max_sequence_length),
+                        max_memory
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if options
+            .independence_test
+            .is_some_and(|prefix_length| prefix_length == 0 || prefix_length >= max_sequence_length)
+        {
+            return Err(String::from(
+                "--independence-test PREFIX must be at least 1 and less than the maximum sequence length",
+            ));
+        }
+        if options.dot_graph.is_some() && max_sequence_length < 2 {
+            return Err(String::from(
+                "--dot-graph needs a length of at least 2, since each edge joins two shorter strings",
+            ));
+        }
+        if options.image.is_some() && max_sequence_length < 2 {
+            return Err(String::from(
+                "--image needs a length of at least 2, since images show even lengths",
+            ));
+        }
+        if let Some(plan) = &options.stats {
+            options.statistics_plan = Some(plan.resolve(1..=max_sequence_length)?);
+        }
+        options.path = path;
+        options.max_sequence_length = max_sequence_length;
+        Ok(options)
+    }
+}
+
+/// How the `matrix` subcommand writes its table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MatrixFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for MatrixFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MatrixFormat, String> {
+        match s {
+            "table" => Ok(MatrixFormat::Table),
+            "csv" => Ok(MatrixFormat::Csv),
+            "json" => Ok(MatrixFormat::Json),
+            _ => Err(format!("expected table, csv, or json, not {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MatrixOptions {
+    paths: Vec<PathBuf>,
+    format: MatrixFormat,
+    sort_by: Option<String>,
+    precision: usize,
+}
+
+fn process_matrix_args() -> Result<MatrixOptions, String> {
+    let mut options = MatrixOptions {
+        paths: Vec::new(),
+        format: MatrixFormat::Table,
+        sort_by: None,
+        precision: number_format::DEFAULT_PRECISION,
+    };
+
+    let mut args = env::args_os().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--format") => options.format = flag_value("--format", args.next())?,
+            Some("--sort-by") => options.sort_by = Some(flag_value("--sort-by", args.next())?),
+            Some("--precision") => options.precision = flag_value("--precision", args.next())?,
+            Some(flag) if flag.starts_with("--") => {
+                return Err(format!("unknown option {}", flag));
+            }
+            _ => options.paths.extend(expand_pattern(&arg)?),
+        }
+    }
+    if options.precision == 0 || options.precision > number_format::MAX_PRECISION {
+        return Err(format!(
+            "--precision must be between 1 and {}",
+            number_format::MAX_PRECISION
+        ));
+    }
+    if options.paths.is_empty() {
+        return Err(String::from("no given saved reports"));
+    }
+    Ok(options)
+}
+
+/// Expands `*` and `?` in the file name of `pattern` to the matching files in its directory,
+/// in name order. Anything else is returned as is.
+fn expand_pattern(pattern: &OsStr) -> Result<Vec<PathBuf>, String> {
+    fn matches(pattern: &[u8], name: &[u8]) -> bool {
+        match (pattern.split_first(), name.split_first()) {
+            (None, _) => name.is_empty(),
+            (Some((b'*', rest)), _) => {
+                matches(rest, name) || (!name.is_empty() && matches(pattern, &name[1..]))
+            }
+            (Some((b'?', rest)), Some((_, name_rest))) => matches(rest, name_rest),
+            (Some((byte, rest)), Some((name_byte, name_rest))) => {
+                byte == name_byte && matches(rest, name_rest)
+            }
+            (Some(_), None) => false,
+        }
+    }
+
+    let path = Path::new(pattern);
+    let file_pattern = match path.file_name().map(OsStr::as_encoded_bytes) {
+        Some(file_pattern) if file_pattern.iter().any(|byte| b"*?".contains(byte)) => file_pattern,
+        _ => return Ok(vec![path.to_path_buf()]),
+    };
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let entries = fs::read_dir(paths::for_opening(directory))
+        .map_err(|err| format!("cannot read {}: {}", directory.display(), err))?;
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter(|entry| matches(file_pattern, entry.file_name().as_encoded_bytes()))
+        .map(|entry| directory.join(entry.file_name()))
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Err(format!("no files match {}", path.display()));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Runs the `matrix` subcommand, which loads every saved run and writes one table comparing
+/// them.
+fn run_matrix() -> ! {
+    let options = match process_matrix_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\nError: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    };
+
+    let mut runs = Vec::with_capacity(options.paths.len());
+    for path in &options.paths {
+        let contents = match fs::read_to_string(paths::for_opening(path)) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("Error reading {}: {}", path.display(), err);
+                finish(Status::InputError);
+            }
+        };
+        INPUT_BYTES.fetch_add(contents.len() as u64, Ordering::Relaxed);
+        let name = path
+            .file_stem()
+            .map_or_else(|| path.to_string_lossy(), |stem| stem.to_string_lossy());
+        match SavedRun::parse(&name, &contents) {
+            Ok(run) => runs.push(run),
+            Err(err) => {
+                eprintln!("Error loading {}: {}", path.display(), err);
+                finish(Status::InputError);
+            }
+        }
+    }
+
+    let mut matrix = Matrix::new(&runs);
+    if let Some(column) = &options.sort_by {
+        if let Err(err) = matrix.sort_by(column) {
+            eprintln!("{}\nError: bad --sort-by value: {}", USAGE, err);
+            finish(Status::Usage);
+        }
+    }
+
+    let number_format = NumberFormat::new(options.precision);
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let written = match options.format {
+        MatrixFormat::Table => matrix.write_table(&mut out, &number_format),
+        MatrixFormat::Csv => matrix.write_csv(&mut out, &number_format),
+        MatrixFormat::Json => matrix.write_json(&mut out, &number_format),
+    }
+    .and_then(|()| out.flush());
+    match written {
+        Ok(()) => finish(Status::Ok),
+        Err(err) => {
+            eprintln!("Error writing the matrix: {}", err);
+            finish(Status::Partial);
+        }
+    }
+}
+
+/// Runs the `selftest` subcommand, counting every built-in case under every configuration,
+/// then checking every golden case against its checked-in counts.
+fn run_selftest() -> ! {
+    if env::args_os().nth(2).is_some() {
+        eprintln!("{}\nError: selftest takes no arguments", USAGE);
+        finish(Status::Usage);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut runs = 0;
+    let mut failures = 0;
+    for case in selftest::cases() {
+        INPUT_BYTES.fetch_add(case.input.len() as u64, Ordering::Relaxed);
+        for configuration in selftest::configurations() {
+            runs += 1;
+            let line = match selftest::run_case(&case, configuration) {
+                Ok(()) => format!("PASS {} ({})", case.name, configuration),
+                Err(err) => {
+                    failures += 1;
+                    format!("FAIL {} ({}): {}", case.name, configuration, err)
+                }
+            };
+            if writeln!(out, "{}", line).is_err() {
+                finish(Status::Partial);
+            }
+        }
+    }
+    for case in golden::CASES {
+        INPUT_BYTES.fetch_add(case.input.len() as u64, Ordering::Relaxed);
+        for configuration in golden::configurations() {
+            runs += 1;
+            let line = match golden::check(case, configuration) {
+                Ok(()) => format!("PASS golden {} ({})", case.name, configuration),
+                Err(err) => {
+                    failures += 1;
+                    format!("FAIL golden {} ({}): {}", case.name, configuration, err)
+                }
+            };
+            if writeln!(out, "{}", line).is_err() {
+                finish(Status::Partial);
+            }
+        }
+    }
+    let _ = writeln!(out, "{} of {} passed", runs - failures, runs);
+    let _ = out.flush();
+    finish(if failures == 0 {
+        Status::Ok
+    } else {
+        Status::SelfTestFailed
+    });
+}
+
+fn report_progress(workers: &[WorkerProgress], counting_done: &AtomicBool) {
+    const MIB: f64 = (1 << 20) as f64;
+
+    let mut last_bytes = vec![0; workers.len()];
+    let mut last_report = Instant::now();
+    while !counting_done.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(100));
+        let elapsed = last_report.elapsed().as_secs_f64();
+        if elapsed < 1.0 {
+            continue;
+        }
+        last_report = Instant::now();
+
+        let mut line = String::new();
+        let mut total = 0;
+        for (worker, (progress, last_bytes)) in workers.iter().zip(&mut last_bytes).enumerate() {
+            let bytes = progress.bytes();
+            line.push_str(&format!(
+                ", worker {}: {:.1} MiB/s",
+                worker,
+                (bytes - *last_bytes) as f64 / MIB / elapsed
+            ));
+            total += bytes - *last_bytes;
+            *last_bytes = bytes;
+        }
+        eprintln!(
+            "Progress: {:.1} MiB/s total{}",
+            total as f64 / MIB / elapsed,
+            line
+        );
+    }
+}
+
+fn flag_value<T>(flag: &str, value: Option<OsString>) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match value {
+        Some(value) => match value.into_string() {
+            Ok(value) => match value.parse() {
+                Ok(value) => Ok(value),
+                Err(err) => Err(format!("bad {} value: {}", flag, err)),
+            },
+            Err(_) => Err(format!("bad {} value: non-UTF8 bytes", flag)),
+        },
+        None => Err(format!("missing {} value", flag)),
     }
 }
@@ -0,0 +1,49 @@
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    /// Returns the conditional distributions of the last digits given the first
+    /// `prefix_length` digits, as a row-stochastic matrix where `matrix[prefix][suffix]` is
+    /// the probability of `suffix` following `prefix`. The matrix is
+    /// `16^prefix_length` by `16^(sequence_length - prefix_length)`, and each row sums to 1,
+    /// or to 0 for a prefix never seen. With a sequence length of 2 and a prefix length of 1,
+    /// it is the Markov transition matrix between digits.
+    ///
+    /// Sketches have no exact counts and give a matrix of zeros.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x31415".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let matrix = counter.as_probability_matrix(1);
+    /// assert_eq!((matrix.len(), matrix[0].len()), (16, 16));
+    /// assert_eq!((matrix[1][4], matrix[1][5], matrix[4][1]), (0.5, 0.5, 1.0));
+    /// assert_eq!(matrix[3].iter().sum::<f64>(), 0.0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_length` isn't less than the sequence length.
+    pub fn as_probability_matrix(&self, prefix_length: usize) -> Vec<Vec<f64>> {
+        if prefix_length >= self.sequence_length {
+            panic!(
+                "Cannot condition on a prefix of {} digits with a sequence length of {}.",
+                prefix_length, self.sequence_length
+            );
+        }
+        let suffix_bits = (self.sequence_length - prefix_length) << 2;
+        let mut matrix = vec![vec![0.0; 1 << suffix_bits]; 1 << (prefix_length << 2)];
+        let mut row_totals = vec![0; matrix.len()];
+        let nonzero_counts = self.nonzero_counts();
+        for &(sequence_index, count) in &nonzero_counts {
+            row_totals[sequence_index >> suffix_bits] += count;
+        }
+        for (sequence_index, count) in nonzero_counts {
+            let prefix = sequence_index >> suffix_bits;
+            let suffix = sequence_index & ((1 << suffix_bits) - 1);
+            matrix[prefix][suffix] = count as f64 / row_totals[prefix] as f64;
+        }
+        matrix
+    }
+}
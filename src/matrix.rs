@@ -0,0 +1,359 @@
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+use crate::json;
+use crate::number_format::NumberFormat;
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// The counters of one earlier run, loaded back from what it saved.
+#[derive(Debug)]
+pub struct SavedRun {
+    pub name: String,
+    /// The input size recorded in a text report's trailer.
+    pub input_bytes: Option<u64>,
+    /// One counter per length found, in ascending length order.
+    pub counters: Vec<DigitSequenceCounter>,
+}
+
+impl SavedRun {
+    /// Loads a run from either of two formats, told apart by their first line:
+    ///
+    /// * a state file of `serialize_to_hex` lines, one per length, or
+    /// * a text report as the CLI prints it, with one `unique [counts]` line per length in
+    ///   ascending order from length 1 and an optional `# input_size:` trailer line. A
+    ///   `Decimal subset` section and everything after it is ignored.
+    ///
+    /// A text report lists the nonzero counts without their sequences, which is enough for
+    /// the matrix's statistics but not for anything that depends on which sequence had which
+    /// count. Lengths counted with HyperLogLog storage have no counts and are left out.
+    pub fn parse(name: &str, contents: &str) -> Result<SavedRun, String> {
+        let mut lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .peekable();
+        let mut run = SavedRun {
+            name: String::from(name),
+            input_bytes: None,
+            counters: Vec::new(),
+        };
+
+        if lines.peek().is_some_and(|line| line.starts_with("DSC")) {
+            for line in lines {
+                let counter = DigitSequenceCounter::deserialize_from_hex(line)
+                    .map_err(|err| err.to_string())?;
+                run.counters.push(counter);
+            }
+        } else {
+            let mut sequence_length = 0;
+            for line in lines {
+                if line.starts_with("Decimal subset") {
+                    break;
+                }
+                if let Some(input_size) = line.strip_prefix("# input_size:") {
+                    run.input_bytes = Some(
+                        input_size
+                            .trim()
+                            .parse()
+                            .map_err(|err| format!("bad input size {:?}: {}", input_size, err))?,
+                    );
+                    continue;
+                }
+                let (unique, counts) = match line.split_once(" [") {
+                    Some((unique, counts)) if counts.ends_with(']') => {
+                        (unique, &counts[..counts.len() - 1])
+                    }
+                    _ => continue,
+                };
+                let unique_digits = unique.strip_prefix('~').unwrap_or(unique);
+                if unique_digits.is_empty() || !unique_digits.bytes().all(|b| b.is_ascii_digit()) {
+                    continue;
+                }
+                sequence_length += 1;
+                if unique.starts_with('~') {
+                    continue;
+                }
+                if sequence_length > DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH {
+                    return Err(format!(
+                        "more than {} lengths of counts",
+                        DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH
+                    ));
+                }
+                let mut counter =
+                    DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse);
+                for (sequence_index, count) in counts
+                    .split(", ")
+                    .filter(|count| !count.is_empty())
+                    .enumerate()
+                {
+                    match count.parse() {
+                        Ok(count) if sequence_index < counter.possible_sequences() => {
+                            counter.add_count(sequence_index, count)
+                        }
+                        _ => {
+                            return Err(format!(
+                                "bad count {:?} for length {}",
+                                count, sequence_length
+                            ))
+                        }
+                    }
+                }
+                run.counters.push(counter);
+            }
+        }
+
+        if run.counters.is_empty() && run.input_bytes.is_none() {
+            return Err(String::from("neither counts nor an input size found"));
+        }
+        run.counters
+            .sort_by_key(|counter| counter.sequence_length());
+        Ok(run)
+    }
+}
+
+/// A statistic shown for each length in the matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthMetric {
+    EntropyBits,
+    ChiSquarePValue,
+    MaxAbsZScore,
+}
+
+impl LengthMetric {
+    pub const ALL: [LengthMetric; 3] = [
+        LengthMetric::EntropyBits,
+        LengthMetric::ChiSquarePValue,
+        LengthMetric::MaxAbsZScore,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LengthMetric::EntropyBits => "entropy",
+            LengthMetric::ChiSquarePValue => "chi_square_p",
+            LengthMetric::MaxAbsZScore => "max_abs_z",
+        }
+    }
+
+    pub fn of(self, counter: &DigitSequenceCounter) -> f64 {
+        match self {
+            LengthMetric::EntropyBits => counter.entropy_bits(),
+            LengthMetric::ChiSquarePValue => counter.chi_square_p_value(),
+            LengthMetric::MaxAbsZScore => counter.max_abs_z_score(),
+        }
+    }
+}
+
+/// One row of a `Matrix`, with a cell per column that is empty where the run has no value.
+#[derive(Clone, Debug)]
+pub struct MatrixRow {
+    pub name: String,
+    pub cells: Vec<Option<f64>>,
+}
+
+/// A cross-tabulation of saved runs: a row per run, with `input_bytes` and `digits` columns
+/// followed by a column per `LengthMetric` for every length any run counted, named like
+/// `entropy_len3`. Runs made with different lengths leave the cells they lack empty.
+///
+/// `digits` is the digits counted, taken from the windows of the shortest length on the
+/// assumption that no non-digit byte broke the run.
+///
+/// ```
+/// use digit_sequence_counter::matrix::{Matrix, SavedRun};
+///
+/// let pi = SavedRun::parse("pi", "9 [1, 2, 3, 2, 3, 2, 1, 2, 3]\n# input_size: 22").unwrap();
+/// let e = SavedRun::parse("e", "DSC02;00000027:00000000000000000000000000000001").unwrap();
+/// let mut matrix = Matrix::new(&[pi, e]);
+///
+/// assert_eq!(matrix.columns[..5], ["input_bytes", "digits", "entropy_len1", "chi_square_p_len1", "max_abs_z_len1"]);
+/// assert_eq!(matrix.rows[0].cells[1], Some(20.0));
+/// assert_eq!(matrix.rows[1].cells[2], None);
+///
+/// matrix.sort_by("digits").unwrap();
+/// assert_eq!(matrix.rows[0].name, "e");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Matrix {
+    pub columns: Vec<String>,
+    pub rows: Vec<MatrixRow>,
+}
+
+impl Matrix {
+    pub fn new(runs: &[SavedRun]) -> Matrix {
+        let mut sequence_lengths = runs
+            .iter()
+            .flat_map(|run| {
+                run.counters
+                    .iter()
+                    .map(DigitSequenceCounter::sequence_length)
+            })
+            .collect::<Vec<_>>();
+        sequence_lengths.sort_unstable();
+        sequence_lengths.dedup();
+
+        let mut columns = vec![String::from("input_bytes"), String::from("digits")];
+        for &sequence_length in &sequence_lengths {
+            for metric in &LengthMetric::ALL {
+                columns.push(format!("{}_len{}", metric.name(), sequence_length));
+            }
+        }
+
+        let rows = runs
+            .iter()
+            .map(|run| {
+                let mut cells = vec![
+                    run.input_bytes.map(|input_bytes| input_bytes as f64),
+                    run.counters.first().map(|counter| {
+                        (counter.total_count() + counter.sequence_length() as u128) as f64
+                    }),
+                ];
+                for &sequence_length in &sequence_lengths {
+                    let counter = run
+                        .counters
+                        .iter()
+                        .find(|counter| counter.sequence_length() == sequence_length);
+                    for metric in &LengthMetric::ALL {
+                        cells.push(counter.map(|counter| metric.of(counter)));
+                    }
+                }
+                MatrixRow {
+                    name: run.name.clone(),
+                    cells,
+                }
+            })
+            .collect();
+
+        Matrix { columns, rows }
+    }
+
+    /// Sorts the rows by `column`, or by run name for `name`, in ascending order with empty
+    /// cells last. A leading `-` sorts in descending order instead, still with empty cells
+    /// last. Ties keep their order.
+    pub fn sort_by(&mut self, column: &str) -> Result<(), String> {
+        let (descending, column) = match column.strip_prefix('-') {
+            Some(column) => (true, column),
+            None => (false, column),
+        };
+        let direction = |ordering: Ordering| {
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        if column == "name" {
+            self.rows.sort_by(|a, b| direction(a.name.cmp(&b.name)));
+            return Ok(());
+        }
+
+        let i = match self.columns.iter().position(|name| name == column) {
+            Some(i) => i,
+            None => return Err(format!("no column named {}", column)),
+        };
+        self.rows.sort_by(|a, b| {
+            match (
+                a.cells[i].filter(|v| !v.is_nan()),
+                b.cells[i].filter(|v| !v.is_nan()),
+            ) {
+                (Some(a), Some(b)) => direction(a.total_cmp(&b)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        });
+        Ok(())
+    }
+
+    fn formatted_cells(&self, row: &MatrixRow, number_format: &NumberFormat) -> Vec<String> {
+        row.cells
+            .iter()
+            .map(|cell| cell.map_or_else(String::new, |value| number_format.format(value)))
+            .collect()
+    }
+
+    /// Writes the matrix as a table with columns padded to line up, showing empty cells as
+    /// `-`.
+    pub fn write_table<W: Write>(
+        &self,
+        out: &mut W,
+        number_format: &NumberFormat,
+    ) -> io::Result<()> {
+        let mut lines = vec![std::iter::once(String::from("name"))
+            .chain(self.columns.iter().cloned())
+            .collect::<Vec<_>>()];
+        for row in &self.rows {
+            lines.push(
+                std::iter::once(row.name.clone())
+                    .chain(
+                        self.formatted_cells(row, number_format)
+                            .into_iter()
+                            .map(|cell| {
+                                if cell.is_empty() {
+                                    String::from("-")
+                                } else {
+                                    cell
+                                }
+                            }),
+                    )
+                    .collect(),
+            );
+        }
+        let widths = (0..=self.columns.len())
+            .map(|i| lines.iter().map(|line| line[i].len()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+        for line in &lines {
+            let mut text = format!("{:<width$}", line[0], width = widths[0]);
+            for (cell, &width) in line[1..].iter().zip(&widths[1..]) {
+                text.push_str(&format!("  {:>width$}", cell, width = width));
+            }
+            writeln!(out, "{}", text.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the matrix as CSV with a header row, leaving empty cells empty. Run names are
+    /// quoted when they need it.
+    pub fn write_csv<W: Write>(&self, out: &mut W, number_format: &NumberFormat) -> io::Result<()> {
+        writeln!(out, "name,{}", self.columns.join(","))?;
+        for row in &self.rows {
+            let name = if row.name.contains([',', '"', '\n']) {
+                format!("\"{}\"", row.name.replace('"', "\"\""))
+            } else {
+                row.name.clone()
+            };
+            writeln!(
+                out,
+                "{},{}",
+                name,
+                self.formatted_cells(row, number_format).join(",")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the matrix as a JSON array with an object per row, using `null` for empty cells.
+    pub fn write_json<W: Write>(
+        &self,
+        out: &mut W,
+        number_format: &NumberFormat,
+    ) -> io::Result<()> {
+        write!(out, "[")?;
+        for (i, row) in self.rows.iter().enumerate() {
+            if i != 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "{{\"name\": ")?;
+            json::write_string(out, &row.name)?;
+            for (column, cell) in self.columns.iter().zip(&row.cells) {
+                write!(out, ", ")?;
+                json::write_string(out, column)?;
+                write!(out, ": ")?;
+                match cell {
+                    Some(value) => json::write_number(out, *value, number_format)?,
+                    None => write!(out, "null")?,
+                }
+            }
+            write!(out, "}}")?;
+        }
+        writeln!(out, "]")
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::storage::Counts;
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// Why `merge_weighted` couldn't merge a set of counters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeError {
+    /// There were no counters to take the sequence length from.
+    NoCounters,
+    /// The counter at `index` has a different sequence length than the first.
+    SequenceLengthMismatch {
+        index: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::NoCounters => write!(f, "there are no counters to merge"),
+            MergeError::SequenceLengthMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "counter {} has sequence length {}, but the first has {}",
+                index, found, expected
+            ),
+        }
+    }
+}
+
+impl Error for MergeError {}
+
+impl DigitSequenceCounter {
+    /// Merges counters of one sequence length into a new, adaptive counter whose count of each
+    /// sequence is `Σ weight * count` over the `(counter, weight)` pairs, rounded to the
+    /// nearest integer, so inputs of different importance can be combined. Weights don't need
+    /// to sum to 1. The sums are taken in `f64`, so they can't overflow but are only exact up
+    /// to 2^53; a sum beyond `u128::MAX` saturates, and a negative or NaN one becomes 0.
+    ///
+    /// ```
+    /// use digit_sequence_counter::merge::MergeError;
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut a = DigitSequenceCounter::new(2);
+    /// b"x3141592653".iter().for_each(|&byte| a.process_character(byte));
+    /// let mut b = DigitSequenceCounter::new(2);
+    /// b"x2718281828".iter().for_each(|&byte| b.process_character(byte));
+    ///
+    /// // Equal weights are the sum of the counters, scaled.
+    /// let merged = DigitSequenceCounter::merge_weighted(&[(a.clone(), 3.0), (b.clone(), 3.0)]);
+    /// let mut sum = a.clone();
+    /// sum += &b;
+    /// sum.apply_transform(|_, count| count * 3);
+    /// assert_eq!(merged, Ok(sum));
+    ///
+    /// let merged = DigitSequenceCounter::merge_weighted(&[(a.clone(), 0.5), (b.clone(), 2.0)]).unwrap();
+    /// assert_eq!(merged.count_at(0x18), 4);
+    /// assert_eq!(merged.count_at(0x14), 1);
+    /// assert_eq!(merged.count_at(0x15), 1);
+    ///
+    /// assert_eq!(
+    ///     DigitSequenceCounter::merge_weighted(&[(a, 1.0), (DigitSequenceCounter::new(3), 1.0)]),
+    ///     Err(MergeError::SequenceLengthMismatch { index: 1, expected: 2, found: 3 })
+    /// );
+    /// assert_eq!(DigitSequenceCounter::merge_weighted(&[]), Err(MergeError::NoCounters));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn merge_weighted(
+        counters: &[(DigitSequenceCounter, f64)],
+    ) -> Result<DigitSequenceCounter, MergeError> {
+        let sequence_length = match counters.first() {
+            Some((counter, _)) => counter.sequence_length,
+            None => return Err(MergeError::NoCounters),
+        };
+        let mut weighted = BTreeMap::new();
+        for (index, (counter, weight)) in counters.iter().enumerate() {
+            if counter.sequence_length != sequence_length {
+                return Err(MergeError::SequenceLengthMismatch {
+                    index,
+                    expected: sequence_length,
+                    found: counter.sequence_length,
+                });
+            }
+            if let Counts::HyperLogLog(_) | Counts::CountMin(..) = counter.sequence_counts {
+                panic!("Cannot weight the counts of a sketch, which doesn't keep them exactly.");
+            }
+            for (sequence_index, count) in counter.nonzero_counts() {
+                *weighted.entry(sequence_index).or_insert(0.0) += weight * count as f64;
+            }
+        }
+
+        let mut merged = DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive);
+        for (sequence_index, count) in weighted {
+            // `as` saturates, which takes negative and NaN sums to 0.
+            merged.add_count(sequence_index, count.round() as u128);
+        }
+        Ok(merged)
+    }
+}
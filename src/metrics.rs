@@ -0,0 +1,71 @@
+use std::io::{self, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+use crate::DigitSequenceCounter;
+
+/// What `process_reader_with_metrics` saw while counting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProcessingMetrics {
+    pub bytes_read: u64,
+    pub hex_chars_processed: u64,
+    /// Non-hex bytes, each of which reset the window. Many resets relative to the hex
+    /// characters mean fragmented input.
+    pub window_resets: u64,
+    pub elapsed: Duration,
+}
+
+impl DigitSequenceCounter {
+    /// Counts every byte of `reader` with `process_character` and reports how much was read,
+    /// how much of it was hex, and how long it took.
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let input = b"3.14x15\n9";
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// let metrics = counter
+    ///     .process_reader_with_metrics(input[..4].chain(&input[4..]))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     (metrics.bytes_read, metrics.hex_chars_processed, metrics.window_resets),
+    ///     (9, 6, 3)
+    /// );
+    ///
+    /// let mut expected = DigitSequenceCounter::new(1);
+    /// expected.process_bytes(input);
+    /// assert_eq!(counter, expected);
+    /// assert_eq!(counter.nonzero_counts(), vec![(4, 1), (5, 1)]);
+    /// ```
+    pub fn process_reader_with_metrics<R: Read>(
+        &mut self,
+        mut reader: R,
+    ) -> io::Result<ProcessingMetrics> {
+        let started = Instant::now();
+        let mut bytes_read = 0;
+        let mut hex_chars_processed = 0;
+        let mut buffer = vec![0; 65536];
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            bytes_read += n as u64;
+            for &byte in &buffer[..n] {
+                if byte.is_ascii_hexdigit() {
+                    hex_chars_processed += 1;
+                }
+                self.process_character(byte);
+            }
+        }
+        Ok(ProcessingMetrics {
+            bytes_read,
+            hex_chars_processed,
+            window_resets: bytes_read - hex_chars_processed,
+            elapsed: started.elapsed(),
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use crate::DigitSequenceCounter;
+
+/// The counts of every sequence that differs from a target in exactly one digit position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Neighborhood {
+    pub target: usize,
+    pub target_count: u128,
+    /// `counts[position][digit]` is the count of the target with the digit at `position`
+    /// (0 being the leftmost) replaced by `digit`. The target's own digits hold its count.
+    pub counts: Vec<[u128; 16]>,
+    /// The mean count of the `15 * sequence_length` neighbors, excluding the target.
+    pub mean: f64,
+}
+
+impl Neighborhood {
+    /// Returns the target's digit at `position`, counting from the left.
+    pub fn target_digit(&self, position: usize) -> usize {
+        let shift = (self.counts.len() - 1 - position) << 2;
+        (self.target >> shift) & 0xF
+    }
+}
+
+impl DigitSequenceCounter {
+    /// Looks up the Hamming-distance-1 neighborhood of `target`. Sequences never seen count
+    /// as zero, so this works with any storage.
+    pub fn neighborhood(&self, target: usize) -> Neighborhood {
+        let target_count = self.count_at(target);
+        let mut total = 0;
+        let counts = (0..self.sequence_length)
+            .map(|position| {
+                let shift = (self.sequence_length - 1 - position) << 2;
+                let mut row = [0; 16];
+                for (digit, count) in row.iter_mut().enumerate() {
+                    let neighbor = (target & !(0xF << shift)) | (digit << shift);
+                    *count = self.count_at(neighbor);
+                    if neighbor != target {
+                        total += *count;
+                    }
+                }
+                row
+            })
+            .collect();
+        Neighborhood {
+            target,
+            target_count,
+            counts,
+            mean: total as f64 / (15 * self.sequence_length) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighborhood_matches_hand_counts() {
+        // Each run of three digits counts only its last two, so the pairs seen are
+        // 12, 13, 22, 12, A2, and 19.
+        let mut counter = DigitSequenceCounter::new(2);
+        b"012 013 022 012 0A2 019 345"
+            .iter()
+            .for_each(|&byte| counter.process_character(byte));
+        let neighborhood = counter.neighborhood(0x12);
+
+        let mut first = [0; 16];
+        first[0x1] = 2;
+        first[0x2] = 1;
+        first[0xA] = 1;
+        let mut second = [0; 16];
+        second[0x2] = 2;
+        second[0x3] = 1;
+        second[0x9] = 1;
+        assert_eq!(
+            neighborhood,
+            Neighborhood {
+                target: 0x12,
+                target_count: 2,
+                counts: vec![first, second],
+                mean: 4.0 / 30.0,
+            }
+        );
+        assert_eq!(neighborhood.target_digit(0), 0x1);
+        assert_eq!(neighborhood.target_digit(1), 0x2);
+    }
+
+    #[test]
+    fn unseen_target_has_an_empty_neighborhood() {
+        let mut counter = DigitSequenceCounter::new(3);
+        b"x31415"
+            .iter()
+            .for_each(|&byte| counter.process_character(byte));
+        let neighborhood = counter.neighborhood(0xFFF);
+        assert_eq!(neighborhood.target_count, 0);
+        assert!(neighborhood
+            .counts
+            .iter()
+            .flatten()
+            .all(|&count| count == 0));
+        assert_eq!(neighborhood.mean, 0.0);
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// How sequences are written wherever a counter labels them.
+///
+/// Counters read every digit as a hex digit, and the digits of radix 2 and 10 are the hex
+/// digits `0` and `1` and `0` through `9`, so the three digit notations write the same
+/// characters: each digit as it appeared in the input, zero-padded to the sequence length.
+/// They differ in what they claim about the input, which outputs like the summary file
+/// record. A digit above the notation's radix, which an input in that radix never has, is
+/// still written as its hex digit.
+///
+/// `Value` writes the packed sequence index instead, as a decimal integer, for joining
+/// against data keyed by it.
+///
+/// ```
+/// use digit_sequence_counter::notation::SequenceNotation;
+///
+/// // 0110 in radix 2, 0314 in radix 10, and 0A3F in radix 16.
+/// for &(sequence_index, digits, value) in &[
+///     (0x0110, "0110", "272"),
+///     (0x0314, "0314", "788"),
+///     (0x0A3F, "0A3F", "2623"),
+/// ] {
+///     assert_eq!(SequenceNotation::Binary.label(sequence_index, 4), digits);
+///     assert_eq!(SequenceNotation::Decimal.label(sequence_index, 4), digits);
+///     assert_eq!(SequenceNotation::Hex.label(sequence_index, 4), digits);
+///     assert_eq!(SequenceNotation::Value.label(sequence_index, 4), value);
+/// }
+/// assert_eq!(SequenceNotation::Decimal.label(0x7, 3), "007");
+///
+/// assert_eq!(SequenceNotation::for_radix(2), SequenceNotation::Binary);
+/// assert_eq!(SequenceNotation::for_radix(10), SequenceNotation::Decimal);
+/// assert_eq!(SequenceNotation::for_radix(16), SequenceNotation::Hex);
+/// assert_eq!("value".parse(), Ok(SequenceNotation::Value));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SequenceNotation {
+    Binary,
+    Decimal,
+    #[default]
+    Hex,
+    Value,
+}
+
+impl SequenceNotation {
+    /// Returns the digit notation for an input in `radix`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` isn't 2, 10, or 16.
+    pub fn for_radix(radix: u32) -> SequenceNotation {
+        match radix {
+            2 => SequenceNotation::Binary,
+            10 => SequenceNotation::Decimal,
+            16 => SequenceNotation::Hex,
+            _ => panic!("No sequence notation for radix {}.", radix),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SequenceNotation::Binary => "binary",
+            SequenceNotation::Decimal => "decimal",
+            SequenceNotation::Hex => "hex",
+            SequenceNotation::Value => "value",
+        }
+    }
+
+    /// Writes the sequence at `sequence_index` of `sequence_length` digits.
+    pub fn label(self, sequence_index: usize, sequence_length: usize) -> String {
+        match self {
+            SequenceNotation::Value => sequence_index.to_string(),
+            _ => format!("{:0width$X}", sequence_index, width = sequence_length),
+        }
+    }
+}
+
+impl FromStr for SequenceNotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SequenceNotation, String> {
+        match s {
+            "binary" => Ok(SequenceNotation::Binary),
+            "decimal" => Ok(SequenceNotation::Decimal),
+            "hex" => Ok(SequenceNotation::Hex),
+            "value" => Ok(SequenceNotation::Value),
+            _ => Err(format!(
+                "expected binary, decimal, hex, or value, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for SequenceNotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
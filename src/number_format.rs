@@ -0,0 +1,87 @@
+pub const DEFAULT_PRECISION: usize = 6;
+pub const MAX_PRECISION: usize = 17;
+
+/// How report writers render floating-point statistics.
+///
+/// Values are rounded to `significant_digits` and trailing zeros are dropped, so the same
+/// statistic always prints the same way and runs can be compared with `diff`. The decimal
+/// separator is always `.`, since Rust's formatting never consults the locale. Values whose
+/// rounded decimal exponent falls outside `min_exponent..max_exponent` use scientific
+/// notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub significant_digits: usize,
+    pub min_exponent: i32,
+    pub max_exponent: i32,
+}
+
+impl Default for NumberFormat {
+    fn default() -> NumberFormat {
+        NumberFormat::new(DEFAULT_PRECISION)
+    }
+}
+
+impl NumberFormat {
+    pub fn new(significant_digits: usize) -> NumberFormat {
+        if significant_digits == 0 || significant_digits > MAX_PRECISION {
+            panic!(
+                "Cannot format numbers with fewer than 1 or more than {} significant digits.",
+                MAX_PRECISION
+            );
+        }
+        NumberFormat {
+            significant_digits,
+            min_exponent: -4,
+            max_exponent: 15,
+        }
+    }
+
+    /// Formats `value`, writing exact integers without a decimal point.
+    ///
+    /// ```
+    /// use digit_sequence_counter::number_format::NumberFormat;
+    ///
+    /// let format = NumberFormat::default();
+    /// assert_eq!(format.format(19.0), "19");
+    /// assert_eq!(format.format(-0.0), "0");
+    /// assert_eq!(format.format(0.5625), "0.5625");
+    /// assert_eq!(format.format(18.894736842105264), "18.8947");
+    /// assert_eq!(format.format(3.0761025), "3.0761");
+    /// assert_eq!(format.format(9.9999999), "10");
+    /// assert_eq!(format.format(0.000123456789), "0.000123457");
+    /// assert_eq!(format.format(1.2345678e-12), "1.23457e-12");
+    /// assert_eq!(format.format(0.5e300), "5e299");
+    /// assert_eq!(format.format(123456789012345.6), "123457000000000");
+    /// assert_eq!(format.format(f64::NAN), "NaN");
+    /// assert_eq!(NumberFormat::new(2).format(2.0 / 3.0), "0.67");
+    /// ```
+    pub fn format(&self, value: f64) -> String {
+        if value == 0.0 {
+            return String::from("0");
+        }
+        if !value.is_finite() {
+            return value.to_string();
+        }
+
+        let scientific = format!("{:.*e}", self.significant_digits - 1, value);
+        let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+        let exponent = exponent[1..].parse::<i32>().unwrap();
+
+        if exponent < self.min_exponent || exponent >= self.max_exponent {
+            return format!("{}e{}", trim_fraction(mantissa), exponent);
+        }
+        let decimals = (self.significant_digits as i32 - 1 - exponent).max(0) as usize;
+        let rounded = mantissa.parse::<f64>().unwrap() * 10_f64.powi(exponent);
+        let fixed = format!("{:.*}", decimals, rounded);
+        String::from(trim_fraction(&fixed))
+    }
+}
+
+/// Drops trailing zeros after a decimal point, and the point itself if nothing follows it.
+fn trim_fraction(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::DigitSequenceCounter;
+
+/// A count that was already `u128::MAX` when its sequence was seen again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OverflowError {
+    pub sequence_index: usize,
+    pub sequence_length: usize,
+}
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the count of sequence {:0width$X} would overflow",
+            self.sequence_index,
+            width = self.sequence_length
+        )
+    }
+}
+
+impl Error for OverflowError {}
+
+impl DigitSequenceCounter {
+    /// Creates a dense counter whose `process_character` checks each count before adding to
+    /// it, for uses where a wrapped count must never go unnoticed. A count that would overflow
+    /// stays at `u128::MAX`, and the first such overflow is kept for `overflow`.
+    ///
+    /// The check is one comparison against the count already being updated, so it costs
+    /// little, but ordinary counters skip it: a `u128` count can't overflow from any input
+    /// that can be read, even one byte per window.
+    ///
+    /// Counts merged in with `+=` aren't checked.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new_with_overflow_check(2);
+    /// b"x111".iter().for_each(|&byte| counter.process_character(byte));
+    /// counter.apply_transform(|_, count| count * u128::MAX);
+    /// assert_eq!(counter.overflow(), None);
+    ///
+    /// counter.process_character(b'1');
+    /// assert_eq!(counter.count_at(0x11), u128::MAX);
+    /// let overflow = counter.overflow().unwrap();
+    /// assert_eq!(overflow.to_string(), "the count of sequence 11 would overflow");
+    /// ```
+    pub fn new_with_overflow_check(sequence_length: usize) -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new(sequence_length);
+        counter.overflow_check = true;
+        counter
+    }
+
+    /// Returns the first overflow `process_character` caught in a counter created with
+    /// `new_with_overflow_check`.
+    pub fn overflow(&self) -> Option<OverflowError> {
+        self.overflow
+    }
+
+    /// Like `process_character`, but returns an error instead of counting a sequence whose
+    /// count is already `u128::MAX`, leaving the count as it was. This works with any
+    /// counter, checked or not. Sketches keep no exact counts to overflow and never fail.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// assert!(b"x77".iter().all(|&byte| counter.process_character_checked(byte).is_ok()));
+    /// counter.apply_transform(|_, count| count * u128::MAX);
+    ///
+    /// let err = counter.process_character_checked(b'7').unwrap_err();
+    /// assert_eq!((err.sequence_index, err.sequence_length), (7, 1));
+    /// assert_eq!(counter.count_at(7), u128::MAX);
+    /// assert!(counter.process_character_checked(b'8').is_ok());
+    /// ```
+    pub fn process_character_checked(&mut self, character: u8) -> Result<(), OverflowError> {
+        if self.shift_in(character) {
+            if self.count_at(self.current_sequence) == u128::MAX {
+                return Err(OverflowError {
+                    sequence_index: self.current_sequence,
+                    sequence_length: self.sequence_length,
+                });
+            }
+            self.count_current_sequence();
+        }
+        Ok(())
+    }
+
+    /// Counts the current window for a counter created with `new_with_overflow_check`.
+    pub(crate) fn count_current_sequence_checked(&mut self) {
+        if self.count_at(self.current_sequence) == u128::MAX {
+            if self.overflow.is_none() {
+                self.overflow = Some(OverflowError {
+                    sequence_index: self.current_sequence,
+                    sequence_length: self.sequence_length,
+                });
+            }
+        } else {
+            self.count_current_sequence();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DigitSequenceCounter;
+
+    #[test]
+    fn classified_digits_are_checked_too() {
+        let mut counter = DigitSequenceCounter::new_with_overflow_check(2);
+        [1, 1, 1]
+            .iter()
+            .for_each(|&digit| counter.process_digit(digit));
+        counter.apply_transform(|_, count| count * u128::MAX);
+        assert_eq!(counter.overflow(), None);
+
+        counter.process_digit(1);
+        assert_eq!(counter.count_at(0x11), u128::MAX);
+        assert_eq!(counter.overflow().map(|err| err.sequence_index), Some(0x11));
+        counter.process_digit(2);
+        assert_eq!(counter.count_at(0x12), 1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, ErrorKind, Read};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{DigitSequenceCounter, StorageMode};
+
+pub const DEFAULT_CHUNK_SIZE: usize = 64 << 20;
+
+/// Live statistics for one worker thread, readable while counting is in progress.
+#[derive(Debug, Default)]
+pub struct WorkerProgress {
+    chunks: AtomicU64,
+    bytes: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+impl WorkerProgress {
+    pub fn new() -> WorkerProgress {
+        WorkerProgress::default()
+    }
+
+    pub fn chunks(&self) -> u64 {
+        self.chunks.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn busy(&self) -> Duration {
+        Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn record_chunk(&self, bytes: usize, busy: Duration) {
+        self.chunks.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.busy_nanos
+            .fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+struct Chunk {
+    warm_up_len: usize,
+    bytes: Vec<u8>,
+}
+
+/// Counts everything after the first `.` in `reader` using one worker thread per entry of
+/// `workers`.
+///
+/// The input is read sequentially on the calling thread and cut into chunks of `chunk_size`
+/// digits, which idle workers pull from a shared queue. Each chunk carries the bytes just
+/// before it so workers can warm their windows up, which makes the merged result identical
+/// to feeding every byte to a single set of counters.
+pub fn count_in_chunks<R: Read>(
+    reader: R,
+    sequence_lengths: RangeInclusive<usize>,
+    storage_mode: StorageMode,
+    chunk_size: usize,
+    workers: &[WorkerProgress],
+) -> io::Result<Vec<DigitSequenceCounter>> {
+    count_in_chunks_with(
+        reader,
+        sequence_lengths,
+        |sequence_length| DigitSequenceCounter::with_storage(sequence_length, storage_mode),
+        chunk_size,
+        workers,
+    )
+}
+
+/// Like `count_in_chunks`, but creates each worker's counters with `new_counter`, such as
+/// sketches of a chosen size.
+pub fn count_in_chunks_with<R, F>(
+    mut reader: R,
+    sequence_lengths: RangeInclusive<usize>,
+    new_counter: F,
+    chunk_size: usize,
+    workers: &[WorkerProgress],
+) -> io::Result<Vec<DigitSequenceCounter>>
+where
+    R: Read,
+    F: Fn(usize) -> DigitSequenceCounter + Sync,
+{
+    if workers.is_empty() || chunk_size == 0 {
+        panic!("Cannot count in chunks without workers or with empty chunks.");
+    }
+    let warm_up_size = *sequence_lengths.end();
+    let (sender, receiver) = mpsc::sync_channel::<Chunk>(workers.len() * 2);
+    let queue = Mutex::new(receiver);
+
+    thread::scope(|scope| {
+        let handles = workers
+            .iter()
+            .map(|progress| {
+                let queue = &queue;
+                let new_counter = &new_counter;
+                let sequence_lengths = sequence_lengths.clone();
+                scope.spawn(move || {
+                    let mut counters = sequence_lengths.map(new_counter).collect::<Vec<_>>();
+                    loop {
+                        let chunk = match queue.lock().unwrap().recv() {
+                            Ok(chunk) => chunk,
+                            Err(_) => break,
+                        };
+                        let started = Instant::now();
+                        let (warm_up, body) = chunk.bytes.split_at(chunk.warm_up_len);
+                        counters.iter_mut().for_each(|counter| {
+                            counter.reset_window();
+                            warm_up.iter().for_each(|&byte| counter.warm_up(byte));
+                            body.iter()
+                                .for_each(|&byte| counter.process_character(byte));
+                        });
+                        progress.record_chunk(body.len(), started.elapsed());
+                    }
+                    counters
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let read_result = split_into_chunks(&mut reader, chunk_size, warm_up_size, &sender);
+        drop(sender);
+
+        let mut merged: Option<Vec<DigitSequenceCounter>> = None;
+        for handle in handles {
+            let counters = handle.join().unwrap();
+            match &mut merged {
+                Some(merged) => merged
+                    .iter_mut()
+                    .zip(counters.iter())
+                    .for_each(|(merged, counter)| *merged += counter),
+                None => merged = Some(counters),
+            }
+        }
+        read_result.map(|()| merged.unwrap())
+    })
+}
+
+fn split_into_chunks<R: Read>(
+    reader: &mut R,
+    chunk_size: usize,
+    warm_up_size: usize,
+    sender: &mpsc::SyncSender<Chunk>,
+) -> io::Result<()> {
+    let mut buffer = vec![0; 65536];
+    let mut found_point = false;
+    let mut chunk = Vec::with_capacity(warm_up_size + chunk_size);
+    let mut warm_up_len = 0;
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let mut data = &buffer[..n];
+        if !found_point {
+            match data.iter().position(|&byte| byte == b'.') {
+                Some(point) => {
+                    found_point = true;
+                    data = &data[point + 1..];
+                }
+                None => continue,
+            }
+        }
+
+        while !data.is_empty() {
+            let take = (chunk_size - (chunk.len() - warm_up_len)).min(data.len());
+            chunk.extend_from_slice(&data[..take]);
+            data = &data[take..];
+
+            if chunk.len() - warm_up_len == chunk_size {
+                let mut next = Vec::with_capacity(warm_up_size + chunk_size);
+                next.extend_from_slice(&chunk[chunk.len() - warm_up_size.min(chunk.len())..]);
+                let next_warm_up_len = next.len();
+                let bytes = mem::replace(&mut chunk, next);
+                if sender.send(Chunk { warm_up_len, bytes }).is_err() {
+                    return Ok(());
+                }
+                warm_up_len = next_warm_up_len;
+            }
+        }
+    }
+
+    if chunk.len() > warm_up_len {
+        let _ = sender.send(Chunk {
+            warm_up_len,
+            bytes: chunk,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use arrow_array::{Array, Int32Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::{ParquetError, Result};
+use parquet::file::metadata::KeyValue;
+use parquet::file::reader::ChunkReader;
+
+use crate::notation::SequenceNotation;
+use crate::reporter::{LengthSummary, Reporter, SequenceRecord};
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// The key of the file metadata listing, comma-separated, every sequence length written,
+/// including those with no counts.
+pub const SEQUENCE_LENGTHS_KEY: &str = "dsc.sequence_lengths";
+
+/// The Arrow schema of the Parquet files written by `write_to_parquet`: one row per nonzero
+/// count, with the sequence as its uppercase hex label whatever the counter's sequence
+/// notation, so `read_from_parquet` can read it back.
+pub fn arrow_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("sequence_length", DataType::Int32, false),
+        Field::new("sequence", DataType::Utf8, false),
+        Field::new("count", DataType::Int64, false),
+    ])
+}
+
+/// Writes every counter's nonzero counts to `writer` as a Parquet file with the schema of
+/// `arrow_schema`, which is embedded so Arrow and Polars read the columns back with their
+/// types. Counts above `i64::MAX` are saturated. The sequence lengths written are also kept
+/// in the file's key-value metadata under `SEQUENCE_LENGTHS_KEY`, so a counter with no
+/// counts comes back from `read_from_parquet` too.
+pub fn write_to_parquet<W: Write + Send>(
+    counters: &[DigitSequenceCounter],
+    writer: W,
+) -> Result<()> {
+    let mut reporter = ParquetReporter::new(writer)?;
+    for counter in counters {
+        reporter.begin(counter.sequence_length());
+        for (sequence_index, count) in counter.nonzero_counts() {
+            reporter.push(sequence_index, count)?;
+        }
+        reporter.write_batch()?;
+    }
+    reporter.close()
+}
+
+/// Writes every length reported to it to one Parquet file, the same way `write_to_parquet`
+/// does, in record batches of at most `BATCH_ROWS` rows. The file is finished by `end_run`,
+/// or by `close` for lengths reported on their own.
+pub struct ParquetReporter<W: Write + Send> {
+    writer: Option<ArrowWriter<W>>,
+    schema: Arc<Schema>,
+    sequence_length: usize,
+    sequence_lengths: Vec<usize>,
+    sequences: Vec<String>,
+    counts: Vec<i64>,
+}
+
+impl<W: Write + Send> ParquetReporter<W> {
+    pub const BATCH_ROWS: usize = 65536;
+
+    pub fn new(writer: W) -> Result<ParquetReporter<W>> {
+        let schema = Arc::new(arrow_schema());
+        Ok(ParquetReporter {
+            writer: Some(ArrowWriter::try_new(writer, schema.clone(), None)?),
+            schema,
+            sequence_length: 0,
+            sequence_lengths: Vec::new(),
+            sequences: Vec::new(),
+            counts: Vec::new(),
+        })
+    }
+
+    fn begin(&mut self, sequence_length: usize) {
+        self.sequence_length = sequence_length;
+        if !self.sequence_lengths.contains(&sequence_length) {
+            self.sequence_lengths.push(sequence_length);
+        }
+    }
+
+    fn push(&mut self, sequence_index: usize, count: u128) -> Result<()> {
+        self.sequences
+            .push(SequenceNotation::Hex.label(sequence_index, self.sequence_length));
+        self.counts.push(i64::try_from(count).unwrap_or(i64::MAX));
+        if self.counts.len() == Self::BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        if self.counts.is_empty() {
+            return Ok(());
+        }
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => return Err(ParquetError::General(String::from("file already closed"))),
+        };
+        let columns: Vec<Arc<dyn Array>> = vec![
+            Arc::new(Int32Array::from(vec![
+                self.sequence_length as i32;
+                self.counts.len()
+            ])),
+            Arc::new(StringArray::from(std::mem::take(&mut self.sequences))),
+            Arc::new(Int64Array::from(std::mem::take(&mut self.counts))),
+        ];
+        writer.write(&RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+
+    /// Writes any rows still buffered and finishes the file.
+    pub fn close(&mut self) -> Result<()> {
+        self.write_batch()?;
+        if let Some(mut writer) = self.writer.take() {
+            let sequence_lengths = self
+                .sequence_lengths
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>();
+            writer.append_key_value_metadata(KeyValue::new(
+                String::from(SEQUENCE_LENGTHS_KEY),
+                sequence_lengths.join(","),
+            ));
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Reporter for ParquetReporter<W> {
+    fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+        self.begin(length.sequence_length);
+        Ok(())
+    }
+
+    fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()> {
+        self.push(record.sequence_index, record.count)
+            .map_err(io::Error::other)
+    }
+
+    fn end_length(&mut self) -> io::Result<()> {
+        self.write_batch().map_err(io::Error::other)
+    }
+
+    fn end_run(&mut self) -> io::Result<()> {
+        self.close().map_err(io::Error::other)
+    }
+}
+
+/// Reads a file written by `write_to_parquet` back into one adaptive counter per sequence
+/// length found, in ascending length order. Repeated sequences are summed. Lengths listed
+/// under `SEQUENCE_LENGTHS_KEY` come back even without rows; a file without that key, such
+/// as one written by other tools, only has the lengths of its rows.
+pub fn read_from_parquet<R: ChunkReader + 'static>(reader: R) -> Result<Vec<DigitSequenceCounter>> {
+    let mut counters = BTreeMap::new();
+    let builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+    let sequence_lengths = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == SEQUENCE_LENGTHS_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .unwrap_or("");
+    for sequence_length in sequence_lengths.split(',').filter(|s| !s.is_empty()) {
+        let sequence_length = match sequence_length.parse::<usize>() {
+            Ok(sequence_length)
+                if sequence_length <= DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH =>
+            {
+                sequence_length
+            }
+            _ => {
+                return Err(ParquetError::General(format!(
+                    "{} metadata: invalid sequence length {}",
+                    SEQUENCE_LENGTHS_KEY, sequence_length
+                )))
+            }
+        };
+        counters.insert(
+            sequence_length,
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive),
+        );
+    }
+    for batch in builder.build()? {
+        let batch = batch?;
+        let sequence_lengths = column::<Int32Array>(&batch, "sequence_length")?;
+        let sequences = column::<StringArray>(&batch, "sequence")?;
+        let counts = column::<Int64Array>(&batch, "count")?;
+        for row in 0..batch.num_rows() {
+            let sequence_length = match usize::try_from(sequence_lengths.value(row)) {
+                Ok(sequence_length)
+                    if sequence_length <= DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH =>
+                {
+                    sequence_length
+                }
+                _ => {
+                    return Err(ParquetError::General(format!(
+                        "row {}: invalid sequence length {}",
+                        row,
+                        sequence_lengths.value(row)
+                    )))
+                }
+            };
+            let counter = counters.entry(sequence_length).or_insert_with(|| {
+                DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive)
+            });
+            let sequence_index = counter
+                .sequence_string_to_index(sequences.value(row))
+                .map_err(|err| ParquetError::General(format!("row {}: {}", row, err)))?;
+            let count = u128::try_from(counts.value(row)).map_err(|_| {
+                ParquetError::General(format!("row {}: negative count {}", row, counts.value(row)))
+            })?;
+            counter.add_count(sequence_index, count);
+        }
+    }
+    Ok(counters.into_values().collect())
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a T> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<T>())
+        .ok_or_else(|| ParquetError::General(format!("missing or mistyped column {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_then_read(counters: &[DigitSequenceCounter]) -> Vec<DigitSequenceCounter> {
+        let mut file = Vec::new();
+        write_to_parquet(counters, &mut file).unwrap();
+        read_from_parquet(bytes::Bytes::from(file)).unwrap()
+    }
+
+    #[test]
+    fn counters_of_several_lengths_come_back() {
+        let counters = (1..=4)
+            .map(|sequence_length| {
+                let mut counter =
+                    DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse);
+                counter.process_bytes(b"3.14159265358979 CAFE BABE");
+                counter
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(write_then_read(&counters), counters);
+    }
+
+    #[test]
+    fn rows_past_one_batch_come_back() {
+        let mut counter = DigitSequenceCounter::with_storage(5, StorageMode::Sparse);
+        for index in 0..ParquetReporter::<Vec<u8>>::BATCH_ROWS + 100 {
+            counter.process_bytes(format!(" {:06X}", index).as_bytes());
+        }
+        assert!(counter.nonzero_counts().len() > ParquetReporter::<Vec<u8>>::BATCH_ROWS);
+        assert_eq!(write_then_read(&[counter.clone()]), vec![counter]);
+    }
+
+    #[test]
+    fn counters_without_counts_come_back() {
+        let mut counted = DigitSequenceCounter::new(2);
+        counted.process_bytes(b"x31415");
+        let counters = [
+            DigitSequenceCounter::new(1),
+            counted,
+            DigitSequenceCounter::new(3),
+        ];
+        let read = write_then_read(&counters);
+        assert_eq!(read, counters);
+        assert_eq!(
+            read.iter()
+                .map(DigitSequenceCounter::sequence_length)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(write_then_read(&[]), Vec::new());
+    }
+}
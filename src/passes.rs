@@ -0,0 +1,263 @@
+use std::fmt::{self, Display};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::storage::DEFAULT_SKETCH_BITS;
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// How many times to scan the input, each time counting only some of the lengths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Passes {
+    /// As few passes as fit the memory budget.
+    Auto,
+    Count(usize),
+}
+
+impl Display for Passes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Passes::Auto => f.write_str("auto"),
+            Passes::Count(passes) => write!(f, "{}", passes),
+        }
+    }
+}
+
+impl FromStr for Passes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Passes, String> {
+        match s {
+            "auto" => Ok(Passes::Auto),
+            _ => match s.parse() {
+                Ok(0) => Err(String::from("at least one pass is needed")),
+                Ok(passes) => Ok(Passes::Count(passes)),
+                Err(_) => Err(format!("expected auto or a number of passes, not {:?}", s)),
+            },
+        }
+    }
+}
+
+/// Why no pass plan fits the memory budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlanError {
+    /// One length's table alone is over the budget.
+    LengthOverBudget {
+        sequence_length: usize,
+        bytes: usize,
+        max_memory: usize,
+    },
+    /// The requested number of passes leaves a pass over the budget.
+    PassOverBudget {
+        sequence_lengths: RangeInclusive<usize>,
+        bytes: usize,
+        max_memory: usize,
+    },
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::LengthOverBudget {
+                sequence_length,
+                bytes,
+                max_memory,
+            } => write!(
+                f,
+                "length {} alone needs about {} bytes, over the memory budget of {} bytes",
+                sequence_length, bytes, max_memory
+            ),
+            PlanError::PassOverBudget {
+                sequence_lengths,
+                bytes,
+                max_memory,
+            } => write!(
+                f,
+                "the pass for lengths {}-{} needs about {} bytes, over the memory budget of {} bytes",
+                sequence_lengths.start(),
+                sequence_lengths.end(),
+                bytes,
+                max_memory
+            ),
+        }
+    }
+}
+
+/// Returns roughly the most memory a counter's table can use.
+pub fn table_bytes(sequence_length: usize, storage_mode: StorageMode) -> usize {
+    let possible_sequences = 1_usize << (sequence_length << 2);
+    match storage_mode {
+        StorageMode::Dense | StorageMode::Adaptive => {
+            possible_sequences.saturating_mul(mem::size_of::<u128>())
+        }
+        StorageMode::Sparse => {
+            possible_sequences.saturating_mul(mem::size_of::<(usize, u128)>() + 1)
+        }
+        StorageMode::HyperLogLog => 1 << DEFAULT_SKETCH_BITS,
+        StorageMode::Sketch => DigitSequenceCounter::count_min_bytes(
+            DigitSequenceCounter::DEFAULT_SKETCH_WIDTH,
+            DigitSequenceCounter::DEFAULT_SKETCH_DEPTH,
+        ),
+    }
+}
+
+/// Splits `sequence_lengths` into consecutive groups to count one pass at a time.
+///
+/// Tables grow sixteenfold per length, so cheap lengths share passes and expensive ones get
+/// passes of their own. `Passes::Auto` fills each pass with ascending lengths until the next
+/// one would go over `max_memory`, using a single pass without a budget. `Passes::Count(n)`
+/// gives the `n - 1` longest lengths a pass each and counts the rest together, using one pass
+/// per length if `n` is at least the number of lengths.
+pub fn plan_passes(
+    sequence_lengths: RangeInclusive<usize>,
+    storage_mode: StorageMode,
+    passes: Passes,
+    max_memory: Option<usize>,
+) -> Result<Vec<RangeInclusive<usize>>, PlanError> {
+    if sequence_lengths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (first, last) = (*sequence_lengths.start(), *sequence_lengths.end());
+    let pass_bytes = |lengths: &RangeInclusive<usize>| {
+        lengths
+            .clone()
+            .map(|sequence_length| table_bytes(sequence_length, storage_mode))
+            .fold(0_usize, usize::saturating_add)
+    };
+
+    let plan = match (passes, max_memory) {
+        (Passes::Auto, None) => vec![sequence_lengths],
+        (Passes::Auto, Some(max_memory)) => {
+            let mut plan = Vec::new();
+            let mut start = first;
+            let mut bytes = 0_usize;
+            for sequence_length in sequence_lengths {
+                let length_bytes = table_bytes(sequence_length, storage_mode);
+                if length_bytes > max_memory {
+                    return Err(PlanError::LengthOverBudget {
+                        sequence_length,
+                        bytes: length_bytes,
+                        max_memory,
+                    });
+                }
+                if bytes.saturating_add(length_bytes) > max_memory {
+                    plan.push(start..=sequence_length - 1);
+                    start = sequence_length;
+                    bytes = 0;
+                }
+                bytes = bytes.saturating_add(length_bytes);
+            }
+            plan.push(start..=last);
+            plan
+        }
+        (Passes::Count(passes), _) => {
+            let alone = passes.saturating_sub(1).min(last + 1 - first);
+            let alone_start = last + 1 - alone;
+            let mut plan = Vec::with_capacity(alone + 1);
+            if alone_start > first {
+                plan.push(first..=alone_start - 1);
+            }
+            plan.extend(
+                (alone_start..=last).map(|sequence_length| sequence_length..=sequence_length),
+            );
+            plan
+        }
+    };
+
+    if let Some(max_memory) = max_memory {
+        for lengths in &plan {
+            let bytes = pass_bytes(lengths);
+            if bytes > max_memory {
+                return Err(PlanError::PassOverBudget {
+                    sequence_lengths: lengths.clone(),
+                    bytes,
+                    max_memory,
+                });
+            }
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(
+        sequence_lengths: RangeInclusive<usize>,
+        passes: Passes,
+        max_memory: Option<usize>,
+    ) -> Result<Vec<RangeInclusive<usize>>, PlanError> {
+        plan_passes(sequence_lengths, StorageMode::Dense, passes, max_memory)
+    }
+
+    #[test]
+    fn dense_tables_grow_sixteenfold() {
+        assert_eq!(table_bytes(1, StorageMode::Dense), 256);
+        assert_eq!(table_bytes(2, StorageMode::Dense), 4096);
+        assert_eq!(table_bytes(3, StorageMode::Dense), 65536);
+    }
+
+    #[test]
+    fn auto_without_a_budget_uses_one_pass() {
+        assert_eq!(plan(1..=8, Passes::Auto, None), Ok(vec![1..=8]));
+    }
+
+    #[test]
+    fn auto_fills_each_pass_until_the_budget() {
+        // 256 + 4096 bytes fit, but the 65536 bytes of length 3 don't fit beside them.
+        assert_eq!(
+            plan(1..=3, Passes::Auto, Some(66_000)),
+            Ok(vec![1..=2, 3..=3])
+        );
+        assert_eq!(plan(1..=3, Passes::Auto, Some(69_888)), Ok(vec![1..=3]));
+        assert_eq!(
+            plan(1..=2, Passes::Auto, Some(4096)),
+            Ok(vec![1..=1, 2..=2])
+        );
+    }
+
+    #[test]
+    fn auto_fails_for_a_length_over_the_budget() {
+        assert_eq!(
+            plan(1..=4, Passes::Auto, Some(66_000)),
+            Err(PlanError::LengthOverBudget {
+                sequence_length: 4,
+                bytes: 1 << 20,
+                max_memory: 66_000,
+            })
+        );
+    }
+
+    #[test]
+    fn counted_passes_give_the_longest_lengths_their_own() {
+        assert_eq!(plan(1..=5, Passes::Count(1), None), Ok(vec![1..=5]));
+        assert_eq!(
+            plan(1..=5, Passes::Count(3), None),
+            Ok(vec![1..=3, 4..=4, 5..=5])
+        );
+        assert_eq!(
+            plan(2..=4, Passes::Count(10), None),
+            Ok(vec![2..=2, 3..=3, 4..=4])
+        );
+    }
+
+    #[test]
+    fn counted_passes_fail_for_a_pass_over_the_budget() {
+        assert_eq!(
+            plan(1..=3, Passes::Count(2), Some(4096)),
+            Err(PlanError::PassOverBudget {
+                sequence_lengths: 1..=2,
+                bytes: 4352,
+                max_memory: 4096,
+            })
+        );
+    }
+
+    #[test]
+    fn no_lengths_need_no_passes() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 3..=2;
+        assert_eq!(plan(empty, Passes::Auto, Some(1)), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The longest path, in UTF-16 code units, that Windows opens without the extended-length
+/// prefix.
+pub const MAX_PATH: usize = 260;
+
+/// Returns the extended-length form of an absolute Windows path of at least `MAX_PATH`
+/// code units, with `\\?\` before a drive path and `\\?\UNC\` in place of the `\\` of a
+/// network path, or `None` for a path that doesn't need or can't take one: a shorter path,
+/// a relative path, one already prefixed, or one that isn't valid Unicode. The path is
+/// treated as Windows syntax whatever the platform, so the prefixing can be checked
+/// anywhere, but only Windows uses it.
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+///
+/// use digit_sequence_counter::paths::extended_length_path;
+///
+/// let nested = "archive\\".repeat(40);
+/// let long = format!("C:\\{}pi.txt", nested);
+/// let extended = extended_length_path(Path::new(&long)).unwrap();
+/// assert_eq!(extended, PathBuf::from(format!("\\\\?\\C:\\{}pi.txt", nested)));
+///
+/// let share = format!("\\\\server\\share\\{}pi.txt", nested);
+/// let extended = extended_length_path(Path::new(&share)).unwrap();
+/// assert_eq!(extended, PathBuf::from(format!("\\\\?\\UNC\\server\\share\\{}pi.txt", nested)));
+///
+/// assert_eq!(extended_length_path(Path::new("C:\\digits\\pi.txt")), None);
+/// assert_eq!(extended_length_path(Path::new(&format!("\\\\?\\{}", long))), None);
+/// assert_eq!(extended_length_path(Path::new(&format!("{}pi.txt", nested))), None);
+/// ```
+pub fn extended_length_path(path: &Path) -> Option<PathBuf> {
+    let text = path.to_str()?;
+    if text.encode_utf16().count() < MAX_PATH || text.starts_with(r"\\?\") {
+        return None;
+    }
+    if let Some(share) = text.strip_prefix(r"\\") {
+        return Some(PathBuf::from(format!(r"\\?\UNC\{}", share)));
+    }
+    let bytes = text.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\" {
+        return Some(PathBuf::from(format!(r"\\?\{}", text)));
+    }
+    None
+}
+
+/// Returns `path` as it should be handed to the operating system: on Windows, a path too
+/// long for `MAX_PATH` is made absolute and given its extended-length form, and everywhere
+/// else, or for a short path, `path` is returned unchanged.
+pub fn for_opening(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        if let Some(extended) = std::path::absolute(path)
+            .ok()
+            .and_then(|absolute| extended_length_path(&absolute))
+        {
+            return Cow::Owned(extended);
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Opens `path` for reading like `File::open`, through `for_opening`. Paths are kept as
+/// `OsStr`s throughout, so a file name that isn't valid UTF-8 opens like any other.
+///
+/// ```
+/// # #[cfg(unix)]
+/// # {
+/// use std::ffi::OsStr;
+/// use std::fs;
+/// use std::os::unix::ffi::OsStrExt;
+///
+/// use digit_sequence_counter::paths;
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+///
+/// let directory = std::env::temp_dir().join(format!("dsc-paths-{}", std::process::id()));
+/// fs::create_dir_all(&directory).unwrap();
+/// let path = directory.join(OsStr::from_bytes(b"pi-\xFF.txt"));
+/// fs::write(&path, "3.1415").unwrap();
+///
+/// let report = pipeline::count_digits(paths::open(&path).unwrap(), &CountOptions::new(1..=1));
+/// assert_eq!(report.unwrap().counters[0].total_count(), 3);
+/// assert!(path.display().to_string().starts_with(&*directory.to_string_lossy()));
+/// fs::remove_dir_all(&directory).unwrap();
+/// # }
+/// ```
+pub fn open(path: &Path) -> io::Result<File> {
+    File::open(for_opening(path))
+}
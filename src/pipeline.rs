@@ -0,0 +1,810 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, ErrorKind, Read, Write};
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::digit_filter::{self, ByteAction, DigitFilter, FilterConflict};
+use crate::drought::DroughtTracker;
+use crate::external::{ExternalCounter, ExternalCounts, ExternalStorage};
+use crate::number_format::NumberFormat;
+use crate::parallel::{self, WorkerProgress};
+use crate::reporter::{self, JsonReporter, Reporter, RunMeta, TextReporter};
+use crate::reproducibility::DigestReader;
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// Where counting starts in the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipBehavior {
+    /// Skips everything up to and including the first `.`, such as the `3.` of pi.
+    ThroughFirstPoint,
+    /// Counts from the first byte.
+    None,
+}
+
+/// Everything `count_digits` needs to know about how to count.
+#[derive(Clone, Debug)]
+pub struct CountOptions {
+    pub sequence_lengths: RangeInclusive<usize>,
+    pub storage: StorageMode,
+    /// The count-min sketch dimensions for `StorageMode::Sketch`.
+    pub sketch_width: usize,
+    pub sketch_depth: usize,
+    pub skip: SkipBehavior,
+    /// Bytes dropped before counting, so they neither count nor break a sequence, such as
+    /// the line breaks of a wrapped digit file. Any other non-digit byte breaks the sequence.
+    pub separators: Vec<u8>,
+    /// With more than one thread, the input is counted in chunks of `chunk_size` digits.
+    pub threads: usize,
+    pub chunk_size: usize,
+    /// Tracks droughts for this sequence length, which needs a single thread.
+    pub drought_length: Option<usize>,
+    /// Computes this checksum of the whole input alongside counting.
+    pub checksum: Option<ChecksumAlgorithm>,
+    /// Also counts every length over only the digits `0`-`9` into radix-10 counters, with
+    /// `A`-`F` breaking the sequence there. This needs a single thread.
+    pub decimal_subset: bool,
+}
+
+impl CountOptions {
+    /// Counts `sequence_lengths` on one thread into dense tables, skipping through the first
+    /// `.` and without separators or drought tracking.
+    pub fn new(sequence_lengths: RangeInclusive<usize>) -> CountOptions {
+        CountOptions {
+            sequence_lengths,
+            storage: StorageMode::Dense,
+            sketch_width: DigitSequenceCounter::DEFAULT_SKETCH_WIDTH,
+            sketch_depth: DigitSequenceCounter::DEFAULT_SKETCH_DEPTH,
+            skip: SkipBehavior::ThroughFirstPoint,
+            separators: Vec::new(),
+            threads: 1,
+            chunk_size: parallel::DEFAULT_CHUNK_SIZE,
+            drought_length: None,
+            checksum: None,
+            decimal_subset: false,
+        }
+    }
+
+    /// Compiles the byte classification these options ask for, failing if they give one
+    /// byte two different actions.
+    pub fn digit_filter(&self) -> Result<DigitFilter, FilterConflict> {
+        DigitFilter::builder().skip(&self.separators).build()
+    }
+
+    /// Creates an empty counter for `sequence_length` with this storage.
+    pub fn new_counter(&self, sequence_length: usize) -> DigitSequenceCounter {
+        match self.storage {
+            StorageMode::Sketch => DigitSequenceCounter::new_count_min(
+                sequence_length,
+                self.sketch_width,
+                self.sketch_depth,
+            ),
+            storage => DigitSequenceCounter::with_storage(sequence_length, storage),
+        }
+    }
+}
+
+/// Why `count_digits` failed.
+#[derive(Debug)]
+pub enum CountError {
+    InvalidOptions(String),
+    Io(io::Error),
+}
+
+impl Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountError::InvalidOptions(reason) => write!(f, "invalid count options: {}", reason),
+            CountError::Io(err) => write!(f, "error reading input: {}", err),
+        }
+    }
+}
+
+impl Error for CountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CountError::InvalidOptions(_) => None,
+            CountError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for CountError {
+    fn from(err: io::Error) -> CountError {
+        CountError::Io(err)
+    }
+}
+
+/// The result of `count_digits`: one counter per length in ascending order, plus what was
+/// learned about the input.
+///
+/// A report depends only on the input and the counting rules, never on the thread count,
+/// chunking, scheduling, or exact storage mode, so everything written from it is identical
+/// byte for byte across runs and machines: counts are merged with exact or saturating sums,
+/// sequences are reported in index order, and statistics are summed in index order.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use digit_sequence_counter::number_format::NumberFormat;
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+/// use digit_sequence_counter::StorageMode;
+///
+/// let mut state = 0x2545_F491_4F6C_DD1Du64;
+/// let mut input = b"3.".to_vec();
+/// for i in 0..100_000 {
+///     state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+///     input.push(b"0123456789ABCDEF"[(state >> 60) as usize]);
+///     if i % 997 == 0 {
+///         input.push(b'\n');
+///     }
+/// }
+///
+/// let report_bytes = |threads, storage| {
+///     let mut options = CountOptions::new(1..=4);
+///     options.threads = threads;
+///     options.storage = storage;
+///     options.chunk_size = 4093;
+///     let report = pipeline::count_digits(Cursor::new(&input), &options).unwrap();
+///     let number_format = NumberFormat::new(17);
+///     let mut out = Vec::new();
+///     report.write_text(&mut out, 0).unwrap();
+///     report.write_json(&mut out, &number_format).unwrap();
+///     for counter in &report.counters {
+///         counter.print_summary_with(&mut out, &number_format).unwrap();
+///         let distance = counter.total_variation_distance_from_uniform();
+///         out.extend_from_slice(&distance.to_bits().to_le_bytes());
+///     }
+///     out
+/// };
+///
+/// let expected = report_bytes(1, StorageMode::Dense);
+/// for &threads in &[1, 2, 8] {
+///     for &storage in &[StorageMode::Dense, StorageMode::Sparse, StorageMode::Adaptive] {
+///         assert!(report_bytes(threads, storage) == expected, "{} threads, {}", threads, storage);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Report {
+    pub counters: Vec<DigitSequenceCounter>,
+    pub drought_tracker: Option<DroughtTracker>,
+    /// Bytes read from the input, including skipped bytes and separators.
+    pub input_bytes: u64,
+    /// The input's hash with the `blake3` feature, as `blake3:<hex>`.
+    pub content_hash: Option<String>,
+    /// The requested checksum of the input as lowercase hex.
+    pub checksum: Option<String>,
+    /// One radix-10 counter per length for `decimal_subset`, and none otherwise.
+    pub decimal_subset: Vec<DigitSequenceCounter>,
+    /// The most consecutive hex digits counted, with separators dropped. A length's counter
+    /// only counts windows when this is more than the length, since the first window of each
+    /// run isn't counted.
+    pub longest_run: u64,
+}
+
+impl Report {
+    /// Writes the plain text report the CLI prints by default: each counter's
+    /// `unique [counts]` line, then the `drought_count` longest droughts if they were tracked.
+    pub fn write_text<W: Write>(&self, out: &mut W, drought_count: usize) -> io::Result<()> {
+        self.write_to(&mut TextReporter::new(&mut *out))?;
+        if let Some(drought_tracker) = &self.drought_tracker {
+            drought_tracker.write_longest(out, drought_count)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `sequence_length`'s counts as `sequence,count` CSV, returning an `InvalidInput`
+    /// error if that length wasn't counted.
+    pub fn write_csv<W: Write>(&self, out: &mut W, sequence_length: usize) -> io::Result<()> {
+        match self
+            .counters
+            .iter()
+            .find(|counter| counter.sequence_length() == sequence_length)
+        {
+            Some(counter) => counter.write_csv(out),
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("length {} was not counted", sequence_length),
+            )),
+        }
+    }
+
+    /// Writes the input statistics and a summary of each length as one JSON object.
+    pub fn write_json<W: Write>(
+        &self,
+        out: &mut W,
+        number_format: &NumberFormat,
+    ) -> io::Result<()> {
+        self.write_to(&mut JsonReporter::new(out, number_format))
+    }
+
+    /// Reports the run and every counter to `reporter`.
+    pub fn write_to(&self, reporter: &mut dyn Reporter) -> io::Result<()> {
+        let run = RunMeta {
+            sequence_lengths: self
+                .counters
+                .iter()
+                .map(DigitSequenceCounter::sequence_length)
+                .collect(),
+            input_bytes: self.input_bytes,
+            content_hash: self.content_hash.clone(),
+        };
+        reporter::report_run(&run, &self.counters, reporter)
+    }
+}
+
+/// Counts every length in `options` over `reader`, exactly as the CLI does.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+///
+/// let mut options = CountOptions::new(1..=2);
+/// options.separators = b"\n".to_vec();
+/// let report = pipeline::count_digits(Cursor::new(b"3.1415\n9265".to_vec()), &options).unwrap();
+///
+/// assert_eq!(report.input_bytes, 11);
+/// assert_eq!(report.counters[0].total_count(), 7);
+/// assert_eq!(report.counters[0].count_at(5), 2);
+/// assert_eq!(report.counters[1].total_count(), 6);
+/// assert_eq!(report.longest_run, 8);
+/// ```
+pub fn count_digits<R: Read>(reader: R, options: &CountOptions) -> Result<Report, CountError> {
+    let workers = (0..options.threads)
+        .map(|_| WorkerProgress::new())
+        .collect::<Vec<_>>();
+    count_digits_with_progress(reader, options, &workers)
+}
+
+/// The result of `count_digits_external`: one `ExternalCounts` per length in ascending order,
+/// plus what was learned about the input, as in `Report`.
+#[derive(Debug)]
+pub struct ExternalReport {
+    pub counts: Vec<ExternalCounts>,
+    pub input_bytes: u64,
+    pub content_hash: Option<String>,
+    pub checksum: Option<String>,
+    pub longest_run: u64,
+}
+
+impl ExternalReport {
+    /// Reports the run and every length's counts to `reporter`, streaming the counts from
+    /// disk.
+    pub fn write_to(&self, reporter: &mut dyn Reporter) -> io::Result<()> {
+        reporter.begin_run(&RunMeta {
+            sequence_lengths: self
+                .counts
+                .iter()
+                .map(ExternalCounts::sequence_length)
+                .collect(),
+            input_bytes: self.input_bytes,
+            content_hash: self.content_hash.clone(),
+        })?;
+        for counts in &self.counts {
+            counts.report_to(reporter)?;
+        }
+        reporter.end_run()
+    }
+}
+
+/// Counts every length in `options` over `reader` exactly with `ExternalCounter`s, keeping
+/// the windows in `storage` instead of tables. The storage mode, thread count, and chunk
+/// size are ignored, and droughts and the decimal subset aren't supported.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use digit_sequence_counter::external::ExternalStorage;
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+/// use digit_sequence_counter::reporter::TextReporter;
+/// use digit_sequence_counter::StorageMode;
+///
+/// let mut state = 1u64;
+/// let mut input = b"3.".to_vec();
+/// for i in 0..50_000 {
+///     state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+///     input.push(b"0123456789ABCDEF"[(state >> 60) as usize]);
+///     if i % 61 == 0 {
+///         input.push(b'\n');
+///     }
+/// }
+/// let mut options = CountOptions::new(1..=5);
+/// options.separators = b"\n".to_vec();
+///
+/// let mut storage = ExternalStorage::new(std::env::temp_dir());
+/// storage.run_windows = 1000;
+/// storage.merge_fan_in = 8;
+/// let external = pipeline::count_digits_external(Cursor::new(&input), &options, &storage).unwrap();
+///
+/// options.storage = StorageMode::Sparse;
+/// let sparse = pipeline::count_digits(Cursor::new(&input), &options).unwrap();
+///
+/// let mut external_text = Vec::new();
+/// external.write_to(&mut TextReporter::new(&mut external_text)).unwrap();
+/// let mut sparse_text = Vec::new();
+/// sparse.write_text(&mut sparse_text, 0).unwrap();
+/// assert_eq!(external_text, sparse_text);
+/// assert_eq!(
+///     (external.input_bytes, external.longest_run),
+///     (sparse.input_bytes, sparse.longest_run)
+/// );
+/// ```
+pub fn count_digits_external<R: Read>(
+    reader: R,
+    options: &CountOptions,
+    storage: &ExternalStorage,
+) -> Result<ExternalReport, CountError> {
+    let sequential = CountOptions {
+        threads: 1,
+        ..options.clone()
+    };
+    validate(&sequential)?;
+    if options.drought_length.is_some() || options.decimal_subset {
+        return Err(CountError::InvalidOptions(String::from(
+            "external storage doesn't track droughts or count the decimal subset",
+        )));
+    }
+    let mut counters = options
+        .sequence_lengths
+        .clone()
+        .map(|sequence_length| ExternalCounter::new(sequence_length, storage))
+        .collect::<io::Result<Vec<_>>>()?;
+    let ((), totals) = count_filtered(reader, options, |input| {
+        count_externally(input, &mut counters)
+    })?;
+    Ok(ExternalReport {
+        counts: counters
+            .into_iter()
+            .map(ExternalCounter::finish)
+            .collect::<io::Result<Vec<_>>>()?,
+        input_bytes: totals.input_bytes,
+        content_hash: totals.content_hash,
+        checksum: totals.checksum,
+        longest_run: totals.longest_run,
+    })
+}
+
+fn count_externally<R: Read>(mut input: R, counters: &mut [ExternalCounter]) -> io::Result<()> {
+    let mut buffer = vec![0; 65536];
+    let mut found_point = false;
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let mut data = &buffer[..n];
+        if !found_point {
+            match data.iter().position(|&byte| byte == b'.') {
+                Some(point) => {
+                    found_point = true;
+                    data = &data[point + 1..];
+                }
+                None => continue,
+            }
+        }
+        for &byte in data {
+            match digit_filter::HEX.action(byte) {
+                ByteAction::Emit(digit) => {
+                    for counter in counters.iter_mut() {
+                        counter.process_digit(digit)?;
+                    }
+                }
+                _ => counters.iter_mut().for_each(ExternalCounter::reset_window),
+            }
+        }
+    }
+}
+
+/// Like `count_digits`, but also reports the finished run to each of `reporters` in turn,
+/// as `Report::write_to` does. A reporter that fails stops the rest, and its error is
+/// returned.
+pub fn count_digits_with_reporters<R: Read>(
+    reader: R,
+    options: &CountOptions,
+    reporters: &mut [Box<dyn Reporter + '_>],
+) -> Result<Report, CountError> {
+    let report = count_digits(reader, options)?;
+    for reporter in reporters {
+        report.write_to(reporter.as_mut())?;
+    }
+    Ok(report)
+}
+
+/// Like `count_digits`, but records progress in `workers`, which needs one entry per thread
+/// and can be read from other threads while counting runs.
+pub fn count_digits_with_progress<R: Read>(
+    reader: R,
+    options: &CountOptions,
+    workers: &[WorkerProgress],
+) -> Result<Report, CountError> {
+    count(reader, options, workers, None)
+}
+
+/// Like `count_digits_with_progress`, but calls `on_snapshot` with the bytes counted so far
+/// and the counters as they stand after every `report_every` bytes counted, which don't
+/// include the skipped prefix or separators. Every read is counted as soon as it returns,
+/// so snapshots keep up with a pipe whose writer keeps it open. Snapshots need the counters
+/// in one place, so this only counts with one thread.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use digit_sequence_counter::parallel::WorkerProgress;
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+///
+/// let options = CountOptions::new(1..=1);
+/// let mut snapshots = Vec::new();
+/// let report = pipeline::count_digits_with_snapshots(
+///     Cursor::new(b"3.14159265".to_vec()),
+///     &options,
+///     &[WorkerProgress::new()],
+///     3,
+///     |counted, counters| snapshots.push((counted, counters[0].total_count())),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(snapshots, [(3, 2), (6, 5)]);
+/// assert_eq!(report.counters[0].total_count(), 7);
+/// ```
+pub fn count_digits_with_snapshots<R, F>(
+    reader: R,
+    options: &CountOptions,
+    workers: &[WorkerProgress],
+    report_every: u64,
+    mut on_snapshot: F,
+) -> Result<Report, CountError>
+where
+    R: Read,
+    F: FnMut(u64, &[DigitSequenceCounter]),
+{
+    if report_every == 0 {
+        return Err(CountError::InvalidOptions(String::from(
+            "snapshots need at least one byte between them",
+        )));
+    }
+    if options.threads > 1 {
+        return Err(CountError::InvalidOptions(String::from(
+            "snapshots need the counters in one place and cannot be threaded",
+        )));
+    }
+    count(
+        reader,
+        options,
+        workers,
+        Some((report_every, &mut on_snapshot)),
+    )
+}
+
+/// Called with the bytes counted so far and the counters after every so many bytes.
+type Snapshots<'a> = Option<(u64, &'a mut dyn FnMut(u64, &[DigitSequenceCounter]))>;
+
+fn count<R: Read>(
+    reader: R,
+    options: &CountOptions,
+    workers: &[WorkerProgress],
+    snapshots: Snapshots,
+) -> Result<Report, CountError> {
+    validate(options)?;
+    if workers.len() != options.threads {
+        return Err(CountError::InvalidOptions(format!(
+            "{} worker progress entries given for {} threads",
+            workers.len(),
+            options.threads
+        )));
+    }
+
+    let mut drought_tracker = options.drought_length.map(DroughtTracker::new);
+    let mut decimal_subset = Vec::new();
+    if options.decimal_subset {
+        decimal_subset = options
+            .sequence_lengths
+            .clone()
+            .map(|sequence_length| options.new_counter(sequence_length).with_radix(10))
+            .collect();
+    }
+    let (counters, totals) = count_filtered(reader, options, |input| {
+        if options.threads > 1 {
+            parallel::count_in_chunks_with(
+                input,
+                options.sequence_lengths.clone(),
+                |sequence_length| options.new_counter(sequence_length),
+                options.chunk_size,
+                workers,
+            )
+        } else {
+            count_sequentially(
+                input,
+                options,
+                drought_tracker.as_mut(),
+                &mut decimal_subset,
+                &workers[0],
+                snapshots,
+            )
+        }
+    })?;
+
+    Ok(Report {
+        counters,
+        drought_tracker,
+        input_bytes: totals.input_bytes,
+        content_hash: totals.content_hash,
+        checksum: totals.checksum,
+        decimal_subset,
+        longest_run: totals.longest_run,
+    })
+}
+
+/// What `count_filtered` learned about the input besides its counts, as `Report` has it.
+struct InputTotals {
+    input_bytes: u64,
+    content_hash: Option<String>,
+    checksum: Option<String>,
+    longest_run: u64,
+}
+
+/// Hashes `reader` and applies `options`' digit filter, then hands what's left to `count`,
+/// which counts it after its first point.
+fn count_filtered<R, T, F>(
+    reader: R,
+    options: &CountOptions,
+    count: F,
+) -> Result<(T, InputTotals), CountError>
+where
+    R: Read,
+    F: FnOnce(&mut dyn Read) -> io::Result<T>,
+{
+    let mut digest_reader = match options.checksum {
+        Some(algorithm) => DigestReader::with_checksum(reader, algorithm),
+        None => DigestReader::new(reader),
+    };
+    let (counted, longest_run) = {
+        let filtered = FilterReader {
+            inner: &mut digest_reader,
+            filter: options
+                .digit_filter()
+                .map_err(|err| CountError::InvalidOptions(err.to_string()))?,
+            offset: 0,
+            stopped: false,
+        };
+        // Every counting path skips through the first point, so a leading one makes it start
+        // at the first byte instead.
+        let leading_point: &[u8] = match options.skip {
+            SkipBehavior::ThroughFirstPoint => b"",
+            SkipBehavior::None => b".",
+        };
+        let mut input = RunTracker {
+            inner: leading_point.chain(filtered),
+            found_point: false,
+            current: 0,
+            longest: 0,
+        };
+        (count(&mut input)?, input.longest)
+    };
+    Ok((
+        counted,
+        InputTotals {
+            input_bytes: digest_reader.bytes_read(),
+            content_hash: digest_reader.content_hash(),
+            checksum: digest_reader.checksum(),
+            longest_run,
+        },
+    ))
+}
+
+fn validate(options: &CountOptions) -> Result<(), CountError> {
+    let invalid = |reason: String| Err(CountError::InvalidOptions(reason));
+    if *options.sequence_lengths.end() > DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH {
+        return invalid(format!(
+            "sequence lengths must not exceed {}",
+            DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH
+        ));
+    }
+    if options.threads == 0 {
+        return invalid(String::from("at least one thread is needed"));
+    }
+    if options.chunk_size == 0 {
+        return invalid(String::from("chunks must not be empty"));
+    }
+    if options.storage == StorageMode::Sketch
+        && (options.sketch_width == 0
+            || !DigitSequenceCounter::SKETCH_DEPTHS.contains(&options.sketch_depth))
+    {
+        return invalid(String::from(
+            "sketches need a width of at least 1 and a depth of 1 to 16",
+        ));
+    }
+    if let Some(drought_length) = options.drought_length {
+        if drought_length == 0 || drought_length > DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH {
+            return invalid(format!(
+                "drought length must be between 1 and {}",
+                DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH
+            ));
+        }
+        if options.threads > 1 {
+            return invalid(String::from(
+                "droughts need positions in input order and cannot be threaded",
+            ));
+        }
+    }
+    if options.decimal_subset && options.threads > 1 {
+        return invalid(String::from(
+            "the decimal subset is counted alongside one thread and cannot be threaded",
+        ));
+    }
+    if options.separators.contains(&b'.') && options.skip == SkipBehavior::ThroughFirstPoint {
+        return invalid(String::from(
+            "'.' cannot be a separator when skipping through the first point",
+        ));
+    }
+    Ok(())
+}
+
+fn count_sequentially<R: Read>(
+    mut input: R,
+    options: &CountOptions,
+    mut drought_tracker: Option<&mut DroughtTracker>,
+    decimal_subset: &mut [DigitSequenceCounter],
+    progress: &WorkerProgress,
+    mut snapshots: Snapshots,
+) -> io::Result<Vec<DigitSequenceCounter>> {
+    let mut counters = options
+        .sequence_lengths
+        .clone()
+        .map(|sequence_length| options.new_counter(sequence_length))
+        .collect::<Vec<_>>();
+    let mut buffer = vec![0; 65536];
+    let mut found_point = false;
+    let mut counted = 0;
+
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let started = Instant::now();
+        let mut data = &buffer[..n];
+        if !found_point {
+            match data.iter().position(|&byte| byte == b'.') {
+                Some(point) => {
+                    found_point = true;
+                    data = &data[point + 1..];
+                }
+                None => continue,
+            }
+        }
+        let read = data.len();
+        while !data.is_empty() {
+            let piece = match &snapshots {
+                Some((report_every, _)) => {
+                    let until_snapshot = report_every - counted % report_every;
+                    &data[..until_snapshot.min(data.len() as u64) as usize]
+                }
+                None => data,
+            };
+            for &byte in piece {
+                let action = digit_filter::HEX.action(byte);
+                match action {
+                    ByteAction::Emit(digit) => counters
+                        .iter_mut()
+                        .for_each(|counter| counter.process_digit(digit)),
+                    _ => counters
+                        .iter_mut()
+                        .for_each(DigitSequenceCounter::reset_window),
+                }
+                if let Some(drought_tracker) = &mut drought_tracker {
+                    drought_tracker.process_character(byte);
+                }
+                if !decimal_subset.is_empty() {
+                    match action {
+                        ByteAction::Emit(digit) if digit < 10 => decimal_subset
+                            .iter_mut()
+                            .for_each(|counter| counter.process_digit(digit)),
+                        _ => decimal_subset
+                            .iter_mut()
+                            .for_each(DigitSequenceCounter::reset_window),
+                    }
+                }
+            }
+            counted += piece.len() as u64;
+            data = &data[piece.len()..];
+            if let Some((report_every, on_snapshot)) = &mut snapshots {
+                if counted.is_multiple_of(*report_every) {
+                    on_snapshot(counted, &counters);
+                }
+            }
+        }
+        progress.record_chunk(read, started.elapsed());
+    }
+    Ok(counters)
+}
+
+/// Applies a `DigitFilter` to everything read through it: skipped bytes are dropped, digits
+/// are passed on as uppercase hex digits so the counters read them as `HEX` does, breaks
+/// are passed on unchanged, the input ends at a stop byte, and an error byte fails the read
+/// with its offset in the input.
+struct FilterReader<R> {
+    inner: R,
+    filter: DigitFilter,
+    /// Bytes read from `inner` so far.
+    offset: u64,
+    stopped: bool,
+}
+
+impl<R: Read> Read for FilterReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+        while !self.stopped {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            let mut kept = 0;
+            for i in 0..n {
+                match self.filter.action(buf[i]) {
+                    ByteAction::Emit(digit) => {
+                        buf[kept] = HEX_DIGITS[digit as usize];
+                        kept += 1;
+                    }
+                    ByteAction::Skip => {}
+                    ByteAction::Reset => {
+                        buf[kept] = buf[i];
+                        kept += 1;
+                    }
+                    ByteAction::Stop => {
+                        self.stopped = true;
+                        break;
+                    }
+                    ByteAction::Error => {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "disallowed byte {:?} at offset {}",
+                                buf[i] as char,
+                                self.offset + i as u64
+                            ),
+                        ));
+                    }
+                }
+            }
+            self.offset += n as u64;
+            if kept != 0 {
+                return Ok(kept);
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Passes everything read through it along, recording the longest run of hex digits after
+/// the first point, the same digits that both counting paths see.
+struct RunTracker<R> {
+    inner: R,
+    found_point: bool,
+    current: u64,
+    longest: u64,
+}
+
+impl<R: Read> Read for RunTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for &byte in &buf[..n] {
+            if !self.found_point {
+                self.found_point = byte == b'.';
+            } else if let ByteAction::Emit(_) = digit_filter::HEX.action(byte) {
+                self.current += 1;
+                self.longest = self.longest.max(self.current);
+            } else {
+                self.current = 0;
+            }
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,288 @@
+use std::io::{self, Write};
+
+use crate::json;
+use crate::notation::SequenceNotation;
+use crate::number_format::NumberFormat;
+use crate::DigitSequenceCounter;
+
+/// What a `Reporter` is told about a run before any of its lengths.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunMeta {
+    /// The lengths that will be reported, in order.
+    pub sequence_lengths: Vec<usize>,
+    /// Bytes read from the input, including skipped bytes and separators.
+    pub input_bytes: u64,
+    /// The input's hash with the `blake3` feature, as `blake3:<hex>`.
+    pub content_hash: Option<String>,
+}
+
+/// What a `Reporter` is told about a length before any of its sequences.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LengthSummary {
+    pub sequence_length: usize,
+    pub possible_sequences: usize,
+    pub unique_sequences: usize,
+    pub total_count: u128,
+    pub coverage: f64,
+    /// Whether the counts were estimated by a sketch, which reports no sequences and only an
+    /// estimated `unique_sequences`.
+    pub estimated: bool,
+    pub sequence_notation: SequenceNotation,
+}
+
+impl LengthSummary {
+    /// Writes the sequence at `sequence_index` in this length's notation.
+    pub fn label(&self, sequence_index: usize) -> String {
+        self.sequence_notation
+            .label(sequence_index, self.sequence_length)
+    }
+}
+
+/// One nonzero count, reported between `begin_length` and `end_length`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequenceRecord {
+    pub sequence_index: usize,
+    pub count: u128,
+}
+
+/// A sink for counting results, called in this order for a run:
+///
+/// * `begin_run` once,
+/// * for each length in ascending order, `begin_length`, then `sequence` for each nonzero
+///   count in ascending sequence index order, then `end_length`,
+/// * `end_run` once.
+///
+/// Reporters see one sequence at a time and shouldn't need to hold a whole length's counts.
+/// Lengths reported on their own, as by `DigitSequenceCounter::report_to`, skip `begin_run`
+/// and `end_run`. Every method but `sequence` does nothing by default.
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::io::{self, Cursor};
+/// use std::rc::Rc;
+///
+/// use digit_sequence_counter::pipeline::{self, CountOptions};
+/// use digit_sequence_counter::reporter::{LengthSummary, Reporter, RunMeta, SequenceRecord};
+///
+/// struct Calls(Rc<RefCell<Vec<String>>>);
+///
+/// impl Reporter for Calls {
+///     fn begin_run(&mut self, run: &RunMeta) -> io::Result<()> {
+///         self.0.borrow_mut().push(format!("begin_run {}", run.input_bytes));
+///         Ok(())
+///     }
+///     fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+///         self.0.borrow_mut().push(format!("begin_length {}", length.sequence_length));
+///         Ok(())
+///     }
+///     fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()> {
+///         self.0.borrow_mut().push(format!("{:X} {}", record.sequence_index, record.count));
+///         Ok(())
+///     }
+///     fn end_length(&mut self) -> io::Result<()> {
+///         self.0.borrow_mut().push(String::from("end_length"));
+///         Ok(())
+///     }
+///     fn end_run(&mut self) -> io::Result<()> {
+///         self.0.borrow_mut().push(String::from("end_run"));
+///         Ok(())
+///     }
+/// }
+///
+/// let calls = Rc::new(RefCell::new(Vec::new()));
+/// let mut reporters: Vec<Box<dyn Reporter>> = vec![Box::new(Calls(calls.clone()))];
+/// let options = CountOptions::new(1..=2);
+/// pipeline::count_digits_with_reporters(Cursor::new("3.1415"), &options, &mut reporters).unwrap();
+///
+/// assert_eq!(
+///     *calls.borrow(),
+///     [
+///         "begin_run 6", "begin_length 1", "1 1", "4 1", "5 1", "end_length",
+///         "begin_length 2", "15 1", "41 1", "end_length", "end_run",
+///     ]
+/// );
+/// ```
+pub trait Reporter {
+    fn begin_run(&mut self, _run: &RunMeta) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_length(&mut self, _length: &LengthSummary) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()>;
+
+    fn end_length(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_run(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl DigitSequenceCounter {
+    /// Summarizes this counter the way `Reporter::begin_length` is told about it.
+    pub fn length_summary(&self) -> LengthSummary {
+        LengthSummary {
+            sequence_length: self.sequence_length,
+            possible_sequences: self.possible_sequences(),
+            unique_sequences: self.unique_count(),
+            total_count: self.total_count(),
+            coverage: self.coverage(),
+            estimated: self.sketch_bits().is_some(),
+            sequence_notation: self.sequence_notation(),
+        }
+    }
+
+    /// Reports this counter to `reporter` as one length, without `begin_run` or `end_run`.
+    pub fn report_to(&self, reporter: &mut dyn Reporter) -> io::Result<()> {
+        reporter.begin_length(&self.length_summary())?;
+        for (sequence_index, count) in self.nonzero_counts() {
+            reporter.sequence(&SequenceRecord {
+                sequence_index,
+                count,
+            })?;
+        }
+        reporter.end_length()
+    }
+}
+
+/// Reports a whole run of `counters` to `reporter`, in the order the counters are given.
+pub fn report_run(
+    run: &RunMeta,
+    counters: &[DigitSequenceCounter],
+    reporter: &mut dyn Reporter,
+) -> io::Result<()> {
+    reporter.begin_run(run)?;
+    for counter in counters {
+        counter.report_to(reporter)?;
+    }
+    reporter.end_run()
+}
+
+/// Writes each length as the CLI's default `unique [counts]` line, the same as a counter's
+/// `Display`, with `~` before an estimated unique count.
+pub struct TextReporter<W: Write> {
+    out: W,
+    first_sequence: bool,
+}
+
+impl<W: Write> TextReporter<W> {
+    pub fn new(out: W) -> TextReporter<W> {
+        TextReporter {
+            out,
+            first_sequence: true,
+        }
+    }
+}
+
+impl<W: Write> Reporter for TextReporter<W> {
+    fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+        self.first_sequence = true;
+        if length.estimated {
+            write!(self.out, "~")?;
+        }
+        write!(self.out, "{} [", length.unique_sequences)
+    }
+
+    fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()> {
+        if !self.first_sequence {
+            write!(self.out, ", ")?;
+        }
+        self.first_sequence = false;
+        write!(self.out, "{}", record.count)
+    }
+
+    fn end_length(&mut self) -> io::Result<()> {
+        writeln!(self.out, "]")
+    }
+}
+
+/// Writes each length as a `sequence,count` header and one row per nonzero count, with
+/// sequences in the length's notation.
+pub struct CsvReporter<W: Write> {
+    out: W,
+    length: Option<LengthSummary>,
+}
+
+impl<W: Write> CsvReporter<W> {
+    pub fn new(out: W) -> CsvReporter<W> {
+        CsvReporter { out, length: None }
+    }
+}
+
+impl<W: Write> Reporter for CsvReporter<W> {
+    fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+        self.length = Some(length.clone());
+        writeln!(self.out, "sequence,count")
+    }
+
+    fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()> {
+        let label = match &self.length {
+            Some(length) => length.label(record.sequence_index),
+            None => record.sequence_index.to_string(),
+        };
+        writeln!(self.out, "{},{}", label, record.count)
+    }
+}
+
+/// Writes the input statistics and a summary of each length as one JSON object, ignoring
+/// the sequences.
+pub struct JsonReporter<'a, W: Write> {
+    out: W,
+    number_format: &'a NumberFormat,
+    lengths_written: usize,
+}
+
+impl<'a, W: Write> JsonReporter<'a, W> {
+    pub fn new(out: W, number_format: &'a NumberFormat) -> JsonReporter<'a, W> {
+        JsonReporter {
+            out,
+            number_format,
+            lengths_written: 0,
+        }
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<'_, W> {
+    fn begin_run(&mut self, run: &RunMeta) -> io::Result<()> {
+        self.lengths_written = 0;
+        write!(
+            self.out,
+            "{{\"input_size\": {}, \"input_hash\": ",
+            run.input_bytes
+        )?;
+        match &run.content_hash {
+            Some(content_hash) => json::write_string(&mut self.out, content_hash)?,
+            None => write!(self.out, "null")?,
+        }
+        write!(self.out, ", \"lengths\": [")
+    }
+
+    fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+        if self.lengths_written != 0 {
+            write!(self.out, ", ")?;
+        }
+        self.lengths_written += 1;
+        write!(
+            self.out,
+            "{{\"sequence_length\": {}, \"possible_sequences\": {}, \"unique_sequences\": {}, \"total_count\": {}, \"coverage\": ",
+            length.sequence_length,
+            length.possible_sequences,
+            length.unique_sequences,
+            length.total_count
+        )?;
+        json::write_number(&mut self.out, length.coverage, self.number_format)?;
+        write!(self.out, "}}")
+    }
+
+    fn sequence(&mut self, _record: &SequenceRecord) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_run(&mut self) -> io::Result<()> {
+        writeln!(self.out, "]}}")
+    }
+}
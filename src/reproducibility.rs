@@ -0,0 +1,196 @@
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::checksum::{ChecksumAlgorithm, ChecksumHasher};
+use crate::json;
+
+/// Wraps a reader, counting and (with the `blake3` feature) hashing every byte that flows
+/// through it, so the digest covers exactly what was read. It can also compute a checksum
+/// to verify the input against a published one.
+pub struct DigestReader<R> {
+    inner: R,
+    bytes_read: u64,
+    #[cfg(feature = "blake3")]
+    hasher: blake3::Hasher,
+    checksum: Option<ChecksumHasher>,
+}
+
+impl<R: Read> DigestReader<R> {
+    pub fn new(inner: R) -> DigestReader<R> {
+        DigestReader {
+            inner,
+            bytes_read: 0,
+            #[cfg(feature = "blake3")]
+            hasher: blake3::Hasher::new(),
+            checksum: None,
+        }
+    }
+
+    pub fn with_checksum(inner: R, algorithm: ChecksumAlgorithm) -> DigestReader<R> {
+        DigestReader {
+            checksum: Some(ChecksumHasher::new(algorithm)),
+            ..DigestReader::new(inner)
+        }
+    }
+
+    /// Returns the checksum of everything read so far as lowercase hex, if one was requested.
+    pub fn checksum(&self) -> Option<String> {
+        self.checksum.as_ref().map(ChecksumHasher::hex)
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    #[cfg(feature = "blake3")]
+    pub fn content_hash(&self) -> Option<String> {
+        Some(format!("blake3:{}", self.hasher.finalize().to_hex()))
+    }
+
+    #[cfg(not(feature = "blake3"))]
+    pub fn content_hash(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        #[cfg(feature = "blake3")]
+        self.hasher.update(&buf[..n]);
+        if let Some(checksum) = &mut self.checksum {
+            checksum.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// A point in time rendered as an ISO 8601 UTC timestamp with second precision.
+#[derive(Clone, Copy, Debug)]
+pub struct UtcTimestamp(SystemTime);
+
+impl UtcTimestamp {
+    pub fn now() -> UtcTimestamp {
+        UtcTimestamp(SystemTime::now())
+    }
+}
+
+impl Display for UtcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+        // Converts days since the epoch to a proleptic Gregorian date, with eras of 400 years
+        // counted from 0000-03-01.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            seconds_of_day / 3600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60
+        )
+    }
+}
+
+/// The git commit the tool was built from, or `None` for a build outside a git checkout.
+pub const COMMIT: Option<&str> = option_env!("DSC_GIT_COMMIT");
+
+/// Trailer appended to every report describing how it was produced.
+#[derive(Debug)]
+pub struct ReproducibilityBlock {
+    pub options: Vec<(String, String)>,
+    pub input_size: u64,
+    pub content_hash: Option<String>,
+    /// When counting started and finished, or `None` to leave the times out, so that reports
+    /// of the same input and options are byte for byte identical.
+    pub started: Option<UtcTimestamp>,
+    pub finished: Option<UtcTimestamp>,
+}
+
+impl ReproducibilityBlock {
+    /// Writes the block as the members of a JSON object, without the surrounding braces.
+    pub fn write_json_members<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "\"version\": ")?;
+        json::write_string(out, env!("CARGO_PKG_VERSION"))?;
+        write!(out, ", \"commit\": ")?;
+        match COMMIT {
+            Some(commit) => json::write_string(out, commit)?,
+            None => write!(out, "null")?,
+        }
+        write!(out, ", \"options\": {{")?;
+        for (i, (name, value)) in self.options.iter().enumerate() {
+            if i != 0 {
+                write!(out, ", ")?;
+            }
+            json::write_string(out, name)?;
+            write!(out, ": ")?;
+            json::write_string(out, value)?;
+        }
+        write!(
+            out,
+            "}}, \"input_size\": {}, \"input_hash\": ",
+            self.input_size
+        )?;
+        match &self.content_hash {
+            Some(content_hash) => json::write_string(out, content_hash)?,
+            None => write!(out, "null")?,
+        }
+        for (name, timestamp) in [("started", &self.started), ("finished", &self.finished)] {
+            write!(out, ", \"{}\": ", name)?;
+            match timestamp {
+                Some(timestamp) => json::write_string(out, &timestamp.to_string())?,
+                None => write!(out, "null")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ReproducibilityBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# version: {}", env!("CARGO_PKG_VERSION"))?;
+        if let Some(commit) = COMMIT {
+            writeln!(f, "# commit: {}", commit)?;
+        }
+        write!(f, "# options:")?;
+        for (name, value) in &self.options {
+            write!(f, " {}={}", name, value)?;
+        }
+        writeln!(f)?;
+        write!(f, "# input_size: {}", self.input_size)?;
+        if let Some(content_hash) = &self.content_hash {
+            write!(f, "\n# input_hash: {}", content_hash)?;
+        }
+        if let Some(started) = &self.started {
+            write!(f, "\n# started: {}", started)?;
+        }
+        if let Some(finished) = &self.finished {
+            write!(f, "\n# finished: {}", finished)?;
+        }
+        Ok(())
+    }
+}
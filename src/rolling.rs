@@ -0,0 +1,82 @@
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read};
+
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// Returns the entropy of the sequences counted over the last `window_bytes` bytes of
+/// `reader`, as `(byte_offset, entropy_bits)` after every tenth of a window once a whole
+/// window has been read, to show how the distribution changes through the input. A
+/// sequence belongs to the window its last digit is in, so the window at `byte_offset`
+/// counts what `process_character` would have counted for bytes
+/// `byte_offset - window_bytes..byte_offset`. Bytes are read from the first one on, as
+/// `process_character` takes them.
+///
+/// The counter both adds each byte's sequence and takes back the sequence of the byte
+/// leaving the window, which a ring of the last `window_bytes` sequences remembers.
+///
+/// ```
+/// use digit_sequence_counter::rolling::rolling_entropy;
+/// use digit_sequence_counter::DigitSequenceCounter;
+///
+/// let mut input = b"0123456789ABCDEF".repeat(20);
+/// input.extend_from_slice(&[b'7'; 320]);
+/// let entropies = rolling_entropy(&input[..], 1, 100).unwrap();
+/// assert_eq!(entropies.len(), (640 - 100) / 10 + 1);
+///
+/// for &(byte_offset, entropy) in &entropies {
+///     let start = byte_offset as usize - 100;
+///     let mut window = DigitSequenceCounter::new(1);
+///     input[..start].iter().for_each(|&byte| window.warm_up(byte));
+///     input[start..byte_offset as usize].iter().for_each(|&byte| window.process_character(byte));
+///     assert!((entropy - window.entropy_bits()).abs() < 1e-9);
+/// }
+/// assert_eq!(entropies[0].0, 100);
+/// assert!(entropies[0].1 > 3.9);
+/// assert_eq!(entropies.last(), Some(&(640, 0.0)));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `window_bytes` is 0, if it doesn't fit in memory as a `usize`, or if
+/// `sequence_length` is greater than `DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH`.
+pub fn rolling_entropy<R: Read>(
+    mut reader: R,
+    sequence_length: usize,
+    window_bytes: u64,
+) -> io::Result<Vec<(u64, f64)>> {
+    if window_bytes == 0 {
+        panic!("Cannot take the entropy of an empty window.");
+    }
+    let window_len = usize::try_from(window_bytes)
+        .unwrap_or_else(|_| panic!("Cannot hold a window of {} bytes.", window_bytes));
+    let step = (window_bytes / 10).max(1);
+
+    let mut counter = DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive);
+    // The sequence each byte in the window completed, if any, at `byte_offset % window_len`.
+    let mut window = vec![None; window_len];
+    let mut byte_offset = 0u64;
+    let mut entropies = Vec::new();
+    let mut buffer = vec![0; 65536];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(entropies),
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for &byte in &buffer[..n] {
+            let slot = &mut window[(byte_offset % window_bytes) as usize];
+            if let Some(leaving) = slot.take() {
+                counter.remove_count(leaving);
+            }
+            *slot = counter.advance_window(byte);
+            if let Some(sequence_index) = *slot {
+                counter.add_count(sequence_index, 1);
+            }
+            byte_offset += 1;
+            if byte_offset >= window_bytes && (byte_offset - window_bytes).is_multiple_of(step) {
+                entropies.push((byte_offset, counter.entropy_bits()));
+            }
+        }
+    }
+}
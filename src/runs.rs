@@ -0,0 +1,66 @@
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    /// Returns `(start_index, run_length, total_count_in_run)` for the longest block of
+    /// consecutive sequence indices that all have nonzero counts, the first one on ties, or
+    /// `None` without counts.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x01 x34567 x89A".split(|&byte| byte == b' ').for_each(|run| {
+    ///     run.iter().for_each(|&byte| counter.process_character(byte));
+    /// });
+    ///
+    /// assert_eq!(counter.sequence_longest_run(), Some((4, 4, 4)));
+    /// assert_eq!(counter.sequence_gaps(), vec![(0, 1), (2, 2), (8, 1), (11, 5)]);
+    /// ```
+    pub fn sequence_longest_run(&self) -> Option<(usize, usize, u128)> {
+        let mut longest: Option<(usize, usize, u128)> = None;
+        let mut run_start = 0;
+        let mut run_total = 0;
+        for sequence_index in 0..=self.possible_sequences() {
+            let count = if sequence_index < self.possible_sequences() {
+                self.count_at(sequence_index)
+            } else {
+                0
+            };
+            if count != 0 {
+                if run_total == 0 {
+                    run_start = sequence_index;
+                }
+                run_total += count;
+                continue;
+            }
+            let run_length = sequence_index - run_start;
+            if run_total != 0 && longest.is_none_or(|(_, longest, _)| run_length > longest) {
+                longest = Some((run_start, run_length, run_total));
+            }
+            run_total = 0;
+        }
+        longest
+    }
+
+    /// Returns `(start, length)` for every block of consecutive sequence indices that were
+    /// never seen, in index order.
+    pub fn sequence_gaps(&self) -> Vec<(usize, usize)> {
+        let mut gaps = Vec::new();
+        let mut gap_start = None;
+        for sequence_index in 0..self.possible_sequences() {
+            match (self.count_at(sequence_index), gap_start) {
+                (0, None) => gap_start = Some(sequence_index),
+                (0, Some(_)) => {}
+                (_, Some(start)) => {
+                    gaps.push((start, sequence_index - start));
+                    gap_start = None;
+                }
+                (_, None) => {}
+            }
+        }
+        if let Some(start) = gap_start {
+            gaps.push((start, self.possible_sequences() - start));
+        }
+        gaps
+    }
+}
@@ -0,0 +1,116 @@
+use rand::Rng;
+
+use crate::DigitSequenceCounter;
+
+/// Walker's alias table over a counter's sequences, built by Vose's method, which draws a
+/// sequence with probability proportional to its count in constant time.
+#[derive(Clone, Debug)]
+pub struct AliasTable {
+    sequence_indices: Vec<usize>,
+    /// The chance of keeping each column's own sequence instead of its alias.
+    keep: Vec<f64>,
+    /// The column whose sequence each column gives way to.
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    /// Draws one sequence index.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let column = rng.gen_range(0..self.keep.len());
+        if rng.gen::<f64>() < self.keep[column] {
+            self.sequence_indices[column]
+        } else {
+            self.sequence_indices[self.alias[column]]
+        }
+    }
+}
+
+impl DigitSequenceCounter {
+    /// Builds an `AliasTable` over the counted sequences in time linear in their number, or
+    /// returns `None` without counts. `reservoir_sample` builds one for each call, so keep
+    /// the table to draw more samples from the same counts.
+    pub fn alias_table(&self) -> Option<AliasTable> {
+        let nonzero_counts = self.nonzero_counts();
+        if nonzero_counts.is_empty() {
+            return None;
+        }
+        let columns = nonzero_counts.len();
+        let total = self.total_count() as f64;
+        // Each column starts with its probability scaled so the average column holds 1.
+        let mut keep = nonzero_counts
+            .iter()
+            .map(|&(_, count)| count as f64 * columns as f64 / total)
+            .collect::<Vec<_>>();
+        let mut alias = (0..columns).collect::<Vec<_>>();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..columns).partition(|&column| keep[column] < 1.0);
+        while let (Some(&under), Some(&over)) = (small.last(), large.last()) {
+            small.pop();
+            alias[under] = over;
+            keep[over] -= 1.0 - keep[under];
+            if keep[over] < 1.0 {
+                large.pop();
+                small.push(over);
+            }
+        }
+        // What is left is full up to rounding error.
+        for column in small.into_iter().chain(large) {
+            keep[column] = 1.0;
+        }
+        Some(AliasTable {
+            sequence_indices: nonzero_counts
+                .into_iter()
+                .map(|(sequence_index, _)| sequence_index)
+                .collect(),
+            keep,
+            alias,
+        })
+    }
+
+    /// Returns `n` sequence indices drawn with replacement, each with probability
+    /// proportional to its count, from an `AliasTable` built once for the call.
+    ///
+    /// The table isn't kept in the counter between calls: counts change through counting,
+    /// merging, and every transform, and a kept table would have to be thrown away by each of
+    /// them to never sample stale counts. Keep an `alias_table` instead to draw from the same
+    /// counts many times.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// // Counts 1 of 0, 2 of 1, 3 of 2, 4 of 3, and 10 of F.
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x00112223333FFFFFFFFFF".iter().for_each(|&byte| counter.process_character(byte));
+    /// let total = counter.total_count() as f64;
+    ///
+    /// let draws = 1_000_000;
+    /// let samples = counter.reservoir_sample(draws, &mut StdRng::seed_from_u64(314159));
+    /// assert_eq!(samples.len(), draws);
+    /// let mut drawn = [0usize; 16];
+    /// samples.iter().for_each(|&sequence_index| drawn[sequence_index] += 1);
+    ///
+    /// for sequence_index in 0..16 {
+    ///     let p = counter.count_at(sequence_index) as f64 / total;
+    ///     let expected = draws as f64 * p;
+    ///     let sigma = (draws as f64 * p * (1.0 - p)).sqrt();
+    ///     assert!((drawn[sequence_index] as f64 - expected).abs() <= 3.0 * sigma);
+    /// }
+    /// assert_eq!(drawn[4], 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no counts to sample from and `n` isn't 0.
+    pub fn reservoir_sample(&self, n: usize, rng: &mut impl Rng) -> Vec<usize> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let table = match self.alias_table() {
+            Some(table) => table,
+            None => panic!("Cannot sample from a DigitSequenceCounter without counts."),
+        };
+        (0..n).map(|_| table.sample(rng)).collect()
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt::{self, Display};
+use std::io::Cursor;
+
+use crate::golden;
+use crate::pipeline::{self, CountOptions, SkipBehavior};
+use crate::StorageMode;
+
+/// The longest length each case is counted for.
+const SELF_TEST_LENGTHS: std::ops::RangeInclusive<usize> = 1..=4;
+
+/// A synthetic input with a known shape, counted under every `Configuration`.
+#[derive(Clone, Debug)]
+pub struct SelfTestCase {
+    pub name: &'static str,
+    pub input: Vec<u8>,
+    pub separators: Vec<u8>,
+    pub skip: SkipBehavior,
+}
+
+/// One way of running the counting pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Configuration {
+    pub threads: usize,
+    pub storage: StorageMode,
+}
+
+impl Display for Configuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "threads={} storage={}", self.threads, self.storage)
+    }
+}
+
+/// Returns the built-in cases, generated the same way on every run: a point just past the
+/// first read buffer, invalid characters, separators, CRLF line breaks, a run long enough
+/// for many threaded chunks, a single repeated digit, and counting from the first byte.
+pub fn cases() -> Vec<SelfTestCase> {
+    let mut random = SplitMix64(0x5EED_D15C);
+    let mut random_hex = |len: usize| {
+        (0..len)
+            .map(|_| b"0123456789ABCDEFabcdef"[(random.next() % 22) as usize])
+            .collect::<Vec<u8>>()
+    };
+    let wrap = |digits: &[u8], line_break: &[u8]| {
+        let mut wrapped = b"3.".to_vec();
+        for line in digits.chunks(50) {
+            wrapped.extend_from_slice(line);
+            wrapped.extend_from_slice(line_break);
+        }
+        wrapped
+    };
+
+    let mut point_at_boundary = vec![b'3'; 65535];
+    point_at_boundary.push(b'.');
+    point_at_boundary.extend(random_hex(5000));
+
+    let mut invalid = b"3.".to_vec();
+    for (i, byte) in random_hex(20000).into_iter().enumerate() {
+        invalid.push(byte);
+        if i % 37 == 0 {
+            invalid.push(b"xG .-\0\xFF"[i % 7]);
+        }
+    }
+
+    let wrapped_digits = random_hex(10000);
+    let long_run = [b"3.".to_vec(), random_hex(300_000)].concat();
+    let identical = [b"3.".to_vec(), vec![b'7'; 100_000]].concat();
+    let from_start = [random_hex(3000), b".".to_vec(), random_hex(3000)].concat();
+
+    let case = |name, input, separators: &[u8], skip| SelfTestCase {
+        name,
+        input,
+        separators: separators.to_vec(),
+        skip,
+    };
+    vec![
+        case(
+            "point at buffer boundary",
+            point_at_boundary,
+            b"",
+            SkipBehavior::ThroughFirstPoint,
+        ),
+        case(
+            "invalid characters",
+            invalid,
+            b"",
+            SkipBehavior::ThroughFirstPoint,
+        ),
+        case(
+            "separators",
+            wrap(&wrapped_digits, b"\n"),
+            b"\n",
+            SkipBehavior::ThroughFirstPoint,
+        ),
+        case(
+            "CRLF",
+            wrap(&wrapped_digits, b"\r\n"),
+            b"\r\n",
+            SkipBehavior::ThroughFirstPoint,
+        ),
+        case("long run", long_run, b"", SkipBehavior::ThroughFirstPoint),
+        case(
+            "all identical digits",
+            identical,
+            b"",
+            SkipBehavior::ThroughFirstPoint,
+        ),
+        case("from start", from_start, b"", SkipBehavior::None),
+    ]
+}
+
+/// Returns every configuration the self test runs each case under: one and four threads,
+/// each with dense, sparse, and adaptive storage.
+pub fn configurations() -> Vec<Configuration> {
+    let mut configurations = Vec::new();
+    for &threads in &[1, 4] {
+        for &storage in &[
+            StorageMode::Dense,
+            StorageMode::Sparse,
+            StorageMode::Adaptive,
+        ] {
+            configurations.push(Configuration { threads, storage });
+        }
+    }
+    configurations
+}
+
+/// Counts `case` through `pipeline::count_digits` under `configuration` and compares every
+/// length against a naive reference count, describing the first difference found.
+pub fn run_case(case: &SelfTestCase, configuration: Configuration) -> Result<(), String> {
+    let mut options = CountOptions::new(SELF_TEST_LENGTHS);
+    options.storage = configuration.storage;
+    options.threads = configuration.threads;
+    options.separators = case.separators.clone();
+    options.skip = case.skip;
+    // Small chunks put many chunk boundaries inside each input.
+    options.chunk_size = 4093;
+
+    let report = pipeline::count_digits(Cursor::new(&case.input), &options)
+        .map_err(|err| err.to_string())?;
+    if report.input_bytes != case.input.len() as u64 {
+        return Err(format!(
+            "read {} bytes of {}",
+            report.input_bytes,
+            case.input.len()
+        ));
+    }
+    for (counter, sequence_length) in report.counters.iter().zip(SELF_TEST_LENGTHS) {
+        let expected = reference_counts(case, sequence_length);
+        if let Some(difference) = golden::describe_difference(counter, &expected) {
+            return Err(format!("length {}: {}", sequence_length, difference));
+        }
+    }
+    Ok(())
+}
+
+/// Counts `case` the slow, obvious way, sharing no code with the counters: drop the
+/// separators, skip through the first point, split what's left into runs of hex digits, and
+/// count each run's windows except its first, as the counters do.
+fn reference_counts(case: &SelfTestCase, sequence_length: usize) -> Vec<(usize, u128)> {
+    let mut text = case
+        .input
+        .iter()
+        .copied()
+        .filter(|byte| !case.separators.contains(byte))
+        .collect::<Vec<u8>>();
+    if case.skip == SkipBehavior::ThroughFirstPoint {
+        text = match text.iter().position(|&byte| byte == b'.') {
+            Some(point) => text[point + 1..].to_vec(),
+            None => Vec::new(),
+        };
+    }
+
+    let mut counts = std::collections::BTreeMap::new();
+    for run in text.split(|byte| !byte.is_ascii_hexdigit()) {
+        for start in 1..(run.len() + 1).saturating_sub(sequence_length) {
+            let window = std::str::from_utf8(&run[start..start + sequence_length]).unwrap();
+            let sequence_index = usize::from_str_radix(window, 16).unwrap();
+            *counts.entry(sequence_index).or_insert(0) += 1;
+        }
+    }
+    counts.into_iter().collect()
+}
+
+/// A small deterministic generator for the synthetic inputs.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,455 @@
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+use std::io;
+use std::str::FromStr;
+
+use crate::reporter::CsvReporter;
+use crate::{DigitSequenceCounter, StorageMode};
+
+const MAGIC: &str = "DSC";
+
+/// What was wrong with the token a `DeserializeError` points at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeserializeErrorKind {
+    MissingMagic,
+    InvalidSequenceLength,
+    MalformedEntry,
+    InvalidIndex,
+    InvalidCount,
+    DuplicateIndex,
+}
+
+/// A failure to parse the hex text format, identifying the offending token and the byte
+/// position where it starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeserializeError {
+    pub kind: DeserializeErrorKind,
+    pub token: String,
+    pub position: usize,
+}
+
+impl DeserializeError {
+    fn new(kind: DeserializeErrorKind, token: &str, position: usize) -> DeserializeError {
+        DeserializeError {
+            kind,
+            token: String::from(token),
+            position,
+        }
+    }
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problem = match self.kind {
+            DeserializeErrorKind::MissingMagic => "missing \"DSC\" header",
+            DeserializeErrorKind::InvalidSequenceLength => "invalid sequence length",
+            DeserializeErrorKind::MalformedEntry => "malformed index:count entry",
+            DeserializeErrorKind::InvalidIndex => "invalid sequence index",
+            DeserializeErrorKind::InvalidCount => "invalid count",
+            DeserializeErrorKind::DuplicateIndex => "duplicate sequence index",
+        };
+        write!(f, "{} at byte {}: {:?}", problem, self.position, self.token)
+    }
+}
+
+impl Error for DeserializeError {}
+
+/// A failure to parse the JSON object of `write_json`, with the byte position of the
+/// offending token where there is one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonParseError {
+    /// Something other than one object of hex-string keys and unsigned integer counts, with
+    /// no key given twice.
+    Malformed { position: usize },
+    /// A key with a different number of hex digits than the first key.
+    InconsistentKeyLength {
+        expected: usize,
+        found: usize,
+        position: usize,
+    },
+    /// A count greater than `u128::MAX`.
+    CountOverflow { position: usize },
+    /// An empty object, which has no key to take the sequence length from.
+    NoSequences,
+}
+
+impl Display for JsonParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonParseError::Malformed { position } => {
+                write!(f, "malformed counts JSON at byte {}", position)
+            }
+            JsonParseError::InconsistentKeyLength {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "key at byte {} has {} hex digits, but the first key has {}",
+                position, found, expected
+            ),
+            JsonParseError::CountOverflow { position } => {
+                write!(f, "count at byte {} is greater than u128::MAX", position)
+            }
+            JsonParseError::NoSequences => {
+                write!(f, "no sequences to take the sequence length from")
+            }
+        }
+    }
+}
+
+impl Error for JsonParseError {}
+
+impl DigitSequenceCounter {
+    /// Writes a `sequence,count` header and one row per nonzero count in index order.
+    pub fn write_csv<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        self.report_to(&mut CsvReporter::new(out))
+    }
+
+    /// Serializes the counter as `DSC` followed by the sequence length as two hex digits,
+    /// then `;INDEX:COUNT` for every nonzero count, with the index as at least eight and the
+    /// count as 32 uppercase hex digits.
+    pub fn serialize_to_hex(&self) -> String {
+        let mut s = format!("{}{:02X}", MAGIC, self.sequence_length);
+        for (sequence_index, count) in self.nonzero_counts() {
+            write!(s, ";{:08X}:{:032X}", sequence_index, count).unwrap();
+        }
+        s
+    }
+
+    /// Parses the format produced by `serialize_to_hex`. The result uses adaptive storage.
+    /// Zero counts are rejected, since `serialize_to_hex` never writes them.
+    pub fn deserialize_from_hex(s: &str) -> Result<DigitSequenceCounter, DeserializeError> {
+        let mut tokens = s.split(';');
+        let header = tokens.next().unwrap_or("");
+        if !header.starts_with(MAGIC) {
+            return Err(DeserializeError::new(
+                DeserializeErrorKind::MissingMagic,
+                header,
+                0,
+            ));
+        }
+
+        let length_token = &header[MAGIC.len()..];
+        let sequence_length = match usize::from_str_radix(length_token, 16) {
+            Ok(sequence_length)
+                if length_token.len() == 2
+                    && !length_token.starts_with('+')
+                    && (1..=DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH)
+                        .contains(&sequence_length) =>
+            {
+                sequence_length
+            }
+            _ => {
+                return Err(DeserializeError::new(
+                    DeserializeErrorKind::InvalidSequenceLength,
+                    length_token,
+                    MAGIC.len(),
+                ))
+            }
+        };
+
+        let mut counter =
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive);
+        let mut position = header.len() + 1;
+        for token in tokens {
+            let mut fields = token.splitn(2, ':');
+            let (index_token, count_token) = match (fields.next(), fields.next()) {
+                (Some(index_token), Some(count_token)) => (index_token, count_token),
+                _ => {
+                    return Err(DeserializeError::new(
+                        DeserializeErrorKind::MalformedEntry,
+                        token,
+                        position,
+                    ))
+                }
+            };
+
+            let sequence_index = match usize::from_str_radix(index_token, 16) {
+                Ok(sequence_index)
+                    if !index_token.starts_with('+')
+                        && sequence_index < counter.possible_sequences() =>
+                {
+                    sequence_index
+                }
+                _ => {
+                    return Err(DeserializeError::new(
+                        DeserializeErrorKind::InvalidIndex,
+                        index_token,
+                        position,
+                    ))
+                }
+            };
+            let count_position = position + index_token.len() + 1;
+            let count = match u128::from_str_radix(count_token, 16) {
+                Ok(count) if count != 0 && !count_token.starts_with('+') => count,
+                _ => {
+                    return Err(DeserializeError::new(
+                        DeserializeErrorKind::InvalidCount,
+                        count_token,
+                        count_position,
+                    ))
+                }
+            };
+            if counter.count_at(sequence_index) != 0 {
+                return Err(DeserializeError::new(
+                    DeserializeErrorKind::DuplicateIndex,
+                    index_token,
+                    position,
+                ));
+            }
+
+            counter.add_count(sequence_index, count);
+            position += token.len() + 1;
+        }
+
+        Ok(counter)
+    }
+
+    /// Writes the nonzero counts as one JSON object in index order, with each sequence as a
+    /// key of uppercase hex digits zero-padded to the sequence length, such as
+    /// `{"14": 2, "41": 1}`.
+    pub fn write_json<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{{")?;
+        for (i, (sequence_index, count)) in self.nonzero_counts().into_iter().enumerate() {
+            if i != 0 {
+                write!(out, ", ")?;
+            }
+            write!(
+                out,
+                "\"{:0width$X}\": {}",
+                sequence_index,
+                count,
+                width = self.sequence_length
+            )?;
+        }
+        write!(out, "}}")
+    }
+
+    /// Returns what `write_json` writes.
+    pub fn to_json_string(&self) -> String {
+        let mut json = Vec::new();
+        self.write_json(&mut json).unwrap();
+        String::from_utf8(json).unwrap()
+    }
+
+    /// Parses the JSON object of `write_json`, in any spacing and either case of hex digits,
+    /// taking the sequence length from the width of the keys. This is only the subset of
+    /// JSON that `write_json` produces, so string escapes and non-integer numbers are
+    /// malformed. The result uses adaptive storage. An empty counter's `{}` has no sequence
+    /// length to read back, so it is an error.
+    ///
+    /// ```
+    /// use digit_sequence_counter::serialization::JsonParseError;
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(3);
+    /// b"x3141592653589793".iter().for_each(|&byte| counter.process_character(byte));
+    /// let json = counter.to_json_string();
+    /// assert!(json.starts_with("{\"141\": 1, \"159\": 1, "));
+    /// assert_eq!(json.parse::<DigitSequenceCounter>(), Ok(counter));
+    ///
+    /// let parsed = DigitSequenceCounter::from_json_str(" { \"0a\" : 7 ,\"FF\":1}\n").unwrap();
+    /// assert_eq!((parsed.sequence_length(), parsed.count_at(0x0A)), (2, 7));
+    ///
+    /// assert_eq!(
+    ///     DigitSequenceCounter::from_json_str(r#"{"0A": 1, "123": 2}"#),
+    ///     Err(JsonParseError::InconsistentKeyLength { expected: 2, found: 3, position: 10 })
+    /// );
+    /// assert_eq!(
+    ///     DigitSequenceCounter::from_json_str(r#"{"0A": 340282366920938463463374607431768211456}"#),
+    ///     Err(JsonParseError::CountOverflow { position: 7 })
+    /// );
+    /// assert_eq!(
+    ///     DigitSequenceCounter::from_json_str(r#"{"0A": 1.5}"#),
+    ///     Err(JsonParseError::Malformed { position: 8 })
+    /// );
+    /// assert_eq!(
+    ///     DigitSequenceCounter::from_json_str(r#"{"0A": 1, "0a": 2}"#),
+    ///     Err(JsonParseError::Malformed { position: 10 })
+    /// );
+    /// assert_eq!(DigitSequenceCounter::from_json_str("{}"), Err(JsonParseError::NoSequences));
+    /// ```
+    pub fn from_json_str(s: &str) -> Result<DigitSequenceCounter, JsonParseError> {
+        let bytes = s.as_bytes();
+        let mut position = 0;
+        let skip_whitespace = |position: &mut usize| {
+            while bytes.get(*position).is_some_and(u8::is_ascii_whitespace) {
+                *position += 1;
+            }
+        };
+        let expect = |position: &mut usize, byte: u8| {
+            skip_whitespace(position);
+            if bytes.get(*position) == Some(&byte) {
+                *position += 1;
+                Ok(())
+            } else {
+                Err(JsonParseError::Malformed {
+                    position: *position,
+                })
+            }
+        };
+
+        expect(&mut position, b'{')?;
+        skip_whitespace(&mut position);
+        if bytes.get(position) == Some(&b'}') {
+            expect(&mut position, b'}')?;
+            skip_whitespace(&mut position);
+            return if position == bytes.len() {
+                Err(JsonParseError::NoSequences)
+            } else {
+                Err(JsonParseError::Malformed { position })
+            };
+        }
+
+        let mut counter: Option<DigitSequenceCounter> = None;
+        loop {
+            expect(&mut position, b'"')?;
+            let key_position = position - 1;
+            let key_length = bytes[position..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_hexdigit())
+                .count();
+            let key = &s[position..position + key_length];
+            position += key_length;
+            if bytes.get(position) != Some(&b'"')
+                || key_length == 0
+                || key_length > DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH
+            {
+                return Err(JsonParseError::Malformed { position });
+            }
+            position += 1;
+            let counter = counter.get_or_insert_with(|| {
+                DigitSequenceCounter::with_storage(key_length, StorageMode::Adaptive)
+            });
+            if key_length != counter.sequence_length {
+                return Err(JsonParseError::InconsistentKeyLength {
+                    expected: counter.sequence_length,
+                    found: key_length,
+                    position: key_position,
+                });
+            }
+            let sequence_index = usize::from_str_radix(key, 16).unwrap();
+
+            expect(&mut position, b':')?;
+            skip_whitespace(&mut position);
+            let count_position = position;
+            let digits = bytes[position..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+            if digits == 0 {
+                return Err(JsonParseError::Malformed { position });
+            }
+            let count = s[position..position + digits]
+                .parse::<u128>()
+                .map_err(|_| JsonParseError::CountOverflow {
+                    position: count_position,
+                })?;
+            position += digits;
+            if counter.count_at(sequence_index) != 0 {
+                return Err(JsonParseError::Malformed {
+                    position: key_position,
+                });
+            }
+            counter.add_count(sequence_index, count);
+
+            skip_whitespace(&mut position);
+            match bytes.get(position) {
+                Some(b',') => position += 1,
+                Some(b'}') => {
+                    position += 1;
+                    break;
+                }
+                _ => return Err(JsonParseError::Malformed { position }),
+            }
+        }
+        skip_whitespace(&mut position);
+        match (counter, position == bytes.len()) {
+            (Some(counter), true) => Ok(counter),
+            _ => Err(JsonParseError::Malformed { position }),
+        }
+    }
+}
+
+impl FromStr for DigitSequenceCounter {
+    type Err = JsonParseError;
+
+    /// Parses the JSON object of `write_json` with `from_json_str`.
+    fn from_str(s: &str) -> Result<DigitSequenceCounter, JsonParseError> {
+        DigitSequenceCounter::from_json_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind_of(s: &str) -> DeserializeErrorKind {
+        DigitSequenceCounter::deserialize_from_hex(s)
+            .unwrap_err()
+            .kind
+    }
+
+    #[test]
+    fn hex_round_trips_every_length() {
+        for sequence_length in 1..=DigitSequenceCounter::LARGEST_SEQUENCE_LENGTH {
+            let mut counter =
+                DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse);
+            b"x3243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89452821E638D0"
+                .iter()
+                .for_each(|&byte| counter.process_character(byte));
+            counter.add_count(counter.possible_sequences() - 1, 1 << 100);
+            let serialized = counter.serialize_to_hex();
+            assert_eq!(
+                DigitSequenceCounter::deserialize_from_hex(&serialized).unwrap(),
+                counter,
+                "{}",
+                serialized
+            );
+        }
+    }
+
+    #[test]
+    fn signed_sequence_length_is_rejected() {
+        assert_eq!(
+            kind_of("DSC+5"),
+            DeserializeErrorKind::InvalidSequenceLength
+        );
+        assert_eq!(
+            kind_of("DSC-5"),
+            DeserializeErrorKind::InvalidSequenceLength
+        );
+        assert_eq!(
+            kind_of("DSC00"),
+            DeserializeErrorKind::InvalidSequenceLength
+        );
+        assert_eq!(kind_of("DSC5"), DeserializeErrorKind::InvalidSequenceLength);
+        assert_eq!(
+            kind_of("DSC10"),
+            DeserializeErrorKind::InvalidSequenceLength
+        );
+    }
+
+    #[test]
+    fn zero_count_is_rejected() {
+        let error =
+            DigitSequenceCounter::deserialize_from_hex("DSC02;00000014:0;00000014:2").unwrap_err();
+        assert_eq!(
+            error,
+            DeserializeError::new(DeserializeErrorKind::InvalidCount, "0", 15)
+        );
+    }
+
+    #[test]
+    fn signed_entries_are_rejected() {
+        assert_eq!(kind_of("DSC02;+14:2"), DeserializeErrorKind::InvalidIndex);
+        assert_eq!(kind_of("DSC02;14:+2"), DeserializeErrorKind::InvalidCount);
+        assert_eq!(kind_of("DSC02;100:2"), DeserializeErrorKind::InvalidIndex);
+        assert_eq!(
+            kind_of("DSC02;14:2;14:1"),
+            DeserializeErrorKind::DuplicateIndex
+        );
+        assert_eq!(kind_of("DSC02;14"), DeserializeErrorKind::MalformedEntry);
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+use std::arch::x86_64::{
+    __m128i, _mm_and_si128, _mm_andnot_si128, _mm_cmpgt_epi8, _mm_cmplt_epi8, _mm_loadu_si128,
+    _mm_movemask_epi8, _mm_or_si128, _mm_set1_epi8, _mm_storeu_si128, _mm_sub_epi8,
+};
+
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    /// Counts `bytes` exactly as `process_bytes` does, classifying 16 bytes at a time as hex
+    /// digits with SSE2 and finding their values in the same instructions. The window is
+    /// still advanced a digit at a time, the way `process_character` does. A chunk with no
+    /// hex digits only resets the window, and one that is all hex digits skips the check
+    /// for a reset at each byte.
+    ///
+    /// `std::simd` still needs a nightly compiler, so this uses the x86-64 intrinsics, which
+    /// every x86-64 processor has. Other targets use `process_bytes`.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut state = 314159u32;
+    /// let bytes = (0..100_000)
+    ///     .map(|i| {
+    ///         state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    ///         let byte = (state >> 16) as u8;
+    ///         // Long digit runs along with every byte value.
+    ///         if i % 3000 < 2000 { b"0123456789abcdefABCDEF"[byte as usize % 22] } else { byte }
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// for sequence_length in 1..=4 {
+    ///     let mut scalar = DigitSequenceCounter::new(sequence_length);
+    ///     let mut simd = DigitSequenceCounter::new(sequence_length);
+    ///     // Uneven splits carry the window across calls and leave a partial last chunk.
+    ///     for chunk in bytes.chunks(4099) {
+    ///         scalar.process_bytes(chunk);
+    ///         simd.process_bytes_simd(chunk);
+    ///     }
+    ///     assert_eq!(simd, scalar);
+    ///     assert_eq!(simd.total_count(), scalar.total_count());
+    /// }
+    /// ```
+    pub fn process_bytes_simd(&mut self, bytes: &[u8]) {
+        #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+        {
+            let mut chunks = bytes.chunks_exact(16);
+            for chunk in &mut chunks {
+                let mut digits = [0u8; 16];
+                // SAFETY: SSE2 is enabled for this target, and both pointers are to 16 bytes
+                // that unaligned loads and stores may use.
+                let hex_mask = unsafe {
+                    let chunk = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                    let (hex_mask, values) = classify_hex(chunk);
+                    _mm_storeu_si128(digits.as_mut_ptr() as *mut __m128i, values);
+                    hex_mask
+                };
+                match hex_mask {
+                    0 => self.reset_window(),
+                    0xFFFF => {
+                        for &digit in &digits {
+                            if self.shift_in_digit(digit) {
+                                self.count_shifted_sequence();
+                            }
+                        }
+                    }
+                    _ => {
+                        for (i, &digit) in digits.iter().enumerate() {
+                            if hex_mask >> i & 1 == 0 {
+                                self.reset_window();
+                            } else if self.shift_in_digit(digit) {
+                                self.count_shifted_sequence();
+                            }
+                        }
+                    }
+                }
+            }
+            self.process_bytes(chunks.remainder());
+        }
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+        self.process_bytes(bytes);
+    }
+}
+
+/// Returns a bit mask with bit `i` set if byte `i` of `chunk` is a hex digit in either case,
+/// and the value of each hex digit, which is meaningless for the other bytes.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[inline]
+unsafe fn classify_hex(chunk: __m128i) -> (u32, __m128i) {
+    // The comparisons are of signed bytes, which puts every byte from 0x80 up below b'0'.
+    let in_range = |bytes: __m128i, low: u8, high: u8| {
+        _mm_and_si128(
+            _mm_cmpgt_epi8(bytes, _mm_set1_epi8(low as i8 - 1)),
+            _mm_cmplt_epi8(bytes, _mm_set1_epi8(high as i8 + 1)),
+        )
+    };
+    let is_decimal = in_range(chunk, b'0', b'9');
+    // Setting 0x20 lowercases letters and leaves b'a'..=b'f' only for hex letters.
+    let lowercase = _mm_or_si128(chunk, _mm_set1_epi8(0x20));
+    let is_letter = in_range(lowercase, b'a', b'f');
+
+    let decimal_values = _mm_sub_epi8(chunk, _mm_set1_epi8(b'0' as i8));
+    let letter_values = _mm_sub_epi8(lowercase, _mm_set1_epi8((b'a' - 10) as i8));
+    let values = _mm_or_si128(
+        _mm_and_si128(is_decimal, decimal_values),
+        _mm_andnot_si128(is_decimal, letter_values),
+    );
+    let hex_mask = _mm_movemask_epi8(_mm_or_si128(is_decimal, is_letter)) as u32;
+    (hex_mask, values)
+}
@@ -0,0 +1,203 @@
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    fn counts_as_f64(&self) -> Vec<f64> {
+        (0..self.possible_sequences())
+            .map(|sequence_index| self.count_at(sequence_index) as f64)
+            .collect()
+    }
+
+    /// Returns every count averaged with its neighbors over a centered window of `window`
+    /// sequence indices, one value per possible sequence. Past either end, the edge count is
+    /// repeated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is even or greater than `possible_sequences()`.
+    pub fn moving_average_counts(&self, window: usize) -> Vec<f64> {
+        if window.is_multiple_of(2) || window > self.possible_sequences() {
+            panic!(
+                "Cannot take a moving average over a window of {} with {} possible sequences; it must be odd and no larger.",
+                window,
+                self.possible_sequences()
+            );
+        }
+        let counts = self.counts_as_f64();
+        let last = counts.len() - 1;
+        let radius = window / 2;
+        let padded = |position: isize| counts[position.clamp(0, last as isize) as usize];
+
+        let mut sum = (-(radius as isize)..=radius as isize)
+            .map(padded)
+            .sum::<f64>();
+        let mut averages = Vec::with_capacity(counts.len());
+        for sequence_index in 0..counts.len() as isize {
+            averages.push(sum / window as f64);
+            sum += padded(sequence_index + radius as isize + 1)
+                - padded(sequence_index - radius as isize);
+        }
+        averages
+    }
+
+    /// Returns the counts convolved with a normalized Gaussian kernel of standard deviation
+    /// `sigma` sequence indices, truncated at three standard deviations, one value per
+    /// possible sequence. Past either end, the edge count is repeated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` is not positive and finite.
+    pub fn gaussian_smooth_counts(&self, sigma: f64) -> Vec<f64> {
+        if !(sigma > 0.0 && sigma.is_finite()) {
+            panic!(
+                "Cannot smooth with a Gaussian of standard deviation {}; it must be positive and finite.",
+                sigma
+            );
+        }
+        let counts = self.counts_as_f64();
+        let last = counts.len() - 1;
+        let radius = ((3.0 * sigma).ceil() as usize).min(counts.len());
+        let mut kernel = (0..=2 * radius)
+            .map(|offset| {
+                let x = offset as f64 - radius as f64;
+                (-x * x / (2.0 * sigma * sigma)).exp()
+            })
+            .collect::<Vec<_>>();
+        let kernel_sum = kernel.iter().sum::<f64>();
+        kernel.iter_mut().for_each(|weight| *weight /= kernel_sum);
+
+        (0..counts.len())
+            .map(|sequence_index| {
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, weight)| {
+                        let position = sequence_index as isize + offset as isize - radius as isize;
+                        weight * counts[position.clamp(0, last as isize) as usize]
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DigitSequenceCounter, StorageMode};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    /// A length-1 counter whose count at each index is the index.
+    fn ramp() -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new(1);
+        counter.apply_transform(|sequence_index, _| sequence_index as u128);
+        counter
+    }
+
+    #[test]
+    fn constant_counts_stay_constant() {
+        let mut counter = DigitSequenceCounter::new(2);
+        counter.apply_transform(|_, _| 7);
+        for window in [1, 3, 15, 255] {
+            assert!(counter
+                .moving_average_counts(window)
+                .iter()
+                .all(|&average| close(average, 7.0)));
+        }
+        for sigma in [0.5, 2.0, 40.0, 1000.0] {
+            assert!(counter
+                .gaussian_smooth_counts(sigma)
+                .iter()
+                .all(|&smoothed| close(smoothed, 7.0)));
+        }
+    }
+
+    #[test]
+    fn moving_average_repeats_the_edge_counts() {
+        let averages = ramp().moving_average_counts(3);
+        assert!(close(averages[0], (0.0 + 0.0 + 1.0) / 3.0));
+        assert!(close(averages[15], (14.0 + 15.0 + 15.0) / 3.0));
+        assert!((1..15).all(|i| close(averages[i], i as f64)));
+
+        let averages = ramp().moving_average_counts(7);
+        assert!(close(
+            averages[1],
+            (0.0 + 0.0 + 0.0 + 1.0 + 2.0 + 3.0 + 4.0) / 7.0
+        ));
+        assert!(close(
+            averages[14],
+            (11.0 + 12.0 + 13.0 + 14.0 + 15.0 * 3.0) / 7.0
+        ));
+    }
+
+    #[test]
+    fn moving_average_matches_direct_sums() {
+        let mut counter = DigitSequenceCounter::with_storage(2, StorageMode::Sparse);
+        counter.process_bytes(b"x31415926535897932384626433832795028841971693993751");
+        let counts = (0..256)
+            .map(|i| counter.count_at(i) as f64)
+            .collect::<Vec<_>>();
+        for window in [1, 5, 31] {
+            let radius = window as isize / 2;
+            let averages = counter.moving_average_counts(window);
+            for (i, &average) in averages.iter().enumerate() {
+                let direct = (i as isize - radius..=i as isize + radius)
+                    .map(|position| counts[position.clamp(0, 255) as usize])
+                    .sum::<f64>()
+                    / window as f64;
+                assert!(close(average, direct), "window {} at {}", window, i);
+            }
+        }
+    }
+
+    #[test]
+    fn gaussian_keeps_the_total_away_from_the_edges() {
+        let mut counter = DigitSequenceCounter::new(2);
+        counter.apply_transform(|sequence_index, _| match sequence_index {
+            0x40..=0xC0 => (sequence_index % 7) as u128,
+            _ => 0,
+        });
+        for sigma in [0.5, 3.0, 10.0] {
+            let smoothed = counter.gaussian_smooth_counts(sigma);
+            assert!(close(
+                smoothed.iter().sum::<f64>(),
+                counter.total_count() as f64
+            ));
+            assert!(smoothed[0] == 0.0 && smoothed[255] == 0.0);
+        }
+        let smoothed = ramp().gaussian_smooth_counts(1.0);
+        assert!((1..15).all(|i| smoothed[i] > smoothed[i - 1]));
+        assert!(close(smoothed[7], 7.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "it must be odd and no larger")]
+    fn even_window_panics() {
+        ramp().moving_average_counts(4);
+    }
+
+    #[test]
+    #[should_panic(expected = "it must be odd and no larger")]
+    fn oversized_window_panics() {
+        ramp().moving_average_counts(17);
+    }
+
+    #[test]
+    #[should_panic(expected = "it must be positive and finite")]
+    fn zero_sigma_panics() {
+        ramp().gaussian_smooth_counts(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "it must be positive and finite")]
+    fn nan_sigma_panics() {
+        ramp().gaussian_smooth_counts(f64::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected = "it must be positive and finite")]
+    fn infinite_sigma_panics() {
+        ramp().gaussian_smooth_counts(f64::INFINITY);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::{self, Display};
+use std::io::{self, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SNIFF_BYTES: usize = 1 << 20;
+
+/// How the bytes at the start of an input break down by class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteProfile {
+    pub total: usize,
+    pub digits: usize,
+    pub hex_letters: usize,
+    pub whitespace: usize,
+    pub other_printable: usize,
+    pub non_printable: usize,
+    /// Letters outside `A-F`/`a-f` plus `+`, `/`, and `=`, which base64 uses but digit files
+    /// don't. These are also counted in `other_printable`.
+    base64_only: usize,
+    /// Digits `2` through `9`, which rule out binary. These are also counted in `digits`.
+    above_one: usize,
+    starts_like_json: bool,
+}
+
+impl ByteProfile {
+    pub fn from_bytes(bytes: &[u8]) -> ByteProfile {
+        let mut profile = ByteProfile {
+            total: bytes.len(),
+            starts_like_json: matches!(
+                bytes.iter().find(|byte| !byte.is_ascii_whitespace()),
+                Some(b'{') | Some(b'[')
+            ),
+            ..ByteProfile::default()
+        };
+        for &byte in bytes {
+            match byte {
+                b'0' | b'1' => profile.digits += 1,
+                b'2'..=b'9' => {
+                    profile.digits += 1;
+                    profile.above_one += 1;
+                }
+                b'A'..=b'F' | b'a'..=b'f' => profile.hex_letters += 1,
+                b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' => profile.whitespace += 1,
+                b'G'..=b'Z' | b'g'..=b'z' | b'+' | b'/' | b'=' => {
+                    profile.other_printable += 1;
+                    profile.base64_only += 1;
+                }
+                b'!'..=b'~' => profile.other_printable += 1,
+                _ => profile.non_printable += 1,
+            }
+        }
+        profile
+    }
+
+    fn fraction(&self, count: usize) -> f64 {
+        count as f64 / self.total as f64
+    }
+
+    /// Returns the smallest of radix 2, 10, and 16 that has every digit seen, or 16 when no
+    /// digits were seen.
+    pub fn radix(&self) -> u32 {
+        if self.hex_letters > 0 || self.digits == 0 {
+            16
+        } else if self.above_one > 0 {
+            10
+        } else {
+            2
+        }
+    }
+
+    /// Returns a guess at what the input is when it doesn't look like a digit file, or `None`
+    /// when it does or there is nothing to judge.
+    pub fn diagnosis(&self) -> Option<&'static str> {
+        if self.total == 0 {
+            return None;
+        }
+        if self.fraction(self.non_printable) > 0.05 {
+            Some("looks like binary data")
+        } else if self.fraction(self.digits + self.hex_letters + self.whitespace) >= 0.9 {
+            None
+        } else if self.starts_like_json {
+            Some("looks like JSON")
+        } else if self.fraction(self.digits + self.hex_letters + self.base64_only) >= 0.95
+            && self.base64_only > 0
+        {
+            Some("looks like base64")
+        } else {
+            Some("looks like text rather than digits")
+        }
+    }
+}
+
+impl Display for ByteProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "digits {:.1}%, hex letters {:.1}%, whitespace {:.1}%, other printable {:.1}%, non-printable {:.1}%",
+            100.0 * self.fraction(self.digits),
+            100.0 * self.fraction(self.hex_letters),
+            100.0 * self.fraction(self.whitespace),
+            100.0 * self.fraction(self.other_printable),
+            100.0 * self.fraction(self.non_printable)
+        )
+    }
+}
+
+/// Reads up to `limit` bytes from the start of `reader` so they can be profiled and then
+/// replayed ahead of the rest of the input, which works for pipes as well as files.
+pub fn read_prefix<R: Read>(reader: &mut R, limit: usize) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(limit.min(DEFAULT_SNIFF_BYTES));
+    reader.take(limit as u64).read_to_end(&mut prefix)?;
+    Ok(prefix)
+}
+
+/// Like `read_prefix`, but stops early once reading has taken `interval`, so a pipe fed
+/// slower than `limit` bytes per `interval` doesn't hold back the bytes that have arrived
+/// until a whole prefix has. A read that is waiting on an idle writer still finishes first.
+pub fn read_prefix_within<R: Read>(
+    reader: &mut R,
+    limit: usize,
+    interval: Duration,
+) -> io::Result<Vec<u8>> {
+    let started = Instant::now();
+    let mut prefix = vec![0; limit.min(DEFAULT_SNIFF_BYTES)];
+    let mut filled = 0;
+    while filled < limit && started.elapsed() < interval {
+        if filled == prefix.len() {
+            prefix.resize((filled * 2).min(limit), 0);
+        }
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    prefix.truncate(filled);
+    Ok(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/pi_2000.txt");
+
+    /// The start of a PNG file: its signature and the header of its first chunk.
+    const PNG_HEADER: &[u8] =
+        b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0\0\0\x01\0\x08\x06\0\0\0\x5c\x72\xa8\x66";
+
+    #[test]
+    fn digit_file_is_not_diagnosed() {
+        let profile = ByteProfile::from_bytes(PI_FIXTURE);
+        assert_eq!(profile.diagnosis(), None);
+        assert_eq!(profile.radix(), 10);
+        assert_eq!(profile.digits, 2001);
+        assert_eq!(profile.whitespace, 1);
+        assert_eq!(profile.other_printable, 1);
+    }
+
+    #[test]
+    fn binary_file_is_diagnosed() {
+        let profile = ByteProfile::from_bytes(PNG_HEADER);
+        assert_eq!(profile.diagnosis(), Some("looks like binary data"));
+    }
+
+    #[test]
+    fn other_text_is_diagnosed() {
+        let diagnosis = |bytes: &[u8]| ByteProfile::from_bytes(bytes).diagnosis();
+        assert_eq!(
+            diagnosis(b"{\"digits\": \"3.14159\", \"source\": \"pi\"}"),
+            Some("looks like JSON")
+        );
+        assert_eq!(
+            diagnosis(b"MS4xNDE1OTI2NTM1ODk3OTMyMzg0NjI2NDMzODMyNzk1MDI4ODQxOTcx"),
+            Some("looks like base64")
+        );
+        assert_eq!(
+            diagnosis(b"The digits of pi follow on the next line."),
+            Some("looks like text rather than digits")
+        );
+        assert_eq!(diagnosis(b""), None);
+    }
+
+    #[test]
+    fn radix_is_the_smallest_with_every_digit() {
+        assert_eq!(ByteProfile::from_bytes(b"0110 1001").radix(), 2);
+        assert_eq!(ByteProfile::from_bytes(b"0110 1201").radix(), 10);
+        assert_eq!(ByteProfile::from_bytes(b"0110 10f1").radix(), 16);
+        assert_eq!(ByteProfile::from_bytes(b"    ").radix(), 16);
+    }
+
+    #[test]
+    fn prefix_stops_at_the_limit() {
+        let mut reader = PI_FIXTURE;
+        assert_eq!(read_prefix(&mut reader, 100).unwrap(), &PI_FIXTURE[..100]);
+        assert_eq!(reader, &PI_FIXTURE[100..]);
+        let mut reader = PI_FIXTURE;
+        let prefix = read_prefix_within(&mut reader, 5000, Duration::from_secs(60)).unwrap();
+        assert_eq!(prefix, PI_FIXTURE);
+    }
+}
@@ -0,0 +1,195 @@
+use std::convert::TryFrom;
+
+use std::io;
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Error, Result, Transaction};
+
+use crate::notation::SequenceNotation;
+use crate::reporter::{LengthSummary, Reporter, SequenceRecord};
+use crate::{DigitSequenceCounter, StorageMode};
+
+/// Quotes `table_name` as an SQL identifier so any name can be used safely.
+fn quote_identifier(table_name: &str) -> String {
+    format!("\"{}\"", table_name.replace('"', "\"\""))
+}
+
+/// Writes each length reported to it to a table, the same way `write_to_sqlite` does, in
+/// one transaction per length.
+pub struct SqliteReporter<'c> {
+    conn: &'c Connection,
+    table: String,
+    /// The open transaction and the length being written.
+    transaction: Option<(Transaction<'c>, usize)>,
+}
+
+impl<'c> SqliteReporter<'c> {
+    pub fn new(conn: &'c Connection, table_name: &str) -> SqliteReporter<'c> {
+        SqliteReporter {
+            conn,
+            table: quote_identifier(table_name),
+            transaction: None,
+        }
+    }
+
+    fn begin(&mut self, sequence_length: usize) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        transaction.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (sequence_length INTEGER NOT NULL, sequence TEXT NOT NULL, count INTEGER NOT NULL, PRIMARY KEY (sequence_length, sequence))",
+                self.table
+            ),
+            [],
+        )?;
+        transaction.execute(
+            &format!("DELETE FROM {} WHERE sequence_length = ?1", self.table),
+            params![sequence_length as i64],
+        )?;
+        self.transaction = Some((transaction, sequence_length));
+        Ok(())
+    }
+
+    fn insert(&mut self, sequence_index: usize, count: u128) -> Result<()> {
+        let (transaction, sequence_length) = match &self.transaction {
+            Some((transaction, sequence_length)) => (transaction, *sequence_length),
+            None => return Err(Error::InvalidQuery),
+        };
+        let count =
+            i64::try_from(count).map_err(|err| Error::ToSqlConversionFailure(Box::new(err)))?;
+        transaction
+            .prepare_cached(&format!(
+                "INSERT OR REPLACE INTO {} (sequence_length, sequence, count) VALUES (?1, ?2, ?3)",
+                self.table
+            ))?
+            .execute(params![
+                sequence_length as i64,
+                SequenceNotation::Hex.label(sequence_index, sequence_length),
+                count
+            ])?;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        match self.transaction.take() {
+            Some((transaction, _)) => transaction.commit(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Reporter for SqliteReporter<'_> {
+    fn begin_length(&mut self, length: &LengthSummary) -> io::Result<()> {
+        self.begin(length.sequence_length).map_err(io::Error::other)
+    }
+
+    fn sequence(&mut self, record: &SequenceRecord) -> io::Result<()> {
+        self.insert(record.sequence_index, record.count)
+            .map_err(io::Error::other)
+    }
+
+    fn end_length(&mut self) -> io::Result<()> {
+        self.commit().map_err(io::Error::other)
+    }
+}
+
+impl DigitSequenceCounter {
+    /// Writes the nonzero counts to `table_name` as `(sequence_length, sequence, count)` rows,
+    /// with sequences as uppercase hex labels whatever the counter's sequence notation, so
+    /// `load_from_sqlite` can read them back. The table is created if needed, and any rows
+    /// it already holds for this sequence length are replaced in the same transaction, so it
+    /// ends up matching the counter exactly.
+    ///
+    /// Counts above `i64::MAX` don't fit in an SQLite integer and fail the whole write.
+    pub fn write_to_sqlite(&self, conn: &Connection, table_name: &str) -> Result<()> {
+        let mut reporter = SqliteReporter::new(conn, table_name);
+        reporter.begin(self.sequence_length)?;
+        for (sequence_index, count) in self.nonzero_counts() {
+            reporter.insert(sequence_index, count)?;
+        }
+        reporter.commit()
+    }
+
+    /// Reads the rows for `sequence_length` from a table written by `write_to_sqlite` into an
+    /// adaptive counter. Sequences may be in either case, and repeated sequences are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is greater than `LARGEST_SEQUENCE_LENGTH`.
+    pub fn load_from_sqlite(
+        conn: &Connection,
+        table_name: &str,
+        sequence_length: usize,
+    ) -> Result<DigitSequenceCounter> {
+        let mut counter =
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Adaptive);
+        let mut select = conn.prepare(&format!(
+            "SELECT sequence, count FROM {} WHERE sequence_length = ?1",
+            quote_identifier(table_name)
+        ))?;
+        let mut rows = select.query(params![sequence_length as i64])?;
+        while let Some(row) = rows.next()? {
+            let sequence = row.get::<_, String>(0)?;
+            let sequence_index = counter
+                .sequence_string_to_index(&sequence)
+                .map_err(|err| Error::FromSqlConversionFailure(0, Type::Text, Box::new(err)))?;
+            let count = u128::try_from(row.get::<_, i64>(1)?)
+                .map_err(|err| Error::FromSqlConversionFailure(1, Type::Integer, Box::new(err)))?;
+            counter.add_count(sequence_index, count);
+        }
+        Ok(counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter_of(sequence_length: usize, input: &str) -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new(sequence_length);
+        input
+            .bytes()
+            .for_each(|byte| counter.process_character(byte));
+        counter
+    }
+
+    #[test]
+    fn round_trip_keeps_every_length() {
+        let conn = Connection::open_in_memory().unwrap();
+        let counters = [
+            counter_of(1, "3.14159265358979"),
+            counter_of(2, "3.14159265358979"),
+            counter_of(3, "CAFE x BABE"),
+        ];
+        for counter in &counters {
+            counter.write_to_sqlite(&conn, "counts").unwrap();
+        }
+        for counter in &counters {
+            let loaded =
+                DigitSequenceCounter::load_from_sqlite(&conn, "counts", counter.sequence_length())
+                    .unwrap();
+            assert_eq!(&loaded, counter);
+        }
+    }
+
+    #[test]
+    fn rewriting_a_length_replaces_its_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        counter_of(2, "0123456789")
+            .write_to_sqlite(&conn, "odd name")
+            .unwrap();
+        let counter = counter_of(2, "FEDCBA");
+        counter.write_to_sqlite(&conn, "odd name").unwrap();
+        let loaded = DigitSequenceCounter::load_from_sqlite(&conn, "odd name", 2).unwrap();
+        assert_eq!(loaded, counter);
+    }
+
+    #[test]
+    fn missing_length_loads_empty() {
+        let conn = Connection::open_in_memory().unwrap();
+        counter_of(1, "31415")
+            .write_to_sqlite(&conn, "counts")
+            .unwrap();
+        let loaded = DigitSequenceCounter::load_from_sqlite(&conn, "counts", 4).unwrap();
+        assert_eq!(loaded, DigitSequenceCounter::new(4));
+    }
+}
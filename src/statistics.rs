@@ -0,0 +1,1402 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+use crate::number_format::NumberFormat;
+use crate::storage::Counts;
+use crate::DigitSequenceCounter;
+
+/// The number of possible sequences never seen, computed as `16^sequence_length` minus the
+/// number of sequences seen. If `16^sequence_length` doesn't fit in a `u128`, only a lower
+/// bound is known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZeroBucket {
+    Exact(u128),
+    AtLeast(u128),
+}
+
+impl Display for ZeroBucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZeroBucket::Exact(sequences) => write!(f, "{}", sequences),
+            ZeroBucket::AtLeast(sequences) => write!(
+                f,
+                ">= {} (lower bound: the number of possible sequences overflows u128)",
+                sequences
+            ),
+        }
+    }
+}
+
+/// How much the digits of a sequence tell about the digits that follow, from a counter of
+/// one block length and a counter of twice that length over the same input. `X` is the
+/// first block of a joint sequence and `Y` the second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SecondOrderStats {
+    /// `I(X; Y) = H(X) + H(Y) - H(X, Y)`, in bits.
+    pub mutual_information: f64,
+    /// `H(Y | X) = H(X, Y) - H(X)`, in bits.
+    pub conditional_entropy: f64,
+    /// `H(X, Y)`, the entropy of the joint counter, in bits.
+    pub joint_entropy: f64,
+}
+
+/// The result of `test_independence`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndependenceTestResult {
+    /// The G statistic, twice the mutual information of prefix and suffix in nats times the
+    /// total count.
+    pub g_statistic: f64,
+    pub degrees_of_freedom: usize,
+    /// The chance of a G statistic at least this large if prefix and suffix were
+    /// independent.
+    pub p_value: f64,
+}
+
+/// The result of `two_sample_chi_square_test`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TwoSampleResult {
+    /// Pearson's chi-square statistic of the 2-row contingency table of both counters'
+    /// counts.
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+    /// The chance of a statistic at least this large if both inputs had the same
+    /// distribution.
+    pub p_value: f64,
+    /// Cohen's w, `sqrt(chi_square / n)` for the `n` windows of both counters, which measures
+    /// how different the distributions are whatever the amount of input.
+    pub cohens_w: f64,
+}
+
+impl DigitSequenceCounter {
+    /// Returns the count each sequence of `sequence_length` digits would have if `total`
+    /// windows were spread evenly over them.
+    #[inline]
+    pub fn expected_uniform_count(sequence_length: usize, total: u128) -> f64 {
+        total as f64 / 16.0_f64.powi(sequence_length as i32)
+    }
+
+    /// Returns `expected_uniform_count` for every possible sequence of `sequence_length`
+    /// digits, in index order, to compare observed counts against element by element.
+    /// The statistics here work the expected count out directly rather than allocate this.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// assert_eq!(DigitSequenceCounter::expected_uniform_counts(1, 32), vec![2.0; 16]);
+    /// assert_eq!(DigitSequenceCounter::expected_uniform_counts(2, 64).len(), 256);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is greater than `LARGEST_SEQUENCE_LENGTH`.
+    #[inline]
+    pub fn expected_uniform_counts(sequence_length: usize, total: u128) -> Vec<f64> {
+        if sequence_length > Self::LARGEST_SEQUENCE_LENGTH {
+            panic!(
+                "Cannot give expected counts for a sequence length greater than {}.",
+                Self::LARGEST_SEQUENCE_LENGTH
+            );
+        }
+        vec![
+            DigitSequenceCounter::expected_uniform_count(sequence_length, total);
+            1 << (sequence_length << 2)
+        ]
+    }
+
+    pub fn zero_bucket(&self) -> ZeroBucket {
+        let seen = self.unique_count() as u128;
+        match (self.radix as u128).checked_pow(self.sequence_length as u32) {
+            Some(reachable_sequences) => {
+                ZeroBucket::Exact(reachable_sequences.saturating_sub(seen))
+            }
+            None => ZeroBucket::AtLeast(u128::MAX - seen),
+        }
+    }
+
+    /// Returns the count each reachable sequence would have if `total` windows were spread
+    /// evenly over them.
+    fn expected_count(&self, total: u128) -> f64 {
+        if self.radix == 16 {
+            DigitSequenceCounter::expected_uniform_count(self.sequence_length, total)
+        } else {
+            total as f64 / self.reachable_sequences() as f64
+        }
+    }
+
+    /// Returns the fraction of reachable sequences that were seen at least once.
+    pub fn coverage(&self) -> f64 {
+        self.unique_count() as f64 / self.reachable_sequences() as f64
+    }
+
+    /// Returns the number of reachable sequences that were never seen.
+    pub fn missing_count(&self) -> usize {
+        self.reachable_sequences()
+            .saturating_sub(self.unique_count())
+    }
+
+    /// Returns the sequence indices, in ascending order, that this counter has seen and
+    /// `baseline` never did, such as the sequences a new batch of a stream discovered.
+    ///
+    /// ```
+    /// use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+    ///
+    /// let mut baseline = DigitSequenceCounter::new(2);
+    /// b"x314159".iter().for_each(|&byte| baseline.process_character(byte));
+    /// let mut batch = DigitSequenceCounter::with_storage(2, StorageMode::Sparse);
+    /// b"x3141597".iter().for_each(|&byte| batch.process_character(byte));
+    ///
+    /// // The batch adds 97, and seeing 14 and 41 again adds nothing.
+    /// batch.process_character(b'x');
+    /// b"3141".iter().for_each(|&byte| batch.process_character(byte));
+    /// let mut without_15 = batch.clone();
+    /// without_15.apply_transform(|sequence_index, count| {
+    ///     if sequence_index == 0x15 { 0 } else { count }
+    /// });
+    ///
+    /// assert_eq!(without_15.new_sequences_since(&baseline), vec![0x97]);
+    /// assert_eq!(without_15.disappeared_sequences(&baseline), vec![0x15]);
+    /// assert_eq!(batch.disappeared_sequences(&baseline), Vec::<usize>::new());
+    /// assert_eq!(baseline.new_sequences_since(&without_15), vec![0x15]);
+    /// assert!(batch.new_sequences_since(&batch).is_empty());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the counters have different sequence lengths.
+    pub fn new_sequences_since(&self, baseline: &DigitSequenceCounter) -> Vec<usize> {
+        if self.sequence_length != baseline.sequence_length {
+            panic!("Cannot compare DigitSequenceCounters of different sequence lengths.");
+        }
+        self.nonzero_counts()
+            .into_iter()
+            .map(|(sequence_index, _)| sequence_index)
+            .filter(|&sequence_index| baseline.count_at(sequence_index) == 0)
+            .collect()
+    }
+
+    /// Returns the sequence indices, in ascending order, that `baseline` has seen and this
+    /// counter never did. This is `baseline.new_sequences_since(self)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the counters have different sequence lengths.
+    pub fn disappeared_sequences(&self, baseline: &DigitSequenceCounter) -> Vec<usize> {
+        baseline.new_sequences_since(self)
+    }
+
+    /// Returns the Shannon entropy of the observed distribution in bits, or 0 without counts.
+    pub fn entropy_bits(&self) -> f64 {
+        let total = self.total_count() as f64;
+        self.nonzero_counts()
+            .into_iter()
+            .map(|(_, count)| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Treats this counter as the marginal distribution of both `X` and `Y` and `joint` as
+    /// their joint distribution, so `H(X)` and `H(Y)` are both this counter's entropy and the
+    /// chain rule `H(X, Y) = H(X) + H(Y | X)` holds exactly. The two should count the same
+    /// input; the longer length's total is smaller by one window per run of digits.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let input = b"3.14159265358979323846264338327950288419716939937510582097494459";
+    /// let mut marginal = DigitSequenceCounter::new(1);
+    /// let mut joint = DigitSequenceCounter::new(2);
+    /// for &byte in input.iter() {
+    ///     marginal.process_character(byte);
+    ///     joint.process_character(byte);
+    /// }
+    ///
+    /// let stats = marginal.second_order_statistics(&joint);
+    /// let chain = marginal.entropy_bits() + stats.conditional_entropy;
+    /// assert!((stats.joint_entropy - chain).abs() < 1e-12);
+    /// assert!(stats.mutual_information > 0.0);
+    /// assert!(stats.conditional_entropy < marginal.entropy_bits());
+    ///
+    /// // Repeating one digit leaves nothing to learn from the one before.
+    /// let mut marginal = DigitSequenceCounter::new(1);
+    /// let mut joint = DigitSequenceCounter::new(2);
+    /// for &byte in b"x7777777".iter() {
+    ///     marginal.process_character(byte);
+    ///     joint.process_character(byte);
+    /// }
+    /// let stats = marginal.second_order_statistics(&joint);
+    /// assert_eq!(
+    ///     (stats.mutual_information, stats.conditional_entropy, stats.joint_entropy),
+    ///     (0.0, 0.0, 0.0)
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `joint`'s sequence length isn't twice this counter's.
+    pub fn second_order_statistics(&self, joint: &DigitSequenceCounter) -> SecondOrderStats {
+        if joint.sequence_length != 2 * self.sequence_length {
+            panic!(
+                "Cannot pair a marginal of length {} with a joint of length {}; the joint must be twice as long.",
+                self.sequence_length, joint.sequence_length
+            );
+        }
+        let marginal_entropy = self.entropy_bits();
+        let joint_entropy = joint.entropy_bits();
+        let conditional_entropy = joint_entropy - marginal_entropy;
+        SecondOrderStats {
+            mutual_information: marginal_entropy - conditional_entropy,
+            conditional_entropy,
+            joint_entropy,
+        }
+    }
+
+    /// Returns `H(Y | X) = H(X, Y) - H(X)` in bits, where `X` is the first `prefix_length`
+    /// digits of each sequence and `Y` the rest, so it measures how uncertain the rest of a
+    /// sequence is once its start is known. `H(X, Y)` is `entropy_bits`, and `H(X)` is the
+    /// entropy of the counts summed by prefix. Unlike `second_order_statistics`, both come
+    /// from this one counter.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(4);
+    /// b"x31415926535897932384626433832795028841971693993751"
+    ///     .iter()
+    ///     .for_each(|&byte| counter.process_character(byte));
+    ///
+    /// assert_eq!(counter.conditional_entropy(0), counter.entropy_bits());
+    /// assert_eq!(counter.conditional_entropy(4), 0.0);
+    /// // Knowing more of the start leaves less to guess.
+    /// assert!(counter.conditional_entropy(1) >= counter.conditional_entropy(2));
+    /// assert!(counter.conditional_entropy(2) >= counter.conditional_entropy(3));
+    ///
+    /// // After 12, 34, or 56, the next two digits are always the same.
+    /// let mut counter = DigitSequenceCounter::new(4);
+    /// for run in [&b"x123412341234"[..], b"x56785678"] {
+    ///     run.iter().for_each(|&byte| counter.process_character(byte));
+    /// }
+    /// assert!(counter.conditional_entropy(2).abs() < 1e-12);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_length` is greater than the sequence length.
+    pub fn conditional_entropy(&self, prefix_length: usize) -> f64 {
+        if prefix_length > self.sequence_length {
+            panic!(
+                "Cannot condition on the first {} digits of sequences of length {}.",
+                prefix_length, self.sequence_length
+            );
+        }
+        let suffix_bits = (self.sequence_length - prefix_length) << 2;
+        let total = self.total_count() as f64;
+        let mut prefix_entropy = 0.0;
+        // Sequences sharing a prefix are adjacent in index order, so each prefix's count is
+        // summed in one pass.
+        let mut nonzero_counts = self.nonzero_counts().into_iter().peekable();
+        while let Some((sequence_index, mut prefix_count)) = nonzero_counts.next() {
+            let prefix = sequence_index >> suffix_bits;
+            while let Some(&(_, count)) = nonzero_counts
+                .peek()
+                .filter(|&&(sequence_index, _)| sequence_index >> suffix_bits == prefix)
+            {
+                prefix_count += count;
+                nonzero_counts.next();
+            }
+            let p = prefix_count as f64 / total;
+            prefix_entropy -= p * p.log2();
+        }
+        self.entropy_bits() - prefix_entropy
+    }
+
+    /// Returns the probability that two windows drawn at random, with replacement, are the
+    /// same sequence: `Σ p²`, between `1 / possible_sequences()` for a uniform distribution
+    /// and 1 when every window is one sequence, or 0 without counts. Its negative base-2
+    /// logarithm is the Rényi entropy of order 2.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let empty = DigitSequenceCounter::new(2);
+    /// assert_eq!(empty.count_collision_probability(), 0.0);
+    /// assert_eq!(empty.simpson_diversity_index(), 1.0);
+    ///
+    /// let mut uniform = DigitSequenceCounter::new(2);
+    /// uniform.apply_transform(|_, _| 7);
+    /// assert!((uniform.count_collision_probability() - 1.0 / 256.0).abs() < 1e-15);
+    ///
+    /// let mut constant = DigitSequenceCounter::new(2);
+    /// b"x777777".iter().for_each(|&byte| constant.process_character(byte));
+    /// assert_eq!(constant.count_collision_probability(), 1.0);
+    /// assert_eq!(constant.simpson_diversity_index(), 0.0);
+    ///
+    /// let mut halves = DigitSequenceCounter::new(2);
+    /// b"x11111 x22222".iter().for_each(|&byte| halves.process_character(byte));
+    /// assert_eq!(halves.count_collision_probability(), 0.5);
+    /// ```
+    pub fn count_collision_probability(&self) -> f64 {
+        let total = self.total_count() as f64;
+        self.nonzero_counts()
+            .into_iter()
+            .map(|(_, count)| {
+                let p = count as f64 / total;
+                p * p
+            })
+            .fold(0.0, |sum, p_squared| sum + p_squared)
+    }
+
+    /// Returns the Simpson diversity index, `1 - count_collision_probability()`, which is 1
+    /// without counts.
+    pub fn simpson_diversity_index(&self) -> f64 {
+        1.0 - self.count_collision_probability()
+    }
+
+    /// Returns the entropy as a fraction of the maximum, `sequence_length * log2(radix())` bits,
+    /// which is `4 * sequence_length` bits in radix 16.
+    pub fn entropy_efficiency(&self) -> f64 {
+        self.entropy_bits() / (self.sequence_length as f64 * (self.radix as f64).log2())
+    }
+
+    /// Returns Pearson's chi-square statistic against the uniform distribution over the
+    /// reachable sequences, with `reachable_sequences() - 1` degrees of freedom, or 0 without
+    /// counts.
+    pub fn chi_square(&self) -> f64 {
+        let total = self.total_count();
+        if total == 0 {
+            return 0.0;
+        }
+        let expected = self.expected_count(total);
+        let observed = self
+            .nonzero_counts()
+            .into_iter()
+            .map(|(_, count)| {
+                let difference = count as f64 - expected;
+                difference * difference / expected
+            })
+            .sum::<f64>();
+        observed + self.missing_count() as f64 * expected
+    }
+
+    /// Returns the probability of a chi-square statistic at least as large as `chi_square()`
+    /// if the sequences were uniform, or 1 without counts. Above 1000 degrees of freedom, the
+    /// Wilson-Hilferty normal approximation is used. Sketching counters keep no exact counts to
+    /// test, so they return NaN.
+    pub fn chi_square_p_value(&self) -> f64 {
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return f64::NAN;
+        }
+        if self.total_count() == 0 {
+            return 1.0;
+        }
+        chi_square_survival(self.chi_square(), (self.reachable_sequences() - 1) as f64)
+    }
+
+    /// Tests whether the first `prefix_length` digits of each sequence are independent of
+    /// the rest with a G-test, the likelihood-ratio test of the table of prefixes against
+    /// suffixes: `G = 2 Σ O ln(O / E)`, where each sequence's expected count `E` is its
+    /// prefix's total times its suffix's total over the total count. Only prefixes and
+    /// suffixes that were seen count toward `|X|` and `|Y|`, so the degrees of freedom are
+    /// `(|X| - 1) * (|Y| - 1)`. The p-value is the chi-square tail above `G`, or 1 when there
+    /// are no degrees of freedom. Sketching counters keep no exact counts to test, so their
+    /// statistic and p-value are NaN.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// // Every pair of digits equally often, as for independent digits.
+    /// let mut uniform = DigitSequenceCounter::new(2);
+    /// for sequence_index in 0..256 {
+    ///     for &byte in format!("x0{:02X}", sequence_index).as_bytes() {
+    ///         uniform.process_character(byte);
+    ///     }
+    /// }
+    /// let result = uniform.test_independence(1);
+    /// assert!(result.p_value > 0.05);
+    /// assert_eq!(result.degrees_of_freedom, 15 * 15);
+    ///
+    /// // Each digit mostly followed by the next one up.
+    /// let mut biased = DigitSequenceCounter::new(2);
+    /// for _ in 0..20 {
+    ///     b"x0123456789ABCDEF0".iter().for_each(|&byte| biased.process_character(byte));
+    /// }
+    /// b"x0F1E2D3C4B5A69788796A5B4C3D2E1F0".iter().for_each(|&byte| biased.process_character(byte));
+    /// let result = biased.test_independence(1);
+    /// assert!(result.g_statistic > 0.0);
+    /// assert!(result.p_value < 1e-6);
+    ///
+    /// assert_eq!(biased.test_independence(0).degrees_of_freedom, 0);
+    /// assert_eq!(biased.test_independence(0).p_value, 1.0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_length` is greater than the sequence length.
+    pub fn test_independence(&self, prefix_length: usize) -> IndependenceTestResult {
+        if prefix_length > self.sequence_length {
+            panic!(
+                "Cannot test the first {} digits of sequences of length {} for independence.",
+                prefix_length, self.sequence_length
+            );
+        }
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return IndependenceTestResult {
+                g_statistic: f64::NAN,
+                degrees_of_freedom: 0,
+                p_value: f64::NAN,
+            };
+        }
+        let suffix_bits = (self.sequence_length - prefix_length) << 2;
+        let suffix_mask = (1 << suffix_bits) - 1;
+        let nonzero_counts = self.nonzero_counts();
+        let mut prefix_totals = BTreeMap::new();
+        let mut suffix_totals = BTreeMap::new();
+        for &(sequence_index, count) in &nonzero_counts {
+            *prefix_totals
+                .entry(sequence_index >> suffix_bits)
+                .or_insert(0) += count;
+            *suffix_totals
+                .entry(sequence_index & suffix_mask)
+                .or_insert(0) += count;
+        }
+
+        let total = self.total_count() as f64;
+        let g_statistic = 2.0
+            * nonzero_counts
+                .iter()
+                .map(|&(sequence_index, count)| {
+                    let expected = prefix_totals[&(sequence_index >> suffix_bits)] as f64
+                        * suffix_totals[&(sequence_index & suffix_mask)] as f64
+                        / total;
+                    count as f64 * (count as f64 / expected).ln()
+                })
+                .sum::<f64>();
+        let degrees_of_freedom =
+            prefix_totals.len().saturating_sub(1) * suffix_totals.len().saturating_sub(1);
+        IndependenceTestResult {
+            g_statistic,
+            degrees_of_freedom,
+            p_value: if degrees_of_freedom == 0 {
+                1.0
+            } else {
+                chi_square_survival(g_statistic, degrees_of_freedom as f64)
+            },
+        }
+    }
+
+    /// Returns the circular autocorrelation of the counts as a series over the sequence
+    /// indices, `Σ (c[i] - mean) * (c[(i + lag) % n] - mean) / Σ (c[i] - mean)²` for the
+    /// `n = possible_sequences()` counts, between -1 and 1. It is NaN when it isn't defined,
+    /// which is without counts, when every count is the same, and for sketching counters,
+    /// which keep no exact counts.
+    pub fn autocorrelation(&self, lag: usize) -> f64 {
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return f64::NAN;
+        }
+        let possible_sequences = self.possible_sequences();
+        let counts = self.nonzero_counts();
+        let mean = self.total_count() as f64 / possible_sequences as f64;
+        let centering = possible_sequences as f64 * mean * mean;
+        let lagged = counts
+            .iter()
+            .map(|&(sequence_index, count)| {
+                count as f64 * self.count_at((sequence_index + lag) % possible_sequences) as f64
+            })
+            .sum::<f64>();
+        let squared = counts
+            .iter()
+            .map(|&(_, count)| (count as f64).powi(2))
+            .sum::<f64>();
+        let variance = squared - centering;
+        if variance <= 0.0 {
+            return f64::NAN;
+        }
+        ((lagged - centering) / variance).clamp(-1.0, 1.0)
+    }
+
+    /// The longest sequence length the command line scores with `strongest_autocorrelation`,
+    /// which takes time proportional to `possible_sequences()` times the number of sequences
+    /// seen.
+    pub const PERIODICITY_LENGTH_LIMIT: usize = 3;
+
+    /// Returns `(lag, autocorrelation(lag))` for the lag from 1 to `possible_sequences() / 2`
+    /// with the largest absolute autocorrelation, the first if several tie, or `None` where
+    /// the autocorrelation isn't defined. The first lag with an absolute autocorrelation
+    /// above 0.9 is returned without looking further, as the counts are clearly periodic.
+    pub fn strongest_autocorrelation(&self) -> Option<(usize, f64)> {
+        let mut strongest: Option<(usize, f64)> = None;
+        for lag in 1..=self.possible_sequences() / 2 {
+            let autocorrelation = self.autocorrelation(lag);
+            if autocorrelation.is_nan() {
+                return None;
+            }
+            if strongest.is_none_or(|(_, strongest)| autocorrelation.abs() > strongest.abs()) {
+                strongest = Some((lag, autocorrelation));
+                if autocorrelation.abs() > 0.9 {
+                    break;
+                }
+            }
+        }
+        strongest
+    }
+
+    /// Returns the absolute autocorrelation of `strongest_autocorrelation`, from 0 for no
+    /// periodicity in the counts to 1 for counts that repeat exactly, or 0 where the
+    /// autocorrelation isn't defined. Sketching counters keep no exact counts to score, so
+    /// they return NaN.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// // Every fourth digit is counted four times as often, so the counts repeat at lag 4.
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// counter.apply_transform(|i, _| if i % 4 == 0 { 4 } else { 1 });
+    /// assert!((counter.autocorrelation(1) + 1.0 / 3.0).abs() < 1e-12);
+    /// assert_eq!(counter.strongest_autocorrelation(), Some((4, 1.0)));
+    /// assert_eq!(counter.compute_periodicity_score(), 1.0);
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x3141592653589793238462643383279502884197169399375105820974944592307816406286"
+    ///     .iter()
+    ///     .for_each(|&byte| counter.process_character(byte));
+    /// let score = counter.compute_periodicity_score();
+    /// assert!(score > 0.0 && score < 0.9);
+    ///
+    /// counter.apply_transform(|_, _| 2);
+    /// assert_eq!(counter.strongest_autocorrelation(), None);
+    /// assert_eq!(counter.compute_periodicity_score(), 0.0);
+    /// ```
+    pub fn compute_periodicity_score(&self) -> f64 {
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return f64::NAN;
+        }
+        self.strongest_autocorrelation()
+            .map_or(0.0, |(_, autocorrelation)| autocorrelation.abs())
+    }
+
+    /// Returns the largest absolute z-score of any sequence's count, including never-seen
+    /// ones, against the binomial distribution each count has if the sequences were uniform,
+    /// or 0 without counts. Sketching counters keep no exact counts to compare, so they return NaN.
+    pub fn max_abs_z_score(&self) -> f64 {
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return f64::NAN;
+        }
+        let total = self.total_count() as f64;
+        if total == 0.0 {
+            return 0.0;
+        }
+        let p = 1.0 / self.reachable_sequences() as f64;
+        let expected = self.expected_count(self.total_count());
+        let standard_deviation = (total * p * (1.0 - p)).sqrt();
+        let counts = self.nonzero_counts();
+        let largest = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        let smallest = if self.missing_count() == 0 {
+            counts.iter().map(|&(_, count)| count).min().unwrap_or(0)
+        } else {
+            0
+        };
+        (largest as f64 - expected)
+            .abs()
+            .max((smallest as f64 - expected).abs())
+            / standard_deviation
+    }
+
+    /// Returns `(sequence index, z)` for every reachable sequence, seen or not, whose count is
+    /// more than `z_threshold` standard deviations from what a uniform distribution expects,
+    /// with `z = (count - expected) / sqrt(expected)` by the Poisson approximation. The
+    /// largest `|z|` comes first, and equal ones are in index order. Sketching counters and
+    /// counters without counts have none.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// // 100 of each sequence, but 7 was injected 60 more times and C is 40 short.
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// counter.apply_transform(|i, _| match i {
+    ///     7 => 160,
+    ///     0xC => 60,
+    ///     _ => 100,
+    /// });
+    /// let expected = 100.0f64 + 20.0 / 16.0;
+    ///
+    /// let outliers = counter.find_excess_sequences(3.0);
+    /// assert_eq!(outliers.iter().map(|&(i, _)| i).collect::<Vec<_>>(), vec![7, 0xC]);
+    /// assert!((outliers[0].1 - (160.0 - expected) / expected.sqrt()).abs() < 1e-12);
+    /// assert!(outliers[1].1 < -3.0);
+    /// assert!(counter.find_excess_sequences(10.0).is_empty());
+    ///
+    /// // Never-seen sequences are as far below as the expected count is large.
+    /// counter.apply_transform(|i, count| if i == 0xF { 0 } else { count });
+    /// assert_eq!(counter.find_excess_sequences(9.0).first().map(|&(i, _)| i), Some(0xF));
+    /// ```
+    pub fn find_excess_sequences(&self, z_threshold: f64) -> Vec<(usize, f64)> {
+        let total = self.total_count();
+        if total == 0 {
+            return Vec::new();
+        }
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return Vec::new();
+        }
+        let expected = self.expected_count(total);
+        let z_score = |count: u128| (count as f64 - expected) / expected.sqrt();
+        let mut outliers = self
+            .nonzero_counts()
+            .into_iter()
+            .map(|(sequence_index, count)| (sequence_index, z_score(count)))
+            .filter(|&(_, z)| z.abs() > z_threshold)
+            .collect::<Vec<_>>();
+        if z_score(0).abs() > z_threshold && self.missing_count() != 0 {
+            let radix = self.radix as usize;
+            let reachable = |sequence_index: usize| {
+                (0..self.sequence_length).all(|digit| (sequence_index >> (4 * digit)) & 0xF < radix)
+            };
+            outliers.extend(
+                (0..self.possible_sequences())
+                    .filter(|&sequence_index| {
+                        reachable(sequence_index) && self.count_at(sequence_index) == 0
+                    })
+                    .map(|sequence_index| (sequence_index, z_score(0))),
+            );
+            outliers.sort_unstable_by_key(|&(sequence_index, _)| sequence_index);
+        }
+        outliers.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        outliers
+    }
+
+    /// Returns the total variation distance between the observed distribution and the uniform
+    /// one, `0.5 * Σ |count - expected| / total`, or 0 without counts. This is the largest
+    /// difference in probability any set of sequences can have between the two distributions.
+    /// Sketching counters keep no exact counts to compare, so they return NaN.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let count = |counter: &mut DigitSequenceCounter, text: &[u8]| {
+    ///     text.iter().for_each(|&byte| counter.process_character(byte))
+    /// };
+    ///
+    /// assert_eq!(DigitSequenceCounter::new(2).total_variation_distance_from_uniform(), 0.0);
+    ///
+    /// let mut uniform = DigitSequenceCounter::new(2);
+    /// uniform.apply_transform(|_, _| 5);
+    /// assert_eq!(uniform.total_variation_distance_from_uniform(), 0.0);
+    ///
+    /// let mut constant = DigitSequenceCounter::new(1);
+    /// count(&mut constant, b"x0000000000");
+    /// assert_eq!(constant.total_variation_distance_from_uniform(), 15.0 / 16.0);
+    ///
+    /// let mut decimal = DigitSequenceCounter::new(1).with_radix(10);
+    /// count(&mut decimal, b"x00123456789");
+    /// assert_eq!(decimal.total_variation_distance_from_uniform(), 0.0);
+    /// let mut hex = DigitSequenceCounter::new(1);
+    /// count(&mut hex, b"x00123456789");
+    /// assert_eq!(hex.total_variation_distance_from_uniform(), 0.375);
+    ///
+    /// let mut constant = DigitSequenceCounter::new(1).with_radix(10);
+    /// count(&mut constant, b"x0000000000");
+    /// assert!((constant.total_variation_distance_from_uniform() - 0.9).abs() < 1e-12);
+    ///
+    /// let mut sketch = DigitSequenceCounter::new_hyperloglog(2, 10);
+    /// count(&mut sketch, b"x31415926535");
+    /// assert!(sketch.total_variation_distance_from_uniform().is_nan());
+    /// ```
+    pub fn total_variation_distance_from_uniform(&self) -> f64 {
+        let total = self.total_count();
+        if total == 0 {
+            return 0.0;
+        }
+        let expected = self.expected_count(total);
+        let deviation = |count: u128| (count as f64 - expected).abs();
+        if let Counts::HyperLogLog(_) | Counts::CountMin(..) = self.sequence_counts {
+            return f64::NAN;
+        }
+        // Summed in index order, as sparse storage's map order would change the rounding.
+        let seen_deviation = self
+            .nonzero_counts()
+            .into_iter()
+            .map(|(_, count)| deviation(count))
+            .sum::<f64>();
+        let stored_deviation = seen_deviation + self.missing_count() as f64 * expected;
+        0.5 * stored_deviation / total as f64
+    }
+
+    /// Returns up to `n` of the most frequent sequences as `(sequence index, count)`, most
+    /// frequent first and ties in index order.
+    pub fn top_n(&self, n: usize) -> Vec<(usize, u128)> {
+        let mut counts = self.nonzero_counts();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Returns up to `n` of the least frequent sequences that were seen at least once, least
+    /// frequent first and ties in index order.
+    pub fn bottom_n(&self, n: usize) -> Vec<(usize, u128)> {
+        let mut counts = self.nonzero_counts();
+        counts.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Writes the common statistics as one aligned, human-readable block.
+    pub fn print_summary(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.print_summary_with(writer, &NumberFormat::default())
+    }
+
+    /// Writes the same block as `print_summary`, rendering statistics with `number_format`.
+    pub fn print_summary_with(
+        &self,
+        writer: &mut dyn Write,
+        number_format: &NumberFormat,
+    ) -> io::Result<()> {
+        let format_sequences = |sequences: Vec<(usize, u128)>| {
+            sequences
+                .into_iter()
+                .map(|(sequence_index, count)| {
+                    format!("{} ({})", self.sequence_label(sequence_index), count)
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        writeln!(writer, "Summary for length {}:", self.sequence_length)?;
+        writeln!(writer, "  {:<20}{}", "Total count:", self.total_count())?;
+        writeln!(
+            writer,
+            "  {:<20}{} of {}",
+            "Unique sequences:",
+            self.unique_count(),
+            self.reachable_sequences()
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{}",
+            "Coverage:",
+            number_format.format(self.coverage())
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{} bits (efficiency {})",
+            "Entropy:",
+            number_format.format(self.entropy_bits()),
+            number_format.format(self.entropy_efficiency())
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{} ({} degrees of freedom)",
+            "Chi-square:",
+            number_format.format(self.chi_square()),
+            self.reachable_sequences() - 1
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{}",
+            "Top 5:",
+            format_sequences(self.top_n(5))
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{}",
+            "Bottom 5:",
+            format_sequences(self.bottom_n(5))
+        )?;
+        writeln!(
+            writer,
+            "  {:<20}{}",
+            "Missing sequences:",
+            self.missing_count()
+        )
+    }
+
+    /// Returns the counts of counts: for each count `k` that occurs, how many sequences were
+    /// seen exactly `k` times. Zero is left out; it is `missing_count()`.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x3141592653".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let distribution = counter.count_distribution();
+    /// assert_eq!(distribution.into_iter().collect::<Vec<_>>(), vec![(1, 5), (2, 2)]);
+    ///
+    /// for text in [&b"x3141592653"[..], b"x0000 1111 22", b"", b"x0123456789ABCDEF"] {
+    ///     let mut counter = DigitSequenceCounter::new(2);
+    ///     text.iter().for_each(|&byte| counter.process_character(byte));
+    ///     let distribution = counter.count_distribution();
+    ///     let total = distribution.iter().map(|(&k, &v)| k * v as u128).sum::<u128>();
+    ///     assert_eq!(total, counter.total_count());
+    ///     let seen = distribution.values().sum::<usize>();
+    ///     assert_eq!(seen + counter.missing_count(), counter.possible_sequences());
+    /// }
+    /// ```
+    pub fn count_distribution(&self) -> BTreeMap<u128, usize> {
+        let mut distribution = BTreeMap::new();
+        for (_, count) in self.nonzero_counts() {
+            *distribution.entry(count).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// Estimates sequence probabilities with Simple Good-Turing smoothing (Gale and Sampson,
+    /// 1995), returning one probability per possible sequence and the total probability mass
+    /// given to unseen sequences, which share it equally.
+    ///
+    /// Without any counts, every sequence is unseen and the estimate is uniform. If every
+    /// possible sequence was seen, no mass is reserved for unseen ones.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let pi = b"x3141592653589793238462643383279502884197169399375105820974944592307816406286";
+    /// for sequence_length in 1..=3 {
+    ///     for text in [&pi[..], b"", b"x0123456789ABCDEF0", b"x1111111111 2222 33"] {
+    ///         let mut counter = DigitSequenceCounter::new(sequence_length);
+    ///         text.iter().for_each(|&byte| counter.process_character(byte));
+    ///         let (probabilities, unseen_mass) = counter.good_turing_estimate();
+    ///         assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    ///         assert!(probabilities.iter().all(|&p| p >= 0.0));
+    ///
+    ///         let unseen = (0..counter.possible_sequences())
+    ///             .filter(|&i| counter.count_at(i) == 0)
+    ///             .map(|i| probabilities[i])
+    ///             .sum::<f64>();
+    ///         assert!((unseen - unseen_mass).abs() < 1e-9);
+    ///     }
+    /// }
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x0123456789ABCDEF0".iter().for_each(|&byte| counter.process_character(byte));
+    /// assert_eq!(counter.good_turing_estimate().1, 0.0);
+    /// ```
+    pub fn good_turing_estimate(&self) -> (Vec<f64>, f64) {
+        let possible_sequences = self.possible_sequences();
+        let distribution = self.count_distribution();
+        let singletons = distribution.get(&1).copied().unwrap_or(0);
+        let counts_of_counts = distribution
+            .into_iter()
+            .map(|(count, sequences)| (count as f64, sequences as f64))
+            .collect::<Vec<_>>();
+        let unseen_sequences = possible_sequences - self.unique_count();
+        if counts_of_counts.is_empty() {
+            return (
+                vec![1.0 / possible_sequences as f64; possible_sequences],
+                1.0,
+            );
+        }
+
+        let total = counts_of_counts
+            .iter()
+            .map(|&(count, sequences)| count * sequences)
+            .sum::<f64>();
+        let unseen_mass = if unseen_sequences == 0 {
+            0.0
+        } else {
+            singletons as f64 / total
+        };
+
+        // Averages each N_r over the gap to its neighboring nonzero counts of counts, then fits
+        // log Z_r = a + b log r by least squares.
+        let log_points = counts_of_counts
+            .iter()
+            .enumerate()
+            .map(|(i, &(count, sequences))| {
+                let previous = if i == 0 {
+                    0.0
+                } else {
+                    counts_of_counts[i - 1].0
+                };
+                let next = match counts_of_counts.get(i + 1) {
+                    Some(&(next, _)) => next,
+                    None => 2.0 * count - previous,
+                };
+                (count.ln(), (2.0 * sequences / (next - previous)).ln())
+            })
+            .collect::<Vec<_>>();
+        let slope = if log_points.len() < 2 {
+            -1.0
+        } else {
+            let n = log_points.len() as f64;
+            let mean_x = log_points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+            let mean_y = log_points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+            let covariance = log_points
+                .iter()
+                .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+                .sum::<f64>();
+            let variance = log_points
+                .iter()
+                .map(|&(x, _)| (x - mean_x) * (x - mean_x))
+                .sum::<f64>();
+            covariance / variance
+        };
+
+        // Uses the Turing estimate while it differs significantly from the smoothed one, then
+        // switches to the smoothed estimate for every larger count.
+        let mut use_smoothed = false;
+        let adjusted_counts = counts_of_counts
+            .iter()
+            .enumerate()
+            .map(|(i, &(count, sequences))| {
+                let smoothed = (count + 1.0) * (1.0 + 1.0 / count).powf(slope);
+                let next_sequences = match counts_of_counts.get(i + 1) {
+                    Some(&(next, next_sequences)) if next == count + 1.0 => next_sequences,
+                    _ => 0.0,
+                };
+                if next_sequences == 0.0 {
+                    use_smoothed = true;
+                }
+                if use_smoothed {
+                    return smoothed;
+                }
+                let turing = (count + 1.0) * next_sequences / sequences;
+                let threshold = 1.96
+                    * ((count + 1.0) * (count + 1.0) * next_sequences / (sequences * sequences)
+                        * (1.0 + next_sequences / sequences))
+                        .sqrt();
+                if (turing - smoothed).abs() > threshold {
+                    turing
+                } else {
+                    use_smoothed = true;
+                    smoothed
+                }
+            })
+            .collect::<Vec<_>>();
+        let adjusted_total = counts_of_counts
+            .iter()
+            .zip(&adjusted_counts)
+            .map(|(&(_, sequences), adjusted)| sequences * adjusted)
+            .sum::<f64>();
+
+        let unseen_probability = if unseen_sequences == 0 {
+            0.0
+        } else {
+            unseen_mass / unseen_sequences as f64
+        };
+        let mut probabilities = vec![unseen_probability; possible_sequences];
+        let mut adjusted = counts_of_counts.iter().map(|&(count, _)| count).zip(
+            adjusted_counts
+                .iter()
+                .map(|adjusted| (1.0 - unseen_mass) * adjusted / adjusted_total),
+        );
+        let mut current = adjusted.next();
+        let mut nonzero_counts = self.nonzero_counts();
+        nonzero_counts.sort_unstable_by_key(|&(_, count)| count);
+        for (sequence_index, count) in nonzero_counts {
+            while let Some((r, _)) = current {
+                if r == count as f64 {
+                    break;
+                }
+                current = adjusted.next();
+            }
+            probabilities[sequence_index] = current.map_or(0.0, |(_, probability)| probability);
+        }
+
+        (probabilities, unseen_mass)
+    }
+}
+
+/// Returns Spearman's rank correlation between the counts of `a` and `b` over every possible
+/// sequence: the Pearson correlation of each sequence's rank by count in `a` with its rank
+/// in `b`, with tied counts, including the sequences never seen, sharing their average rank.
+/// It is `NAN` when either counter gives every sequence the same count. Only the seen
+/// sequences are ranked individually, so this takes O(n log n) time in the number seen.
+///
+/// ```
+/// use digit_sequence_counter::statistics::spearman_rank_correlation;
+/// use digit_sequence_counter::DigitSequenceCounter;
+///
+/// // Counts each digit `count(digit)` times, after a first digit that isn't counted.
+/// let counter_with = |count: &dyn Fn(usize) -> usize| {
+///     let mut counter = DigitSequenceCounter::new(1);
+///     counter.process_character(b'0');
+///     for digit in 0..16 {
+///         for _ in 0..count(digit) {
+///             counter.process_character(b"0123456789ABCDEF"[digit]);
+///         }
+///     }
+///     counter
+/// };
+/// let a = counter_with(&|digit| digit + 1);
+/// let reversed = counter_with(&|digit| 16 - digit);
+/// let tied = counter_with(&|digit| digit / 4);
+///
+/// assert!((spearman_rank_correlation(&a, &a) - 1.0).abs() < 1e-12);
+/// assert!((spearman_rank_correlation(&a, &reversed) + 1.0).abs() < 1e-12);
+/// let r = spearman_rank_correlation(&a, &tied);
+/// assert!(r > 0.9 && r < 1.0);
+/// assert!(spearman_rank_correlation(&a, &counter_with(&|_| 3)).is_nan());
+/// assert!(spearman_rank_correlation(&a, &DigitSequenceCounter::new(1)).is_nan());
+///
+/// // The unseen sequences rank as one tie.
+/// let mut seen = DigitSequenceCounter::new(2);
+/// b"x31415".iter().for_each(|&byte| seen.process_character(byte));
+/// assert!((spearman_rank_correlation(&seen, &seen) - 1.0).abs() < 1e-12);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different sequence lengths.
+pub fn spearman_rank_correlation(a: &DigitSequenceCounter, b: &DigitSequenceCounter) -> f64 {
+    if a.sequence_length != b.sequence_length {
+        panic!("Cannot correlate DigitSequenceCounters of different sequence lengths.");
+    }
+    let sequences = a.possible_sequences() as f64;
+    let mean_rank = (sequences + 1.0) / 2.0;
+    let (a_ranks, a_zero_rank) = average_ranks(a);
+    let (b_ranks, b_zero_rank) = average_ranks(b);
+
+    let variance = |ranks: &[(usize, f64)], zero_rank: f64| {
+        let unseen = sequences - ranks.len() as f64;
+        ranks
+            .iter()
+            .map(|&(_, rank)| (rank - mean_rank).powi(2))
+            .sum::<f64>()
+            + unseen * (zero_rank - mean_rank).powi(2)
+    };
+    let a_variance = variance(&a_ranks, a_zero_rank);
+    let b_variance = variance(&b_ranks, b_zero_rank);
+    if a_variance == 0.0 || b_variance == 0.0 {
+        return f64::NAN;
+    }
+
+    // Walks the sequences seen by either counter in index order; the rest are zero in both.
+    let mut covariance = 0.0;
+    let mut seen_by_either = 0;
+    let (mut a_ranks, mut b_ranks) = (a_ranks.iter().peekable(), b_ranks.iter().peekable());
+    loop {
+        let (a_rank, b_rank) = match (a_ranks.peek(), b_ranks.peek()) {
+            (Some(&&(a_index, a_rank)), Some(&&(b_index, b_rank))) => {
+                if a_index < b_index {
+                    a_ranks.next();
+                    (a_rank, b_zero_rank)
+                } else if b_index < a_index {
+                    b_ranks.next();
+                    (a_zero_rank, b_rank)
+                } else {
+                    a_ranks.next();
+                    b_ranks.next();
+                    (a_rank, b_rank)
+                }
+            }
+            (Some(&&(_, a_rank)), None) => {
+                a_ranks.next();
+                (a_rank, b_zero_rank)
+            }
+            (None, Some(&&(_, b_rank))) => {
+                b_ranks.next();
+                (a_zero_rank, b_rank)
+            }
+            (None, None) => break,
+        };
+        covariance += (a_rank - mean_rank) * (b_rank - mean_rank);
+        seen_by_either += 1;
+    }
+    covariance +=
+        (sequences - seen_by_either as f64) * (a_zero_rank - mean_rank) * (b_zero_rank - mean_rank);
+
+    covariance / (a_variance * b_variance).sqrt()
+}
+
+/// Returns `Σ a[i] * b[i]` over every possible sequence, saturating each product and the
+/// sum at `u128::MAX`. Only the sequences one of the counters has seen are visited, looking
+/// each up in the other, so with sparse storage on either side this takes time in the
+/// number that counter has seen.
+///
+/// ```
+/// use digit_sequence_counter::statistics::{cosine_similarity, sparse_dot_product};
+/// use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+///
+/// let mut a = DigitSequenceCounter::new(1);
+/// let mut b = DigitSequenceCounter::with_storage(1, StorageMode::Sparse);
+/// b"x31415".iter().for_each(|&byte| a.process_character(byte));
+/// b"x1141".iter().for_each(|&byte| b.process_character(byte));
+///
+/// // a has 1, 4, 1, 5 and b has 1, 4, 1.
+/// assert_eq!(sparse_dot_product(&a, &b), 2 * 2 + 1 * 1);
+/// assert!((cosine_similarity(&a, &b) - 5.0 / (6.0f64.sqrt() * 5.0f64.sqrt())).abs() < 1e-12);
+/// assert!((cosine_similarity(&a, &a) - 1.0).abs() < 1e-12);
+/// assert!(cosine_similarity(&a, &DigitSequenceCounter::new(1)).is_nan());
+/// ```
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different sequence lengths.
+pub fn sparse_dot_product(a: &DigitSequenceCounter, b: &DigitSequenceCounter) -> u128 {
+    if a.sequence_length != b.sequence_length {
+        panic!("Cannot multiply DigitSequenceCounters of different sequence lengths.");
+    }
+    let (visited, looked_up) = if a.is_dense() { (b, a) } else { (a, b) };
+    visited
+        .nonzero_counts()
+        .into_iter()
+        .map(|(sequence_index, count)| count.saturating_mul(looked_up.count_at(sequence_index)))
+        .fold(0, u128::saturating_add)
+}
+
+/// Returns the cosine of the angle between the count vectors of `a` and `b`,
+/// `Σ a[i] * b[i] / (√(Σ a[i]²) * √(Σ b[i]²))`, from 0 for counters that share no sequences
+/// to 1 for proportional counts, or `NAN` if either has no counts. It is computed in `f64`,
+/// so unlike `sparse_dot_product` it doesn't saturate.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different sequence lengths.
+pub fn cosine_similarity(a: &DigitSequenceCounter, b: &DigitSequenceCounter) -> f64 {
+    if a.sequence_length != b.sequence_length {
+        panic!("Cannot compare DigitSequenceCounters of different sequence lengths.");
+    }
+    let (visited, looked_up) = if a.is_dense() { (b, a) } else { (a, b) };
+    let dot_product = visited
+        .nonzero_counts()
+        .into_iter()
+        .map(|(sequence_index, count)| count as f64 * looked_up.count_at(sequence_index) as f64)
+        .sum::<f64>();
+    let norm = |counter: &DigitSequenceCounter| {
+        counter
+            .nonzero_counts()
+            .into_iter()
+            .map(|(_, count)| (count as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    dot_product / (norm(a) * norm(b))
+}
+
+/// Tests whether the counts of `a` and `b` could come from the same distribution, with
+/// Pearson's chi-square test of homogeneity on the table of both rows of counts. The
+/// expected count of each sequence in a row is its total over both rows split in proportion
+/// to the row totals. Only the sequences seen in either counter are columns, so there are
+/// one fewer degrees of freedom than those, or none if either counter has no counts, which
+/// gives a p-value of 1.
+///
+/// ```
+/// use digit_sequence_counter::statistics::two_sample_chi_square_test;
+/// use digit_sequence_counter::DigitSequenceCounter;
+///
+/// let counter_with = |counts: &dyn Fn(usize) -> u128| {
+///     let mut counter = DigitSequenceCounter::new(1);
+///     counter.apply_transform(|i, _| counts(i));
+///     counter
+/// };
+/// let a = counter_with(&|i| 100 + i as u128);
+///
+/// let same = two_sample_chi_square_test(&a, &counter_with(&|i| 2 * (100 + i as u128)));
+/// assert!(same.chi_square.abs() < 1e-9 && same.cohens_w.abs() < 1e-6);
+/// assert_eq!(same.degrees_of_freedom, 15);
+/// assert!((same.p_value - 1.0).abs() < 1e-9);
+///
+/// let different = two_sample_chi_square_test(&a, &counter_with(&|i| 100 + 10 * i as u128));
+/// assert!(different.p_value < 0.001);
+/// let n = (a.total_count() + 100 * 16 + 10 * 120) as f64;
+/// assert!((different.cohens_w - (different.chi_square / n).sqrt()).abs() < 1e-12);
+///
+/// let empty = two_sample_chi_square_test(&a, &DigitSequenceCounter::new(1));
+/// assert_eq!((empty.chi_square, empty.degrees_of_freedom, empty.p_value), (0.0, 0, 1.0));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different sequence lengths.
+pub fn two_sample_chi_square_test(
+    a: &DigitSequenceCounter,
+    b: &DigitSequenceCounter,
+) -> TwoSampleResult {
+    if a.sequence_length != b.sequence_length {
+        panic!("Cannot compare DigitSequenceCounters of different sequence lengths.");
+    }
+    let (a_total, b_total) = (a.total_count() as f64, b.total_count() as f64);
+    let total = a_total + b_total;
+    if a_total == 0.0 || b_total == 0.0 {
+        return TwoSampleResult {
+            chi_square: 0.0,
+            degrees_of_freedom: 0,
+            p_value: 1.0,
+            cohens_w: 0.0,
+        };
+    }
+
+    // Both are in index order, so they merge into the columns in one pass.
+    let mut a_counts = a.nonzero_counts().into_iter().peekable();
+    let mut b_counts = b.nonzero_counts().into_iter().peekable();
+    let mut columns = 0;
+    let mut chi_square = 0.0;
+    loop {
+        let (a_count, b_count) = match (a_counts.peek(), b_counts.peek()) {
+            (None, None) => break,
+            (Some(&(a_index, _)), Some(&(b_index, _))) if a_index == b_index => {
+                (a_counts.next().unwrap().1, b_counts.next().unwrap().1)
+            }
+            (Some(&(a_index, _)), Some(&(b_index, _))) if a_index > b_index => {
+                (0, b_counts.next().unwrap().1)
+            }
+            (Some(_), _) => (a_counts.next().unwrap().1, 0),
+            (None, Some(_)) => (0, b_counts.next().unwrap().1),
+        };
+        let column_total = (a_count + b_count) as f64;
+        let deviation = |count: u128, row_total: f64| {
+            let expected = column_total * row_total / total;
+            (count as f64 - expected).powi(2) / expected
+        };
+        chi_square += deviation(a_count, a_total) + deviation(b_count, b_total);
+        columns += 1;
+    }
+
+    let degrees_of_freedom = columns - 1;
+    TwoSampleResult {
+        chi_square,
+        degrees_of_freedom,
+        p_value: if degrees_of_freedom == 0 {
+            1.0
+        } else {
+            chi_square_survival(chi_square, degrees_of_freedom as f64)
+        },
+        cohens_w: (chi_square / total).sqrt(),
+    }
+}
+
+/// Ranks every possible sequence of `counter` by count from 1 up, giving tied counts their
+/// average rank. Returns `(sequence index, rank)` for each nonzero count in index order, and
+/// the rank shared by the sequences never seen.
+fn average_ranks(counter: &DigitSequenceCounter) -> (Vec<(usize, f64)>, f64) {
+    let nonzero_counts = counter.nonzero_counts();
+    let unseen = counter.possible_sequences() - nonzero_counts.len();
+    let zero_rank = (unseen as f64 + 1.0) / 2.0;
+
+    let mut by_count = (0..nonzero_counts.len()).collect::<Vec<_>>();
+    by_count.sort_unstable_by_key(|&i| nonzero_counts[i].1);
+    let mut ranks = nonzero_counts
+        .iter()
+        .map(|&(sequence_index, _)| (sequence_index, 0.0))
+        .collect::<Vec<_>>();
+    let mut start = 0;
+    while start < by_count.len() {
+        let count = nonzero_counts[by_count[start]].1;
+        let end = start
+            + by_count[start..]
+                .iter()
+                .take_while(|&&i| nonzero_counts[i].1 == count)
+                .count();
+        // Ranks unseen + start + 1 through unseen + end, averaged.
+        let rank = unseen as f64 + (start + end + 1) as f64 / 2.0;
+        for &i in &by_count[start..end] {
+            ranks[i].1 = rank;
+        }
+        start = end;
+    }
+    (ranks, zero_rank)
+}
+
+/// Returns the probability of a chi-square statistic of `degrees_of_freedom` at least as
+/// large as `statistic`, through the Wilson-Hilferty normal approximation above 1000 degrees
+/// of freedom.
+fn chi_square_survival(statistic: f64, degrees_of_freedom: f64) -> f64 {
+    if degrees_of_freedom > 1000.0 {
+        let spread = 2.0 / (9.0 * degrees_of_freedom);
+        let z = ((statistic / degrees_of_freedom).cbrt() - (1.0 - spread)) / spread.sqrt();
+        0.5 * erfc(z / std::f64::consts::SQRT_2)
+    } else {
+        regularized_upper_gamma(degrees_of_freedom / 2.0, statistic / 2.0)
+    }
+}
+
+/// Returns the regularized upper incomplete gamma function `Q(a, x)`, using its series below
+/// `x = a + 1` and its continued fraction above, as in Numerical Recipes.
+fn regularized_upper_gamma(a: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 10_000;
+    const EPSILON: f64 = 1e-15;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term < sum * EPSILON {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefactor.exp()).max(0.0)
+    } else {
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let step = d * c;
+            fraction *= step;
+            if (step - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        fraction * log_prefactor.exp()
+    }
+}
+
+/// Returns `ln Γ(x)` for positive `x` by the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, &coefficient)| {
+            sum + coefficient / (x + i as f64 + 1.0)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Returns the complementary error function with a fractional error below `1.2e-7`, by the
+/// Chebyshev fit from Numerical Recipes.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let polynomial = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, &coefficient| sum * t + coefficient);
+    let result = t * (-x * x + polynomial).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
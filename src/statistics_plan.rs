@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::number_format::NumberFormat;
+use crate::DigitSequenceCounter;
+
+/// A statistic that a `StatisticsPlan` can turn on or off per length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Statistic {
+    /// The chi-square statistic against the uniform distribution and its p-value.
+    ChiSquare,
+    /// The Shannon entropy and its efficiency.
+    Entropy,
+    /// The largest absolute z-score of any sequence's count.
+    ZScores,
+    /// The total variation distance from the uniform distribution.
+    TotalVariation,
+    /// The collision probability and Simpson diversity index.
+    Simpson,
+    /// How many sequences occurred each number of times.
+    CountHistogram,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 6] = [
+        Statistic::ChiSquare,
+        Statistic::Entropy,
+        Statistic::ZScores,
+        Statistic::TotalVariation,
+        Statistic::Simpson,
+        Statistic::CountHistogram,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Statistic::ChiSquare => "chi2",
+            Statistic::Entropy => "entropy",
+            Statistic::ZScores => "zscores",
+            Statistic::TotalVariation => "tvd",
+            Statistic::Simpson => "simpson",
+            Statistic::CountHistogram => "histogram",
+        }
+    }
+
+    /// Writes this statistic of `counter` as aligned `  Name:  value` lines, like the
+    /// summary block.
+    pub fn write<W: Write>(
+        self,
+        out: &mut W,
+        counter: &DigitSequenceCounter,
+        number_format: &NumberFormat,
+    ) -> io::Result<()> {
+        match self {
+            Statistic::ChiSquare => writeln!(
+                out,
+                "  {:<20}{} ({} degrees of freedom, p = {})",
+                "Chi-square:",
+                number_format.format(counter.chi_square()),
+                counter.reachable_sequences() - 1,
+                number_format.format(counter.chi_square_p_value())
+            ),
+            Statistic::Entropy => writeln!(
+                out,
+                "  {:<20}{} bits (efficiency {})",
+                "Entropy:",
+                number_format.format(counter.entropy_bits()),
+                number_format.format(counter.entropy_efficiency())
+            ),
+            Statistic::ZScores => writeln!(
+                out,
+                "  {:<20}{}",
+                "Max |z|:",
+                number_format.format(counter.max_abs_z_score())
+            ),
+            Statistic::TotalVariation => writeln!(
+                out,
+                "  {:<20}{}",
+                "Total variation:",
+                number_format.format(counter.total_variation_distance_from_uniform())
+            ),
+            Statistic::Simpson => writeln!(
+                out,
+                "  {:<20}{} (collision probability {})",
+                "Simpson index:",
+                number_format.format(counter.simpson_diversity_index()),
+                number_format.format(counter.count_collision_probability())
+            ),
+            Statistic::CountHistogram => {
+                writeln!(out, "  {:<20}count sequences", "Count histogram:")?;
+                for (count, sequences) in counter.count_distribution() {
+                    writeln!(out, "  {:<20}{} {}", "", count, sequences)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Statistic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What one item of a plan turns on, or `None` to turn everything off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Selection {
+    One(Statistic),
+    All,
+    None,
+}
+
+/// Which statistics to compute for which lengths, as a comma-separated list of items
+/// applied in order. Each item is a statistic name, `all`, or `none`, optionally followed by
+/// `@N` or `@N-M` to apply it only to those lengths. Statistic names turn that statistic
+/// on, `all` turns every statistic on, and `none` turns every statistic off.
+///
+/// ```
+/// use digit_sequence_counter::statistics_plan::{Statistic, StatisticsPlan};
+///
+/// let plan: StatisticsPlan = "chi2,entropy@1-4,zscores@1-3,none@8".parse().unwrap();
+/// let resolved = plan.resolve(1..=8).unwrap();
+///
+/// assert_eq!(resolved.for_length(3), [Statistic::ChiSquare, Statistic::Entropy, Statistic::ZScores]);
+/// assert_eq!(resolved.for_length(6), [Statistic::ChiSquare]);
+/// assert!(resolved.for_length(8).is_empty());
+/// assert!("kurtosis".parse::<StatisticsPlan>().is_err());
+/// assert!(plan.resolve(1..=4).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatisticsPlan {
+    items: Vec<(Selection, Option<RangeInclusive<usize>>)>,
+}
+
+impl FromStr for StatisticsPlan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StatisticsPlan, String> {
+        let parse_length = |length: &str| match length.parse() {
+            Ok(0) | Err(_) => Err(format!("bad length {:?} in statistics plan", length)),
+            Ok(length) => Ok(length),
+        };
+
+        let mut items = Vec::new();
+        for item in s.split(',') {
+            let (name, lengths) = match item.split_once('@') {
+                Some((name, lengths)) => (name, Some(lengths)),
+                None => (item, None),
+            };
+            let selection = match name {
+                "all" => Selection::All,
+                "none" => Selection::None,
+                _ => match Statistic::ALL
+                    .iter()
+                    .find(|statistic| statistic.name() == name)
+                {
+                    Some(&statistic) => Selection::One(statistic),
+                    None => {
+                        return Err(format!(
+                            "unknown statistic {:?} (expected {}, all, or none)",
+                            name,
+                            Statistic::ALL
+                                .iter()
+                                .map(|statistic| statistic.name())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                    }
+                },
+            };
+            let lengths = match lengths {
+                Some(lengths) => {
+                    let (start, end) = match lengths.split_once('-') {
+                        Some((start, end)) => (parse_length(start)?, parse_length(end)?),
+                        None => {
+                            let length = parse_length(lengths)?;
+                            (length, length)
+                        }
+                    };
+                    if start > end {
+                        return Err(format!(
+                            "empty length range {:?} in statistics plan",
+                            lengths
+                        ));
+                    }
+                    Some(start..=end)
+                }
+                None => None,
+            };
+            items.push((selection, lengths));
+        }
+        Ok(StatisticsPlan { items })
+    }
+}
+
+impl Display for StatisticsPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (selection, lengths)) in self.items.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            match selection {
+                Selection::One(statistic) => write!(f, "{}", statistic)?,
+                Selection::All => f.write_str("all")?,
+                Selection::None => f.write_str("none")?,
+            }
+            if let Some(lengths) = lengths {
+                if lengths.start() == lengths.end() {
+                    write!(f, "@{}", lengths.start())?;
+                } else {
+                    write!(f, "@{}-{}", lengths.start(), lengths.end())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StatisticsPlan {
+    /// Works out the statistics for each of `sequence_lengths`, failing if an item names a
+    /// length outside them.
+    pub fn resolve(
+        &self,
+        sequence_lengths: RangeInclusive<usize>,
+    ) -> Result<ResolvedStatisticsPlan, String> {
+        let mut statistics = sequence_lengths
+            .clone()
+            .map(|sequence_length| (sequence_length, Vec::new()))
+            .collect::<BTreeMap<_, Vec<Statistic>>>();
+        for (selection, lengths) in &self.items {
+            let lengths = match lengths {
+                Some(lengths)
+                    if !sequence_lengths.contains(lengths.start())
+                        || !sequence_lengths.contains(lengths.end()) =>
+                {
+                    return Err(format!(
+                        "statistics plan names lengths {}-{}, but only {}-{} are counted",
+                        lengths.start(),
+                        lengths.end(),
+                        sequence_lengths.start(),
+                        sequence_lengths.end()
+                    ));
+                }
+                Some(lengths) => lengths.clone(),
+                None => sequence_lengths.clone(),
+            };
+            for sequence_length in lengths {
+                let chosen = statistics.get_mut(&sequence_length).unwrap();
+                match selection {
+                    Selection::One(statistic) => chosen.push(*statistic),
+                    Selection::All => chosen.extend_from_slice(&Statistic::ALL),
+                    Selection::None => chosen.clear(),
+                }
+                chosen.sort_unstable();
+                chosen.dedup();
+            }
+        }
+        Ok(ResolvedStatisticsPlan { statistics })
+    }
+}
+
+/// A `StatisticsPlan` worked out for a set of lengths.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedStatisticsPlan {
+    statistics: BTreeMap<usize, Vec<Statistic>>,
+}
+
+impl ResolvedStatisticsPlan {
+    /// Returns the statistics to compute for `sequence_length`, in `Statistic::ALL` order,
+    /// and none for lengths the plan wasn't resolved for.
+    pub fn for_length(&self, sequence_length: usize) -> &[Statistic] {
+        self.statistics
+            .get(&sequence_length)
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+impl Display for ResolvedStatisticsPlan {
+    /// Writes one `length N: names` line per length, with `none` for lengths without any.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (sequence_length, statistics)) in self.statistics.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "length {}: ", sequence_length)?;
+            if statistics.is_empty() {
+                f.write_str("none")?;
+            } else {
+                let names = statistics
+                    .iter()
+                    .map(|statistic| statistic.name())
+                    .collect::<Vec<_>>();
+                f.write_str(&names.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::mem;
+use std::str::FromStr;
+
+use crate::count_min::CountMin;
+use crate::hyperloglog::Sketch;
+
+/// How a `DigitSequenceCounter` stores its counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageMode {
+    /// One slot per possible sequence.
+    Dense,
+    /// A hash map holding only the sequences seen so far.
+    Sparse,
+    /// Starts sparse and converts to dense once the map would use more memory than the table.
+    Adaptive,
+    /// A HyperLogLog sketch that only estimates how many distinct sequences were seen.
+    HyperLogLog,
+    /// A count-min sketch that estimates each sequence's count, plus a HyperLogLog sketch
+    /// for the number of distinct sequences.
+    Sketch,
+}
+
+impl Display for StorageMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            StorageMode::Dense => "dense",
+            StorageMode::Sparse => "sparse",
+            StorageMode::Adaptive => "adaptive",
+            StorageMode::HyperLogLog => "hyperloglog",
+            StorageMode::Sketch => "sketch",
+        })
+    }
+}
+
+impl FromStr for StorageMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StorageMode, String> {
+        match s {
+            "dense" => Ok(StorageMode::Dense),
+            "sparse" => Ok(StorageMode::Sparse),
+            "adaptive" => Ok(StorageMode::Adaptive),
+            "hyperloglog" => Ok(StorageMode::HyperLogLog),
+            "sketch" => Ok(StorageMode::Sketch),
+            _ => Err(format!(
+                "unknown storage mode {:?} (expected dense, sparse, adaptive, hyperloglog, or sketch)",
+                s
+            )),
+        }
+    }
+}
+
+/// Records when an adaptive counter switched from sparse to dense storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageConversion {
+    pub distinct_sequences: usize,
+    pub sparse_bytes: usize,
+    pub dense_bytes: usize,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Counts {
+    Dense(Vec<u128>),
+    Sparse(HashMap<usize, u128>),
+    HyperLogLog(Sketch),
+    CountMin(CountMin, Sketch),
+}
+
+impl Counts {
+    pub(crate) fn new(mode: StorageMode, possible_sequences: usize) -> Counts {
+        match mode {
+            StorageMode::Dense => Counts::Dense(vec![0; possible_sequences]),
+            StorageMode::Sparse | StorageMode::Adaptive => Counts::Sparse(HashMap::new()),
+            StorageMode::HyperLogLog => Counts::HyperLogLog(Sketch::new(DEFAULT_SKETCH_BITS)),
+            StorageMode::Sketch => Counts::CountMin(
+                CountMin::new(DEFAULT_COUNT_MIN_WIDTH, DEFAULT_COUNT_MIN_DEPTH),
+                Sketch::new(DEFAULT_SKETCH_BITS),
+            ),
+        }
+    }
+
+    /// Returns the count at `index`, which is always 0 for a HyperLogLog sketch and an
+    /// overestimate for a count-min sketch.
+    pub(crate) fn get(&self, index: usize) -> u128 {
+        match self {
+            Counts::Dense(counts) => counts[index],
+            Counts::Sparse(counts) => counts.get(&index).copied().unwrap_or(0),
+            Counts::HyperLogLog(_) => 0,
+            Counts::CountMin(counts, _) => counts.estimate(index),
+        }
+    }
+
+    pub(crate) fn add(&mut self, index: usize, count: u128) {
+        match self {
+            Counts::Dense(counts) => counts[index] += count,
+            Counts::Sparse(counts) => *counts.entry(index).or_insert(0) += count,
+            Counts::HyperLogLog(sketch) => sketch.insert(index, count),
+            Counts::CountMin(counts, distinct) => {
+                counts.insert(index, count);
+                distinct.insert(index, count);
+            }
+        }
+    }
+
+    /// Takes one off the count at `index`, which must be nonzero, dropping a sparse entry
+    /// that reaches zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics for either sketch, which can't forget a sequence it was given.
+    pub(crate) fn remove_one(&mut self, index: usize) {
+        match self {
+            Counts::Dense(counts) => counts[index] -= 1,
+            Counts::Sparse(counts) => {
+                if let Some(count) = counts.get_mut(&index) {
+                    *count -= 1;
+                    if *count == 0 {
+                        counts.remove(&index);
+                    }
+                }
+            }
+            Counts::HyperLogLog(_) | Counts::CountMin(..) => {
+                panic!("Cannot remove a count from a sketch, which doesn't keep them exactly.")
+            }
+        }
+    }
+
+    /// Returns `(index, count)` for every nonzero count in ascending index order, which is
+    /// nothing for either sketch.
+    pub(crate) fn nonzero(&self) -> Vec<(usize, u128)> {
+        match self {
+            Counts::Dense(counts) => counts
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|&(_, count)| count != 0)
+                .collect(),
+            Counts::Sparse(counts) => {
+                let mut nonzero = counts
+                    .iter()
+                    .filter(|&(_, &count)| count != 0)
+                    .map(|(&index, &count)| (index, count))
+                    .collect::<Vec<_>>();
+                nonzero.sort_unstable_by_key(|&(index, _)| index);
+                nonzero
+            }
+            Counts::HyperLogLog(_) | Counts::CountMin(..) => Vec::new(),
+        }
+    }
+
+    /// Replaces every stored count with `f(index, count)`. Sequences absent from sparse storage
+    /// are not visited, so `f` must map a zero count to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics for either sketch, which has no exact counts to update.
+    pub(crate) fn update_stored<F: FnMut(usize, u128) -> u128>(&mut self, mut f: F) {
+        match self {
+            Counts::Dense(counts) => counts
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, count)| *count = f(index, *count)),
+            Counts::Sparse(counts) => counts.retain(|&index, count| {
+                *count = f(index, *count);
+                *count != 0
+            }),
+            Counts::HyperLogLog(_) | Counts::CountMin(..) => {
+                panic!("Cannot update the counts of a sketch, which doesn't keep them exactly.")
+            }
+        }
+    }
+
+    /// Replaces the count of every index in `0..possible_sequences` with `f(index, count)`,
+    /// storing the sparse entries that become nonzero. Unlike `update_stored`, this visits
+    /// every index whatever the storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics for either sketch, which has no exact counts to update.
+    pub(crate) fn update_all<F: FnMut(usize, u128) -> u128>(
+        &mut self,
+        possible_sequences: usize,
+        mut f: F,
+    ) {
+        match self {
+            Counts::Dense(_) => self.update_stored(f),
+            Counts::Sparse(counts) => {
+                for index in 0..possible_sequences {
+                    let count = counts.get(&index).copied().unwrap_or(0);
+                    match f(index, count) {
+                        0 => {
+                            counts.remove(&index);
+                        }
+                        count => {
+                            counts.insert(index, count);
+                        }
+                    }
+                }
+            }
+            Counts::HyperLogLog(_) | Counts::CountMin(..) => {
+                panic!("Cannot update the counts of a sketch, which doesn't keep them exactly.")
+            }
+        }
+    }
+
+    pub(crate) fn sparse_bytes(&self) -> Option<usize> {
+        match self {
+            Counts::Dense(_) | Counts::HyperLogLog(_) | Counts::CountMin(..) => None,
+            Counts::Sparse(counts) => {
+                Some(counts.capacity() * (mem::size_of::<(usize, u128)>() + 1))
+            }
+        }
+    }
+
+    /// Converts to dense storage in one pass over the stored keys and returns the table.
+    pub(crate) fn densify(&mut self, possible_sequences: usize) -> &mut Vec<u128> {
+        if let Counts::Sparse(sparse) = self {
+            let mut dense = vec![0; possible_sequences];
+            for (&index, &count) in sparse.iter() {
+                dense[index] = count;
+            }
+            *self = Counts::Dense(dense);
+        }
+        match self {
+            Counts::Dense(counts) => counts,
+            Counts::Sparse(_) | Counts::HyperLogLog(_) | Counts::CountMin(..) => {
+                unreachable!()
+            }
+        }
+    }
+}
+
+pub(crate) const DEFAULT_SKETCH_BITS: usize = 12;
+pub(crate) const DEFAULT_COUNT_MIN_WIDTH: usize = 1 << 16;
+pub(crate) const DEFAULT_COUNT_MIN_DEPTH: usize = 4;
+
+/// The bytes a dense table would take, saturating for the longest lengths, whose table
+/// couldn't be addressed anyway.
+pub(crate) fn dense_bytes(possible_sequences: usize) -> usize {
+    possible_sequences.saturating_mul(mem::size_of::<u128>())
+}
@@ -0,0 +1,187 @@
+use crate::DigitSequenceCounter;
+
+/// The first-seen offset of a sequence that hasn't been seen.
+const NEVER: u64 = u64::MAX;
+
+impl DigitSequenceCounter {
+    /// Creates a dense counter that also remembers the byte offset at which each sequence was
+    /// first seen, as given to `process_character_tracked`. An occurrence is placed at its
+    /// window's last byte. The offsets take another `8 * possible_sequences()` bytes.
+    ///
+    /// Only `process_character_tracked` records offsets, so counts from any other method or
+    /// merged in with `+=` have none. Transforms that move counts move their offsets along,
+    /// and those that clear a count clear its offset too.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new_with_tracking(2);
+    /// for (offset, &byte) in b"x31415 01492".iter().enumerate() {
+    ///     counter.process_character_tracked(byte, offset as u64);
+    /// }
+    ///
+    /// assert_eq!(counter.first_seen_offset(0x14), Some(3));
+    /// assert_eq!(counter.first_seen_offset(0x49), Some(10));
+    /// assert_eq!(counter.first_seen_offset(0x31), None);
+    /// assert_eq!(counter.count_at(0x14), 2);
+    ///
+    /// // 14, 41, 15, 14 again, 49, then 92.
+    /// assert_eq!(counter.byte_offset_of_nth_sequence(0), Some(3));
+    /// assert_eq!(counter.byte_offset_of_nth_sequence(3), Some(10));
+    /// assert_eq!(counter.byte_offset_of_nth_sequence(4), Some(11));
+    /// assert_eq!(counter.byte_offset_of_nth_sequence(5), None);
+    /// ```
+    pub fn new_with_tracking(sequence_length: usize) -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new(sequence_length);
+        counter.first_seen = Some(vec![NEVER; counter.possible_sequences()]);
+        counter
+    }
+
+    /// Like `process_character`, but records `offset` as where the completed sequence was
+    /// first seen if it hasn't been seen before. Offsets should increase along the input.
+    /// Counters created without `new_with_tracking` only count the character.
+    pub fn process_character_tracked(&mut self, character: u8, offset: u64) {
+        if self.shift_in(character) {
+            self.count_shifted_sequence();
+            if let Some(first_seen) = &mut self.first_seen {
+                let first_seen = &mut first_seen[self.current_sequence];
+                if *first_seen == NEVER {
+                    *first_seen = offset;
+                }
+            }
+        }
+    }
+
+    /// Clears the first-seen offset of every sequence whose count is 0, as after a transform
+    /// removed it.
+    pub(crate) fn forget_unseen_offsets(&mut self) {
+        if let Some(first_seen) = &mut self.first_seen {
+            for (sequence_index, offset) in first_seen.iter_mut().enumerate() {
+                if *offset != NEVER && self.sequence_counts.get(sequence_index) == 0 {
+                    *offset = NEVER;
+                }
+            }
+        }
+    }
+
+    /// Moves the first-seen offset of each sequence index `i` to `permutation(i)`, along with
+    /// its count.
+    pub(crate) fn permute_offsets<F: Fn(usize) -> usize>(&mut self, permutation: F) {
+        if let Some(first_seen) = &mut self.first_seen {
+            let mut permuted = vec![NEVER; first_seen.len()];
+            for (sequence_index, &offset) in first_seen.iter().enumerate() {
+                if offset != NEVER {
+                    permuted[permutation(sequence_index)] = offset;
+                }
+            }
+            *first_seen = permuted;
+        }
+    }
+
+    /// Returns the offset given to `process_character_tracked` for the first occurrence of
+    /// `sequence_index`, or `None` if it wasn't seen there or the counter doesn't track
+    /// offsets.
+    pub fn first_seen_offset(&self, sequence_index: usize) -> Option<u64> {
+        self.first_seen
+            .as_ref()
+            .map(|first_seen| first_seen[sequence_index])
+            .filter(|&offset| offset != NEVER)
+    }
+
+    /// Returns the offset at which the `n`th distinct sequence, counting from 0, was first
+    /// seen, or `None` if fewer than `n + 1` were or the counter doesn't track offsets. The
+    /// offsets of the first few show how quickly new sequences turn up.
+    pub fn byte_offset_of_nth_sequence(&self, n: usize) -> Option<u64> {
+        let mut offsets = self
+            .first_seen
+            .as_ref()?
+            .iter()
+            .copied()
+            .filter(|&offset| offset != NEVER)
+            .collect::<Vec<_>>();
+        if n >= offsets.len() {
+            return None;
+        }
+        Some(*offsets.select_nth_unstable(n).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DigitSequenceCounter;
+
+    /// Tracks `x31415 01492`, which sees 14 at offset 3, 41 at 4, 15 at 5, 49 at 10, and 92
+    /// at 11, with 14 seen again at 9.
+    fn tracked() -> DigitSequenceCounter {
+        let mut counter = DigitSequenceCounter::new_with_tracking(2);
+        for (offset, &byte) in b"x31415 01492".iter().enumerate() {
+            counter.process_character_tracked(byte, offset as u64);
+        }
+        counter
+    }
+
+    fn offsets(counter: &DigitSequenceCounter) -> Vec<(usize, u64)> {
+        (0..counter.possible_sequences())
+            .filter_map(|sequence_index| {
+                counter
+                    .first_seen_offset(sequence_index)
+                    .map(|offset| (sequence_index, offset))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn permutations_move_offsets_with_counts() {
+        let counter = tracked();
+        assert_eq!(
+            offsets(&counter.sequence_reverse()),
+            vec![(0x14, 4), (0x29, 11), (0x41, 3), (0x51, 5), (0x94, 10)]
+        );
+        assert_eq!(
+            offsets(&counter.sequence_complement()),
+            vec![(0x6D, 11), (0xB6, 10), (0xBE, 4), (0xEA, 5), (0xEB, 3)]
+        );
+        assert_eq!(counter.sequence_reverse().sequence_reverse(), counter);
+        assert_eq!(
+            offsets(&counter.sequence_reverse().sequence_reverse()),
+            offsets(&counter)
+        );
+    }
+
+    #[test]
+    fn cleared_counts_clear_offsets() {
+        let counter = tracked();
+
+        let mut mask = vec![1; counter.possible_sequences()];
+        mask[0x41] = 0;
+        let masked = counter.clone().with_mask(&mask);
+        assert_eq!(
+            offsets(&masked),
+            vec![(0x14, 3), (0x15, 5), (0x49, 10), (0x92, 11)]
+        );
+
+        let mut transformed = counter.clone();
+        transformed.apply_transform(
+            |sequence_index, count| {
+                if sequence_index >> 4 == 1 {
+                    0
+                } else {
+                    count
+                }
+            },
+        );
+        assert_eq!(
+            offsets(&transformed),
+            vec![(0x41, 4), (0x49, 10), (0x92, 11)]
+        );
+        assert_eq!(transformed.byte_offset_of_nth_sequence(0), Some(4));
+
+        let (even, odd) = counter.split_even_odd();
+        assert_eq!(offsets(&even), vec![(0x14, 3), (0x92, 11)]);
+        assert_eq!(offsets(&odd), vec![(0x15, 5), (0x41, 4), (0x49, 10)]);
+
+        let downsampled = counter.downsample(1).unwrap();
+        assert_eq!(offsets(&downsampled), vec![(0x14, 3)]);
+        assert_eq!(downsampled.byte_offset_of_nth_sequence(1), None);
+    }
+}
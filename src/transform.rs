@@ -0,0 +1,260 @@
+use crate::{DigitSequenceCounter, StorageMode};
+
+impl DigitSequenceCounter {
+    /// Multiplies each count by the mask value at the same sequence index, saturating at
+    /// `u128::MAX`. A mask value of 0 clears a count and 1 leaves it unchanged.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x3141592653".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let ones = vec![1; counter.possible_sequences()];
+    /// assert_eq!(counter.clone().with_mask(&ones), counter);
+    ///
+    /// let zeros = vec![0; counter.possible_sequences()];
+    /// let cleared = counter.clone().with_mask(&zeros);
+    /// assert_eq!(cleared.total_count(), 0);
+    /// assert_eq!(cleared, DigitSequenceCounter::new(1));
+    ///
+    /// let mut mask = vec![1; counter.possible_sequences()];
+    /// mask[0x1] = u128::MAX;
+    /// mask[0x5] = 3;
+    /// let masked = counter.with_mask(&mask);
+    /// assert_eq!((masked.count_at(0x1), masked.count_at(0x5)), (u128::MAX, 6));
+    /// assert_eq!(masked.count_at(0x9), 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask.len()` differs from `possible_sequences()`.
+    pub fn apply_mask(&mut self, mask: &[u128]) {
+        if mask.len() != self.possible_sequences() {
+            panic!(
+                "Cannot apply a mask of length {} to a DigitSequenceCounter with {} possible sequences.",
+                mask.len(),
+                self.possible_sequences()
+            );
+        }
+        self.sequence_counts
+            .update_stored(|sequence_index, count| count.saturating_mul(mask[sequence_index]));
+        self.rebuild_seen();
+    }
+
+    pub fn with_mask(mut self, mask: &[u128]) -> DigitSequenceCounter {
+        self.apply_mask(mask);
+        self
+    }
+
+    /// Replaces the count of every possible sequence with `f(sequence_index, count)`, seen or
+    /// not, whatever the storage. This takes time proportional to `possible_sequences()` even
+    /// for sparse storage, which stores the sequences `f` gives a nonzero count and may then
+    /// become dense if adaptive.
+    ///
+    /// ```
+    /// use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x3141592653".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// counter.apply_transform(|i, c| if i % 2 == 0 { c } else { 0 });
+    /// assert_eq!(counter.nonzero_counts(), vec![(2, 1), (4, 1), (6, 1)]);
+    ///
+    /// for storage_mode in [StorageMode::Dense, StorageMode::Sparse, StorageMode::Adaptive] {
+    ///     let mut counter = DigitSequenceCounter::with_storage(2, storage_mode);
+    ///     b"x3141".iter().for_each(|&byte| counter.process_character(byte));
+    ///     counter.apply_transform(|_, c| c + 3);
+    ///     assert_eq!(counter.total_count(), 256 * 3 + 2);
+    ///     assert_eq!((counter.count_at(0x14), counter.count_at(0xFF)), (4, 3));
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn apply_transform<F: Fn(usize, u128) -> u128>(&mut self, f: F) {
+        let possible_sequences = self.possible_sequences();
+        self.sequence_counts.update_all(possible_sequences, f);
+        self.rebuild_seen();
+        if self.storage_mode == StorageMode::Adaptive {
+            self.densify_if_profitable();
+        }
+    }
+
+    /// Splits the counts by the parity of the sequence index: the first counter keeps only
+    /// the even indices and the second only the odd ones. Their totals add up to this
+    /// counter's total.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x3141592653".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let (even, odd) = counter.split_even_odd();
+    /// assert_eq!(even.total_count() + odd.total_count(), counter.total_count());
+    /// assert_eq!((even.total_count(), odd.total_count()), (3, 6));
+    /// assert_eq!(even.nonzero_counts(), vec![(2, 1), (4, 1), (6, 1)]);
+    ///
+    /// let mut uniform = DigitSequenceCounter::new(2);
+    /// uniform.apply_transform(|_, _| 3);
+    /// let (even, odd) = uniform.split_even_odd();
+    /// assert_eq!(even.total_count(), odd.total_count());
+    /// assert_eq!(even.total_count() + odd.total_count(), uniform.total_count());
+    /// ```
+    pub fn split_even_odd(&self) -> (DigitSequenceCounter, DigitSequenceCounter) {
+        let mut even = self.clone();
+        even.sequence_counts
+            .update_stored(|sequence_index, count| if sequence_index & 1 == 0 { count } else { 0 });
+        even.rebuild_seen();
+
+        let mut odd = self.clone();
+        odd.sequence_counts
+            .update_stored(|sequence_index, count| if sequence_index & 1 == 1 { count } else { 0 });
+        odd.rebuild_seen();
+
+        (even, odd)
+    }
+
+    /// Returns a copy of this counter with every sequence replaced by its complement, which
+    /// XORs each of its digits with F, so the count of `i` moves to
+    /// `i ^ (possible_sequences() - 1)`. Applying it twice gives back the original counts.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x31415926535".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let complement = counter.sequence_complement();
+    /// assert_eq!(complement.count_at(0xCE), counter.count_at(0x31));
+    /// assert_eq!(complement.count_at(0xA6), counter.count_at(0x59));
+    /// assert_eq!(complement.total_count(), counter.total_count());
+    /// assert_eq!(complement.sequence_complement(), counter);
+    ///
+    /// let mut uniform = DigitSequenceCounter::new(2);
+    /// uniform.apply_transform(|_, _| 3);
+    /// assert_eq!(uniform.sequence_complement(), uniform);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn sequence_complement(&self) -> DigitSequenceCounter {
+        let mask = self.possible_sequences() - 1;
+        self.permute_sequences(|sequence_index| sequence_index ^ mask)
+    }
+
+    /// Returns a copy of this counter with the digits of every sequence in reverse order, so
+    /// the count of 1234 moves to 4321. Applying it twice gives back the original counts.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(4);
+    /// b"x31415926535".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let reverse = counter.sequence_reverse();
+    /// assert_eq!(reverse.count_at(0x5141), counter.count_at(0x1415));
+    /// assert_eq!(reverse.count_at(0x5356), counter.count_at(0x6535));
+    /// assert_eq!(reverse.sequence_reverse(), counter);
+    /// assert!(!counter.is_palindrome_symmetric());
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"x31415926535".iter().for_each(|&byte| counter.process_character(byte));
+    /// assert_eq!(counter.sequence_reverse(), counter);
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x012321".iter().for_each(|&byte| counter.process_character(byte));
+    /// assert!(counter.is_palindrome_symmetric());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn sequence_reverse(&self) -> DigitSequenceCounter {
+        let sequence_length = self.sequence_length;
+        self.permute_sequences(|sequence_index| reverse_nibbles(sequence_index, sequence_length))
+    }
+
+    /// Returns whether every sequence is counted as often as its reverse, so the counts read
+    /// the same from either end.
+    ///
+    /// # Panics
+    ///
+    /// Panics for sketching counters, which don't keep exact counts.
+    pub fn is_palindrome_symmetric(&self) -> bool {
+        *self == self.sequence_reverse()
+    }
+
+    /// Returns a copy of this counter with the count of each sequence index `i` moved to
+    /// `permutation(i)`, which must be one-to-one.
+    fn permute_sequences<F: Fn(usize) -> usize>(&self, permutation: F) -> DigitSequenceCounter {
+        let nonzero_counts = self.nonzero_counts();
+        let mut permuted = self.clone();
+        permuted.sequence_counts.update_stored(|_, _| 0);
+        for (sequence_index, count) in nonzero_counts {
+            permuted
+                .sequence_counts
+                .add(permutation(sequence_index), count);
+        }
+        permuted.permute_offsets(&permutation);
+        permuted.rebuild_seen();
+        permuted
+    }
+
+    /// Sums the counts into `m` buckets by residue, so bucket `r` holds the counts of every
+    /// sequence index congruent to `r` modulo `m`. Since `m` must divide `16^sequence_length`,
+    /// folding by `16^k` groups sequences by their last `k` digits. Grouping by a prefix, the
+    /// leading digits, is folding the `sequence_reverse` instead, so folding by 16 doesn't
+    /// match grouping by the first digit unless the counts are palindrome-symmetric.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x1213F3".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let by_last_digit = counter.fold_counts_by_modulus(16);
+    /// assert_eq!((by_last_digit[1], by_last_digit[3], by_last_digit[0xF]), (1, 2, 1));
+    /// assert_eq!(counter.fold_counts_by_modulus(1), vec![counter.total_count()]);
+    ///
+    /// let counts = (0..counter.possible_sequences())
+    ///     .map(|sequence_index| counter.count_at(sequence_index))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(counter.fold_counts_by_modulus(counter.possible_sequences()), counts);
+    ///
+    /// let mut by_first_digit = vec![0; 16];
+    /// for (sequence_index, count) in counter.nonzero_counts() {
+    ///     by_first_digit[sequence_index >> 4] += count;
+    /// }
+    /// assert_eq!(counter.sequence_reverse().fold_counts_by_modulus(16), by_first_digit);
+    /// assert_ne!(by_last_digit, by_first_digit);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` doesn't evenly divide `possible_sequences()`.
+    pub fn fold_counts_by_modulus(&self, m: usize) -> Vec<u128> {
+        if m == 0 || !self.possible_sequences().is_multiple_of(m) {
+            panic!(
+                "Cannot fold {} possible sequences into {} buckets; the count must divide them evenly.",
+                self.possible_sequences(),
+                m
+            );
+        }
+        let mut buckets = vec![0; m];
+        for (sequence_index, count) in self.nonzero_counts() {
+            buckets[sequence_index % m] += count;
+        }
+        buckets
+    }
+}
+
+/// Returns the lowest `digits` hex digits of `sequence_index` in reverse order.
+fn reverse_nibbles(sequence_index: usize, digits: usize) -> usize {
+    (0..digits).fold(0, |reversed, digit| {
+        reversed << 4 | (sequence_index >> (4 * digit) & 0xF)
+    })
+}
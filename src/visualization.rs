@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use crate::DigitSequenceCounter;
+
+impl DigitSequenceCounter {
+    /// Returns a permutation of the sequence indices ordered by ascending count, so that
+    /// `counts[perm[i]]` is non-decreasing. Ties keep their original index order.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(1);
+    /// b"3141592653".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let perm = counter.histogram_equalization();
+    /// assert_eq!(perm.len(), 16);
+    /// assert_eq!(perm[15], 5);
+    /// ```
+    pub fn histogram_equalization(&self) -> Vec<usize> {
+        let mut perm = (0..self.possible_sequences()).collect::<Vec<_>>();
+        perm.sort_by_key(|&index| self.count_at(index));
+        perm
+    }
+
+    /// Reorders `data` so that element `i` of the result is `data[perm[i]]`.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let counter = DigitSequenceCounter::new(1);
+    /// let labels = ["zero", "one", "two"];
+    /// assert_eq!(
+    ///     counter.apply_permutation(&labels, &[2, 0, 1]),
+    ///     vec!["two", "zero", "one"]
+    /// );
+    /// ```
+    pub fn apply_permutation<T: Clone>(&self, data: &[T], perm: &[usize]) -> Vec<T> {
+        perm.iter().map(|&index| data[index].clone()).collect()
+    }
+
+    /// The longest sequence length `write_flamegraph_data` accepts.
+    pub const FLAMEGRAPH_LENGTH_LIMIT: usize = 4;
+
+    /// Writes the nonzero counts in the collapsed stack format that flamegraph tools like
+    /// `inferno-flamegraph` read, one `D1;D2;...;DL count` line per sequence in index order, so
+    /// the top level is the leading digit and each leaf is as wide as its sequence's count.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(3);
+    /// b"x3141F3141".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// let mut stacks = Vec::new();
+    /// counter.write_flamegraph_data(&mut stacks).unwrap();
+    /// assert_eq!(stacks, b"1;4;1 2\n1;F;3 1\n3;1;4 1\n4;1;F 1\nF;3;1 1\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequence length is 0 or greater than `FLAMEGRAPH_LENGTH_LIMIT`.
+    pub fn write_flamegraph_data<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.sequence_length == 0 || self.sequence_length > Self::FLAMEGRAPH_LENGTH_LIMIT {
+            panic!(
+                "Cannot write flamegraph data for a sequence length outside 1 to {}.",
+                Self::FLAMEGRAPH_LENGTH_LIMIT
+            );
+        }
+        for (sequence_index, count) in self.nonzero_counts() {
+            let label = format!("{:0width$X}", sequence_index, width = self.sequence_length);
+            let stack = label
+                .chars()
+                .map(String::from)
+                .collect::<Vec<_>>()
+                .join(";");
+            writeln!(writer, "{} {}", stack, count)?;
+        }
+        Ok(())
+    }
+
+    /// The longest sequence length `write_dot_graph` accepts, whose graph has up to 4096
+    /// nodes.
+    pub const DOT_GRAPH_LENGTH_LIMIT: usize = 4;
+
+    /// Writes the counts as a Graphviz DOT digraph of transitions: a node for each hex string
+    /// of `sequence_length - 1` digits, and for each sequence counted at least `min_count`
+    /// times, an edge from its first `sequence_length - 1` digits to its last, labeled
+    /// `SEQUENCE: count` and weighted by the count. Only nodes with an edge are written, in
+    /// index order. Each node's width is proportional to the total count of the sequences it
+    /// starts, up to 2 inches for the largest, and at least 0.1 so every node stays visible.
+    ///
+    /// ```
+    /// use digit_sequence_counter::DigitSequenceCounter;
+    ///
+    /// let mut counter = DigitSequenceCounter::new(2);
+    /// b"x31414F14".iter().for_each(|&byte| counter.process_character(byte));
+    ///
+    /// // 14 is counted 3 times, and 41, 4F, and F1 once each.
+    /// let mut dot = Vec::new();
+    /// counter.write_dot_graph(&mut dot, 2).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(dot).unwrap(),
+    ///     "digraph transitions {
+    ///     node [shape=circle, fixedsize=true];
+    ///     \"1\" [width=2.000];
+    ///     \"4\" [width=1.333];
+    ///     \"1\" -> \"4\" [label=\"14: 3\", weight=3];
+    /// }
+    /// "
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequence length is below 2 or greater than `DOT_GRAPH_LENGTH_LIMIT`.
+    pub fn write_dot_graph<W: Write>(&self, mut writer: W, min_count: u128) -> io::Result<()> {
+        if self.sequence_length < 2 || self.sequence_length > Self::DOT_GRAPH_LENGTH_LIMIT {
+            panic!(
+                "Cannot write a transition graph for a sequence length outside 2 to {}.",
+                Self::DOT_GRAPH_LENGTH_LIMIT
+            );
+        }
+        let node_length = self.sequence_length - 1;
+        let node_mask = (1 << (node_length << 2)) - 1;
+        let nonzero_counts = self.nonzero_counts();
+        let mut outgoing = BTreeMap::new();
+        for &(sequence_index, count) in &nonzero_counts {
+            *outgoing.entry(sequence_index >> 4).or_insert(0) += count;
+        }
+        let edges = nonzero_counts
+            .into_iter()
+            .filter(|&(_, count)| count >= min_count)
+            .collect::<Vec<_>>();
+        let nodes = edges
+            .iter()
+            .flat_map(|&(sequence_index, _)| [sequence_index >> 4, sequence_index & node_mask])
+            .collect::<BTreeSet<_>>();
+        let largest = outgoing.values().copied().max().unwrap_or(0);
+        let node_label = |node: usize| format!("{:0width$X}", node, width = node_length);
+
+        writeln!(writer, "digraph transitions {{")?;
+        writeln!(writer, "    node [shape=circle, fixedsize=true];")?;
+        for node in nodes {
+            let weight = outgoing.get(&node).copied().unwrap_or(0);
+            let width = (2.0 * weight as f64 / largest as f64).max(0.1);
+            writeln!(writer, "    \"{}\" [width={:.3}];", node_label(node), width)?;
+        }
+        for (sequence_index, count) in edges {
+            writeln!(
+                writer,
+                "    \"{}\" -> \"{}\" [label=\"{:0width$X}: {}\", weight={}];",
+                node_label(sequence_index >> 4),
+                node_label(sequence_index & node_mask),
+                sequence_index,
+                count,
+                count,
+                width = self.sequence_length
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+}
@@ -0,0 +1,231 @@
+//! Runs the command-line tool the way its users do and checks what it reports.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the binary with `args`, feeding it `stdin`.
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_whatever"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // A run that fails early may close its input before reading all of it.
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().unwrap()
+}
+
+/// Returns the `DSC-RESULT` trailer, the last line of standard error.
+fn trailer(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[test]
+fn two_sample_rejects_standard_input_as_both_samples() {
+    let output = run(&["-", "2", "--two-sample", "-"], b"3.14159265358979");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--two-sample can't read standard input"));
+    assert!(trailer(&output).starts_with("DSC-RESULT status=usage "));
+}
+
+/// 2000 digits of pi after `3.`, on one line.
+const PI_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pi_2000.txt");
+
+/// Returns the value of the reproducibility block's `# name:` line in a report.
+fn block_line(output: &Output, name: &str) -> Option<String> {
+    let prefix = format!("# {}: ", name);
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix).map(String::from))
+}
+
+#[test]
+fn reproducibility_block_names_version_and_commit() {
+    let output = run(&[PI_FIXTURE, "1"], b"");
+    assert!(output.status.success());
+    assert_eq!(
+        block_line(&output, "version").as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+    assert_eq!(
+        block_line(&output, "commit").as_deref(),
+        digit_sequence_counter::reproducibility::COMMIT
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn content_hash_is_the_same_on_every_input_path() {
+    const PI_FIXTURE_HASH: &str =
+        "blake3:8fc03a5cb1a2fc3adccd1d9e596c6470f431852021d302292416b38432c1e8fd";
+
+    let fixture = std::fs::read(PI_FIXTURE).unwrap();
+    let buffered = run(&[PI_FIXTURE, "2"], b"");
+    let threaded = run(
+        &[PI_FIXTURE, "2", "--threads", "4", "--chunk-size", "100"],
+        b"",
+    );
+    let stdin = run(&["-", "2"], &fixture);
+    for output in &[buffered, threaded, stdin] {
+        assert!(output.status.success(), "{}", trailer(output));
+        assert_eq!(
+            block_line(output, "input_hash").as_deref(),
+            Some(PI_FIXTURE_HASH)
+        );
+    }
+}
+
+#[test]
+fn trailer_reports_a_successful_run() {
+    let output = run(&[PI_FIXTURE, "2"], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        trailer(&output).split(" elapsed=").next(),
+        Some("DSC-RESULT status=ok input_bytes=2003 lengths=1-2")
+    );
+    assert!(trailer(&output).ends_with(" output=stdout"));
+}
+
+#[test]
+fn trailer_reports_a_missing_file() {
+    let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing.txt");
+    let output = run(&[missing, "2"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bad given file path"));
+    assert_eq!(
+        trailer(&output).split(" elapsed=").next(),
+        Some("DSC-RESULT status=usage input_bytes=0 lengths=none")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn trailer_reports_an_interrupted_run() {
+    use std::thread;
+    use std::time::Duration;
+
+    // Standard input stays open, so the run waits for more digits until interrupted.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_whatever"))
+        .args(["-", "2", "--flush-interval", "0.1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"3.14159265358979").unwrap();
+    thread::sleep(Duration::from_millis(500));
+    let interrupted = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(interrupted.success());
+
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+    assert_eq!(output.status.code(), Some(130));
+    assert!(trailer(&output).starts_with("DSC-RESULT status=interrupted input_bytes="));
+}
+
+/// Returns a report without its reproducibility block, which names the options.
+fn counts_section(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("# "))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn multiple_passes_count_the_same_as_one() {
+    let single = run(&[PI_FIXTURE, "4", "--summary"], b"");
+    assert!(single.status.success());
+    for passes in &[
+        &["--passes", "3"][..],
+        &["--passes", "4"][..],
+        &["--passes", "auto", "--max-memory", "1100000"][..],
+    ] {
+        let args = [&[PI_FIXTURE, "4", "--summary"][..], passes].concat();
+        let multiple = run(&args, b"");
+        assert!(multiple.status.success(), "{}", trailer(&multiple));
+        assert!(String::from_utf8_lossy(&multiple.stderr).contains("Pass 2/"));
+        assert_eq!(counts_section(&multiple), counts_section(&single));
+    }
+}
+
+#[test]
+fn verify_checksum_accepts_the_fixture_and_rejects_another_digest() {
+    let good = "sha256:e8b47004670d0934ae79bd51e995a9fb8d48f9228049fc6c46c568aa52f31d25";
+    let output = run(&[PI_FIXTURE, "1", "--verify-checksum", good], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(trailer(&output).starts_with("DSC-RESULT status=ok "));
+
+    let bad = "md5:00000000000000000000000000000000";
+    for extra in [&[][..], &["--verify-first"][..]] {
+        let mut args = vec![PI_FIXTURE, "1", "--verify-checksum", bad];
+        args.extend_from_slice(extra);
+        let output = run(&args, b"");
+        assert_eq!(output.status.code(), Some(5));
+        assert!(trailer(&output).starts_with("DSC-RESULT status=checksum_mismatch "));
+    }
+}
+
+/// The start of a PNG file: its signature and the header of its first chunk.
+const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0\0\0\x01\0\x08\x06\0\0\0";
+
+#[test]
+fn sniff_warns_about_binary_input_but_not_digits() {
+    let output = run(&["-", "1"], PNG_HEADER);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: input does not look like a digit file"));
+    assert!(stderr.contains("looks like binary data"));
+
+    let output = run(&["-", "1", "--strict-sniff"], PNG_HEADER);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(trailer(&output).starts_with("DSC-RESULT status=not_digits "));
+
+    let output = run(&[PI_FIXTURE, "1", "--strict-sniff"], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("does not look like"));
+}
+
+#[test]
+fn exact_count_options_are_rejected_with_sketches() {
+    let mut options: Vec<&[&str]> = vec![
+        &["--flamegraph"],
+        &["--transition-matrix"],
+        &["--dot-graph", "1", "--output", "graph.dot"],
+        &["--independence-test", "1"],
+        &["--periodicity"],
+        &["--two-sample", PI_FIXTURE],
+        &["--outliers", "3"],
+    ];
+    if cfg!(feature = "image") {
+        options.push(&["--image", "counts.png"]);
+    }
+    for storage in ["hyperloglog", "sketch"] {
+        for option in &options {
+            let mut args = vec![PI_FIXTURE, "2", "--storage", storage];
+            args.extend_from_slice(option);
+            let output = run(&args, b"");
+            assert_eq!(output.status.code(), Some(1), "{:?}", args);
+            let expected = format!(
+                "{} needs exact counts, which --storage {} doesn't keep",
+                option[0], storage
+            );
+            assert!(
+                String::from_utf8_lossy(&output.stderr).contains(&expected),
+                "{:?}",
+                args
+            );
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Checks how `count_in_chunks` shares uneven input between its workers.
+
+use digit_sequence_counter::parallel::{self, WorkerProgress};
+use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+
+/// Input whose cost is skewed to the front: long digit runs, which every length counts,
+/// followed by as many bytes of short runs broken up by letters that aren't digits.
+fn skewed_input() -> Vec<u8> {
+    let mut state = 314_159u32;
+    let mut next_digit = || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        b"0123456789ABCDEF"[(state >> 16) as usize % 16]
+    };
+    let mut input = b"3.".to_vec();
+    input.extend((0..100_000).map(|_| next_digit()));
+    for _ in 0..25_000 {
+        input.extend_from_slice(&[next_digit(), next_digit(), b'x', b'y']);
+    }
+    input
+}
+
+#[test]
+fn no_worker_does_most_of_a_skewed_input() {
+    let input = skewed_input();
+    let workers = (0..4).map(|_| WorkerProgress::new()).collect::<Vec<_>>();
+    let counters =
+        parallel::count_in_chunks(&input[..], 1..=6, StorageMode::Sparse, 1024, &workers).unwrap();
+
+    let mut expected = (1..=6)
+        .map(|sequence_length| {
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse)
+        })
+        .collect::<Vec<_>>();
+    for counter in &mut expected {
+        input[2..]
+            .iter()
+            .for_each(|&byte| counter.process_character(byte));
+    }
+    assert_eq!(counters, expected);
+
+    let chunks = workers.iter().map(WorkerProgress::chunks).sum::<u64>();
+    let bytes = workers.iter().map(WorkerProgress::bytes).sum::<u64>();
+    assert_eq!(bytes, input.len() as u64 - 2);
+    for (worker, progress) in workers.iter().enumerate() {
+        assert!(
+            progress.bytes() * 2 < bytes,
+            "worker {} counted {} of {} bytes in {} of {} chunks",
+            worker,
+            progress.bytes(),
+            bytes,
+            progress.chunks(),
+            chunks
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Bounds the error of the sketching counters against exact sparse counts of the same input.
+
+use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+
+/// Pseudo-random hex digits after a `.`, the same on every run.
+fn random_hex_digits(len: usize) -> Vec<u8> {
+    let mut state = 271_828u64;
+    let mut input = b".".to_vec();
+    input.extend((0..len).map(|_| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        b"0123456789ABCDEF"[(state >> 60) as usize]
+    }));
+    input
+}
+
+fn count(mut counter: DigitSequenceCounter, input: &[u8]) -> DigitSequenceCounter {
+    input
+        .iter()
+        .for_each(|&byte| counter.process_character(byte));
+    counter
+}
+
+#[test]
+fn hyperloglog_unique_count_is_within_four_standard_errors() {
+    let input = random_hex_digits(200_000);
+    for &(sequence_length, sketch_bits) in &[(3, 12), (6, 12), (6, 16), (8, 10)] {
+        let exact = count(
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse),
+            &input,
+        );
+        let sketch = count(
+            DigitSequenceCounter::new_hyperloglog(sequence_length, sketch_bits),
+            &input,
+        );
+        let relative_error = sketch.unique_count_relative_error().unwrap();
+        let error = (sketch.unique_count() as f64 - exact.unique_count() as f64).abs()
+            / exact.unique_count() as f64;
+        assert!(
+            error <= 4.0 * relative_error,
+            "length {} with {} bits: {} estimated for {} unique",
+            sequence_length,
+            sketch_bits,
+            sketch.unique_count(),
+            exact.unique_count()
+        );
+        assert_eq!(sketch.total_count(), exact.total_count());
+    }
+}
+
+#[test]
+fn count_min_estimates_are_never_low_and_rarely_past_the_bound() {
+    let input = random_hex_digits(200_000);
+    for &(sequence_length, width, depth) in &[(3, 256, 4), (5, 4096, 4), (6, 1 << 14, 2)] {
+        let exact = count(
+            DigitSequenceCounter::with_storage(sequence_length, StorageMode::Sparse),
+            &input,
+        );
+        let sketch = count(
+            DigitSequenceCounter::new_count_min(sequence_length, width, depth),
+            &input,
+        );
+        let (bound, probability) = sketch.count_error_bound().unwrap();
+        let counts = exact.nonzero_counts();
+        let mut past_bound = 0;
+        for &(sequence_index, count) in &counts {
+            let estimate = sketch.count_at(sequence_index);
+            assert!(estimate >= count);
+            if (estimate - count) as f64 > bound {
+                past_bound += 1;
+            }
+        }
+        // Each estimate is within the bound with `probability`; allow twice the misses.
+        let allowed = 2.0 * (1.0 - probability) * counts.len() as f64;
+        assert!(
+            past_bound as f64 <= allowed,
+            "length {} at {}x{}: {} of {} estimates past the bound of {}",
+            sequence_length,
+            width,
+            depth,
+            past_bound,
+            counts.len(),
+            bound
+        );
+        assert_eq!(sketch.total_count(), exact.total_count());
+    }
+}
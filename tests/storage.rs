@@ -0,0 +1,101 @@
+//! Checks that adaptive storage counts the same as pure dense and pure sparse storage on
+//! either side of its switch to a dense table.
+
+use digit_sequence_counter::{DigitSequenceCounter, StorageMode};
+
+const MODES: [StorageMode; 3] = [
+    StorageMode::Dense,
+    StorageMode::Sparse,
+    StorageMode::Adaptive,
+];
+
+/// `len` pseudo-random hex digits after a `3.`.
+fn hex_digits(len: usize) -> Vec<u8> {
+    let mut state = 271_828u32;
+    let mut input = b"3.".to_vec();
+    input.extend((0..len).map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        b"0123456789ABCDEF"[(state >> 16) as usize % 16]
+    }));
+    input
+}
+
+/// Counts `input` with sequences of length 3 in each of `MODES`.
+fn count_in_every_mode(input: &[u8]) -> Vec<DigitSequenceCounter> {
+    MODES
+        .iter()
+        .map(|&storage_mode| {
+            let mut counter = DigitSequenceCounter::with_storage(3, storage_mode);
+            counter.process_bytes(input);
+            counter
+        })
+        .collect()
+}
+
+/// Asserts that every counter has the same counts, merges to the same counts, and
+/// serializes to the same text that reads back to those counts.
+fn assert_equivalent(counters: &[DigitSequenceCounter]) {
+    let expected = &counters[0];
+    for counter in counters {
+        assert!((0..expected.possible_sequences())
+            .all(|sequence_index| counter.count_at(sequence_index)
+                == expected.count_at(sequence_index)));
+        assert_eq!(counter.total_count(), expected.total_count());
+        assert_eq!(counter.unique_count(), expected.unique_count());
+        assert_eq!(counter.serialize_to_hex(), expected.serialize_to_hex());
+        assert_eq!(
+            &DigitSequenceCounter::deserialize_from_hex(&counter.serialize_to_hex()).unwrap(),
+            expected
+        );
+
+        let mut doubled = expected.clone();
+        doubled += expected;
+        for other in counters {
+            let mut merged = counter.clone();
+            merged += other;
+            assert_eq!(merged, doubled);
+        }
+    }
+}
+
+#[test]
+fn adaptive_matches_dense_and_sparse_before_converting() {
+    let counters = count_in_every_mode(&hex_digits(200));
+    let adaptive = &counters[2];
+    assert_eq!(adaptive.storage_conversion(), None);
+    assert!(!adaptive.is_dense());
+    assert_equivalent(&counters);
+}
+
+#[test]
+fn adaptive_matches_dense_and_sparse_after_converting() {
+    let counters = count_in_every_mode(&hex_digits(100_000));
+    let adaptive = &counters[2];
+    assert!(adaptive.is_dense());
+    let conversion = adaptive.storage_conversion().unwrap();
+    assert_eq!(conversion.dense_bytes, 4096 * 16);
+    assert!(conversion.sparse_bytes >= conversion.dense_bytes);
+    assert!(conversion.distinct_sequences < 4096);
+    assert!(!counters[1].is_dense());
+    assert_equivalent(&counters);
+}
+
+#[test]
+fn merging_into_adaptive_converts_it() {
+    let full = count_in_every_mode(&hex_digits(100_000));
+    let mut adaptive = DigitSequenceCounter::with_storage(3, StorageMode::Adaptive);
+    adaptive.process_bytes(&hex_digits(200));
+    assert!(!adaptive.is_dense());
+
+    adaptive += &full[0];
+    assert!(adaptive.is_dense());
+    assert!(adaptive.storage_conversion().is_some());
+    let mut expected = count_in_every_mode(&hex_digits(200)).remove(0);
+    expected += &full[0];
+    assert_eq!(adaptive, expected);
+
+    let deserialized =
+        DigitSequenceCounter::deserialize_from_hex(&full[0].serialize_to_hex()).unwrap();
+    assert!(deserialized.is_dense());
+    assert_eq!(deserialized, full[0]);
+}